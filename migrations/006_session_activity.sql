@@ -0,0 +1,8 @@
+-- Track where and when each session is used
+ALTER TABLE sessions ADD COLUMN user_agent TEXT;
+ALTER TABLE sessions ADD COLUMN ip_address VARCHAR(45);
+ALTER TABLE sessions ADD COLUMN last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX idx_sessions_last_seen_at ON sessions(last_seen_at);
+
+ALTER TABLE users ADD COLUMN last_login_at TIMESTAMPTZ;
@@ -0,0 +1,5 @@
+-- Set once the user proves they own `email`; cleared whenever the address changes
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+-- Accounts created before verification existed are trusted as-is
+UPDATE users SET email_verified_at = created_at;
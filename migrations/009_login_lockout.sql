@@ -0,0 +1,23 @@
+-- Failed login counters, keyed by username or client IP
+CREATE TABLE login_throttles (
+    scope VARCHAR(10) NOT NULL,
+    key VARCHAR(255) NOT NULL,
+    failed_count INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ,
+    PRIMARY KEY (scope, key)
+);
+
+-- Security events (failed logins, lockouts, unlocks) for monitoring and forensics
+CREATE TABLE security_audit_log (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    event_type VARCHAR(50) NOT NULL,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    username VARCHAR(255),
+    ip_address VARCHAR(45),
+    details TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_security_audit_log_user_id ON security_audit_log(user_id);
+CREATE INDEX idx_security_audit_log_created_at ON security_audit_log(created_at);
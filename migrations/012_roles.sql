@@ -0,0 +1,7 @@
+-- Role-based access control. Support staff can inspect accounts, admins can
+-- also change them.
+CREATE TYPE user_role AS ENUM ('user', 'support', 'admin');
+
+ALTER TABLE users ADD COLUMN role user_role NOT NULL DEFAULT 'user';
+
+CREATE INDEX idx_users_role ON users(role);
@@ -0,0 +1,9 @@
+-- Accounts waiting out the grace period between a deletion request and the
+-- purge of their data. Reactivating the account cancels the request.
+CREATE TABLE account_deletions (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    purge_after TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_account_deletions_purge_after ON account_deletions(purge_after);
@@ -0,0 +1,4 @@
+-- Subtasks: a todo may be nested under another todo of the same user
+ALTER TABLE todos ADD COLUMN parent_id UUID REFERENCES todos(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_todos_parent_id ON todos(parent_id);
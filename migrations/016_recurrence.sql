@@ -0,0 +1,30 @@
+-- Recurring todos: a series holds the schedule and the template for its
+-- occurrences, each of which is an ordinary todo
+CREATE TABLE todo_series (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rrule TEXT NOT NULL,
+    timezone TEXT NOT NULL,
+    -- First occurrence (DTSTART)
+    starts_at TIMESTAMPTZ NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    category_id UUID REFERENCES categories(id) ON DELETE SET NULL,
+    priority INTEGER,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    ended_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todo_series_user_id ON todo_series(user_id);
+
+CREATE TRIGGER update_todo_series_updated_at BEFORE UPDATE
+    ON todo_series FOR EACH ROW EXECUTE PROCEDURE update_updated_at_column();
+
+-- `occurrence_at` is the scheduled time of the occurrence, which stays put
+-- when a single occurrence's due date is moved
+ALTER TABLE todos ADD COLUMN series_id UUID REFERENCES todo_series(id) ON DELETE SET NULL;
+ALTER TABLE todos ADD COLUMN occurrence_at TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX idx_todos_series_occurrence ON todos(series_id, occurrence_at);
@@ -0,0 +1,26 @@
+-- Reminders fire at an absolute time or a number of seconds before the
+-- todo's due date, so that moving the due date moves the reminder with it
+CREATE TABLE todo_reminders (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    remind_at TIMESTAMPTZ,
+    before_due_secs INTEGER CHECK (before_due_secs >= 0),
+    -- Set by the scheduler instance that claimed and delivered the reminder
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((remind_at IS NULL) <> (before_due_secs IS NULL))
+);
+
+CREATE INDEX idx_todo_reminders_todo_id ON todo_reminders(todo_id);
+CREATE INDEX idx_todo_reminders_pending ON todo_reminders(todo_id) WHERE sent_at IS NULL;
+
+-- The due date each todo was last reported overdue for. A todo whose due
+-- date has moved since is reported again once the new one passes
+CREATE TABLE todo_overdue_notices (
+    todo_id UUID PRIMARY KEY REFERENCES todos(id) ON DELETE CASCADE,
+    due_date TIMESTAMPTZ NOT NULL,
+    notified_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todos_open_due_date ON todos(due_date) WHERE completed = FALSE;
@@ -0,0 +1,15 @@
+-- Trash: deleted todos, categories and tags are kept with `deleted_at` set
+-- until they are restored or purged
+ALTER TABLE todos ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE categories ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE tags ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_todos_deleted_at ON todos(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_categories_deleted_at ON categories(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_tags_deleted_at ON tags(deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Names only have to be unique among items that are not in the trash
+ALTER TABLE categories DROP CONSTRAINT categories_name_user_id_key;
+CREATE UNIQUE INDEX idx_categories_name_user_id ON categories(name, user_id) WHERE deleted_at IS NULL;
+ALTER TABLE tags DROP CONSTRAINT tags_name_user_id_key;
+CREATE UNIQUE INDEX idx_tags_name_user_id ON tags(name, user_id) WHERE deleted_at IS NULL;
@@ -0,0 +1,29 @@
+-- Revision history: one row per change to a todo, numbered per todo, with the
+-- todo as it was before and after the change
+CREATE TABLE todo_revisions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    before JSONB NOT NULL,
+    after JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (todo_id, revision)
+);
+
+-- Revisions are never rewritten; only deleting the todo (or the acting
+-- user) may touch them
+CREATE OR REPLACE FUNCTION reject_revision_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.actor_id IS NULL AND OLD.actor_id IS NOT NULL
+        AND (NEW.todo_id, NEW.revision, NEW.before, NEW.after, NEW.created_at)
+            IS NOT DISTINCT FROM (OLD.todo_id, OLD.revision, OLD.before, OLD.after, OLD.created_at) THEN
+        RETURN NEW;
+    END IF;
+    RAISE EXCEPTION 'todo revisions are immutable';
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER todo_revisions_immutable BEFORE UPDATE
+    ON todo_revisions FOR EACH ROW EXECUTE PROCEDURE reject_revision_update();
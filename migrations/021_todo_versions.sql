@@ -0,0 +1,14 @@
+-- Optimistic concurrency: every change to a todo bumps its version, which
+-- clients send back in If-Match
+ALTER TABLE todos ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+CREATE OR REPLACE FUNCTION bump_version_column()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.version = OLD.version + 1;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER bump_todos_version BEFORE UPDATE
+    ON todos FOR EACH ROW EXECUTE PROCEDURE bump_version_column();
@@ -0,0 +1,62 @@
+-- Per-user change marker behind the ETag and Last-Modified of list and stats
+-- responses: bumped by every change to the user's todos, categories, tags,
+-- recurring series and the links between todos
+CREATE TABLE user_change_markers (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    revision BIGINT NOT NULL DEFAULT 0,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO user_change_markers (user_id) SELECT id FROM users;
+
+-- Users deleted in the same transaction are skipped, so the deletes that
+-- cascade from them do not bring the marker back
+CREATE OR REPLACE FUNCTION bump_change_marker(owner UUID)
+RETURNS VOID AS $$
+BEGIN
+    INSERT INTO user_change_markers (user_id, revision, changed_at)
+    SELECT id, 1, clock_timestamp() FROM users WHERE id = owner
+    ON CONFLICT (user_id) DO UPDATE
+        SET revision = user_change_markers.revision + 1, changed_at = clock_timestamp();
+END;
+$$ language 'plpgsql';
+
+CREATE OR REPLACE FUNCTION mark_owner_changed()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM bump_change_marker(OLD.user_id);
+    ELSE
+        PERFORM bump_change_marker(NEW.user_id);
+    END IF;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+-- For link tables, whose owner is the owner of the linked todo
+CREATE OR REPLACE FUNCTION mark_todo_owner_changed()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM bump_change_marker((SELECT user_id FROM todos WHERE id = OLD.todo_id));
+    ELSE
+        PERFORM bump_change_marker((SELECT user_id FROM todos WHERE id = NEW.todo_id));
+    END IF;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+-- Deferred to commit, so the marker row is locked last and only briefly,
+-- instead of serializing every transaction that writes the user's data
+CREATE CONSTRAINT TRIGGER todos_change_marker AFTER INSERT OR UPDATE OR DELETE
+    ON todos DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE mark_owner_changed();
+CREATE CONSTRAINT TRIGGER categories_change_marker AFTER INSERT OR UPDATE OR DELETE
+    ON categories DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE mark_owner_changed();
+CREATE CONSTRAINT TRIGGER tags_change_marker AFTER INSERT OR UPDATE OR DELETE
+    ON tags DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE mark_owner_changed();
+CREATE CONSTRAINT TRIGGER todo_series_change_marker AFTER INSERT OR UPDATE OR DELETE
+    ON todo_series DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE mark_owner_changed();
+CREATE CONSTRAINT TRIGGER todo_tags_change_marker AFTER INSERT OR UPDATE OR DELETE
+    ON todo_tags DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE mark_todo_owner_changed();
+CREATE CONSTRAINT TRIGGER todo_dependencies_change_marker AFTER INSERT OR UPDATE OR DELETE
+    ON todo_dependencies DEFERRABLE INITIALLY DEFERRED FOR EACH ROW EXECUTE PROCEDURE mark_todo_owner_changed();
@@ -0,0 +1,9 @@
+-- One index per sort of the todo list, each ending in the id tiebreaker so
+-- that keyset pages are read straight off the index. Descending keys match
+-- the direction they are usually sorted in; NULLs sort last either way
+CREATE INDEX idx_todos_user_created_at ON todos(user_id, created_at DESC, id DESC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_todos_user_updated_at ON todos(user_id, updated_at DESC, id DESC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_todos_user_due_date ON todos(user_id, due_date ASC NULLS LAST, id ASC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_todos_user_priority ON todos(user_id, priority DESC NULLS LAST, id DESC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_todos_user_title ON todos(user_id, title, id) WHERE deleted_at IS NULL;
+CREATE INDEX idx_todos_user_completed ON todos(user_id, completed, created_at DESC, id DESC) WHERE deleted_at IS NULL;
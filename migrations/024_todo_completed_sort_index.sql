@@ -0,0 +1,5 @@
+-- Sorting by `completed` alone breaks ties by id in the same direction, which
+-- the index from 023 could not serve. Only single-key sorts are backed by an
+-- index; sorts on several keys are ordered after filtering by user
+DROP INDEX idx_todos_user_completed;
+CREATE INDEX idx_todos_user_completed ON todos(user_id, completed, id) WHERE deleted_at IS NULL;
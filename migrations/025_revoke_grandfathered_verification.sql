@@ -0,0 +1,7 @@
+-- 008 marked every account that existed at the time as verified, although
+-- none of them had proved it owns its address. Accounts created before 008
+-- ran that still carry its mark, a verification at the moment of creation,
+-- have to verify their address like every other account
+UPDATE users u SET email_verified_at = NULL
+FROM _sqlx_migrations m
+WHERE m.version = 8 AND u.created_at < m.installed_on AND u.email_verified_at = u.created_at;
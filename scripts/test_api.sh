@@ -44,7 +44,7 @@ fi
 
 # Create a category
 echo -e "\n3. Creating a category..."
-CATEGORY_RESPONSE=$(curl -s -X POST "$BASE_URL/api/categories?user_id=$USER_ID" \
+CATEGORY_RESPONSE=$(curl -s -X POST "$BASE_URL/api/categories" \
   -H "$CONTENT_TYPE" \
   $([ -n "$AUTH_HEADER" ] && echo "-H \"$AUTH_HEADER\"") \
   -d '{
@@ -58,7 +58,7 @@ CATEGORY_ID=$(echo $CATEGORY_RESPONSE | jq -r '.id // empty')
 
 # Create tags
 echo -e "\n4. Creating tags..."
-TAG1_RESPONSE=$(curl -s -X POST "$BASE_URL/api/tags?user_id=$USER_ID" \
+TAG1_RESPONSE=$(curl -s -X POST "$BASE_URL/api/tags" \
   -H "$CONTENT_TYPE" \
   $([ -n "$AUTH_HEADER" ] && echo "-H \"$AUTH_HEADER\"") \
   -d '{
@@ -66,7 +66,7 @@ TAG1_RESPONSE=$(curl -s -X POST "$BASE_URL/api/tags?user_id=$USER_ID" \
   }')
 echo "Tag 1 response: $TAG1_RESPONSE"
 
-TAG2_RESPONSE=$(curl -s -X POST "$BASE_URL/api/tags?user_id=$USER_ID" \
+TAG2_RESPONSE=$(curl -s -X POST "$BASE_URL/api/tags" \
   -H "$CONTENT_TYPE" \
   $([ -n "$AUTH_HEADER" ] && echo "-H \"$AUTH_HEADER\"") \
   -d '{
@@ -119,19 +119,19 @@ echo "Filtered todos response: $TODOS_RESPONSE"
 
 # Get categories
 echo -e "\n7. Getting categories..."
-CATEGORIES_RESPONSE=$(curl -s -X GET "$BASE_URL/api/categories?user_id=$USER_ID" \
+CATEGORIES_RESPONSE=$(curl -s -X GET "$BASE_URL/api/categories" \
   $([ -n "$AUTH_HEADER" ] && echo "-H \"$AUTH_HEADER\""))
 echo "Categories response: $CATEGORIES_RESPONSE"
 
 # Get tags
 echo -e "\n8. Getting tags..."
-TAGS_RESPONSE=$(curl -s -X GET "$BASE_URL/api/tags?user_id=$USER_ID" \
+TAGS_RESPONSE=$(curl -s -X GET "$BASE_URL/api/tags" \
   $([ -n "$AUTH_HEADER" ] && echo "-H \"$AUTH_HEADER\""))
 echo "Tags response: $TAGS_RESPONSE"
 
 # Get statistics
 echo -e "\n9. Getting todo statistics..."
-STATS_RESPONSE=$(curl -s -X GET "$BASE_URL/api/stats/todos?user_id=$USER_ID" \
+STATS_RESPONSE=$(curl -s -X GET "$BASE_URL/api/stats/todos" \
   $([ -n "$AUTH_HEADER" ] && echo "-H \"$AUTH_HEADER\""))
 echo "Statistics response: $STATS_RESPONSE"
 
@@ -3,6 +3,8 @@
 # Comprehensive todo client script
 
 SERVER_URL="http://127.0.0.1:3000"
+# Bearer token obtained from /api/users/login, or an API key from /api/auth/api-keys
+TOKEN="${TODO_TOKEN:-}"
 
 show_help() {
     echo "Todo Client - Interact with the Todo API"
@@ -27,6 +29,9 @@ show_help() {
     echo "  -f, --filter       Filter by completed status (true/false)"
     echo "  -u, --url          Server URL (default: http://127.0.0.1:3000)"
     echo ""
+    echo "Environment:"
+    echo "  TODO_TOKEN         Bearer token or API key sent with every request"
+    echo ""
     echo "Examples:"
     echo "  $0 create -t 'Buy groceries' -d 'Milk, bread, eggs'"
     echo "  $0 list -p 1 -l 5"
@@ -40,11 +45,17 @@ make_request() {
     local endpoint="$2"
     local data="$3"
     
+    local auth_args=()
+    if [ -n "$TOKEN" ]; then
+        auth_args=(-H "Authorization: Bearer $TOKEN")
+    fi
+    
     if [ -z "$data" ]; then
-        response=$(curl -s -w "\nHTTP_CODE:%{http_code}" -X "$method" "$SERVER_URL$endpoint")
+        response=$(curl -s -w "\nHTTP_CODE:%{http_code}" -X "$method" "${auth_args[@]}" "$SERVER_URL$endpoint")
     else
         response=$(curl -s -w "\nHTTP_CODE:%{http_code}" \
             -X "$method" \
+            "${auth_args[@]}" \
             -H "Content-Type: application/json" \
             -d "$data" \
             "$SERVER_URL$endpoint")
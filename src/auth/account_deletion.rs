@@ -0,0 +1,125 @@
+//! Right to erasure. A deletion request disables the account straight away;
+//! once the grace period has passed its data is deleted and its entries in the
+//! security audit log are anonymized.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    auth::{lockout, session},
+    db::DbPool,
+    error::{AppError, Result},
+};
+
+/// An account whose data has been purged.
+#[derive(Debug, Clone)]
+pub struct PurgedAccount {
+    pub user_id: Uuid,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// Disables the account, signs it out everywhere and schedules the purge.
+/// Repeated requests keep the original schedule. Returns when the data will be purged.
+pub async fn schedule(pool: &DbPool, user_id: Uuid, grace_secs: i64) -> Result<DateTime<Utc>> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query("UPDATE users SET is_active = false, updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("User with id {} not found", user_id)));
+    }
+
+    session::revoke_all(&mut *tx, user_id, None, session::REVOKED_ACCOUNT_DELETION).await?;
+
+    let purge_after: DateTime<Utc> = sqlx::query_scalar(
+        r#"
+        INSERT INTO account_deletions (user_id, purge_after)
+        VALUES ($1, NOW() + make_interval(secs => $2))
+        ON CONFLICT (user_id) DO UPDATE SET purge_after = account_deletions.purge_after
+        RETURNING purge_after
+        "#,
+    )
+    .bind(user_id)
+    .bind(grace_secs as f64)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(purge_after)
+}
+
+/// Withdraws a pending deletion, returning whether there was one.
+pub async fn cancel<'e, E: PgExecutor<'e>>(executor: E, user_id: Uuid) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM account_deletions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Accounts whose grace period has ended, oldest request first.
+pub async fn due(pool: &DbPool, limit: i64) -> Result<Vec<Uuid>> {
+    let user_ids = sqlx::query_scalar(
+        "SELECT user_id FROM account_deletions WHERE purge_after <= NOW() ORDER BY requested_at LIMIT $1"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(user_ids)
+}
+
+/// Deletes the account and everything it owns, and strips identifying details
+/// from its audit log entries. Returns `None` if the deletion is no longer
+/// pending, e.g. because it was cancelled or another instance purged it.
+pub async fn purge(pool: &DbPool, user_id: Uuid) -> Result<Option<PurgedAccount>> {
+    let mut tx = pool.begin().await?;
+
+    // Deleting the request first locks it against concurrent purges
+    let requested_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+        "DELETE FROM account_deletions WHERE user_id = $1 AND purge_after <= NOW() RETURNING requested_at"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(requested_at) = requested_at else {
+        return Ok(None);
+    };
+
+    let username: String = sqlx::query_scalar("SELECT username FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE security_audit_log
+        SET user_id = NULL, username = NULL, ip_address = NULL, details = NULL
+        WHERE user_id = $1 OR username = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(&username)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM login_throttles WHERE scope = $1 AND key = $2")
+        .bind(lockout::SCOPE_USERNAME)
+        .bind(&username)
+        .execute(&mut *tx)
+        .await?;
+
+    // Todos, categories, tags, sessions, keys and linked identities cascade
+    sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(Some(PurgedAccount { user_id, requested_at }))
+}
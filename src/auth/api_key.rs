@@ -0,0 +1,149 @@
+//! Personal access tokens for scripts and bots. Keys are opaque, stored only
+//! as hashes and limited to the scopes chosen when they were created.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::{
+    auth::session::{generate_token, hash_token},
+    db::DbPool,
+    error::{AppError, Result},
+    models::{ApiKey, Role},
+};
+
+/// Every key starts with this, which is how `auth_middleware` tells them apart from JWTs.
+pub const KEY_PREFIX: &str = "tdk_";
+
+pub const TODOS_READ: &str = "todos:read";
+pub const TODOS_WRITE: &str = "todos:write";
+pub const CATEGORIES_READ: &str = "categories:read";
+pub const CATEGORIES_WRITE: &str = "categories:write";
+pub const TAGS_READ: &str = "tags:read";
+pub const TAGS_WRITE: &str = "tags:write";
+pub const STATS_READ: &str = "stats:read";
+
+pub const SCOPES: &[&str] = &[
+    TODOS_READ,
+    TODOS_WRITE,
+    CATEGORIES_READ,
+    CATEGORIES_WRITE,
+    TAGS_READ,
+    TAGS_WRITE,
+    STATS_READ,
+];
+
+/// Length of the stored, displayable start of a key: the prefix plus 8 characters.
+const DISPLAY_PREFIX_LEN: usize = KEY_PREFIX.len() + 8;
+
+/// `last_used_at` is refreshed at most this often to keep writes down.
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// Creates a key and returns it with the plain-text key, which is not stored.
+pub async fn create(
+    pool: &DbPool,
+    user_id: Uuid,
+    name: &str,
+    scopes: &[String],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(ApiKey, String)> {
+    if let Some(unknown) = scopes.iter().find(|s| !SCOPES.contains(&s.as_str())) {
+        return Err(AppError::Validation(format!(
+            "Unknown scope '{}', expected one of: {}",
+            unknown,
+            SCOPES.join(", ")
+        )));
+    }
+    if expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(AppError::Validation("expires_at must be in the future".to_string()));
+    }
+
+    let mut scopes = scopes.to_vec();
+    scopes.sort();
+    scopes.dedup();
+
+    let (secret, _) = generate_token();
+    let key = format!("{KEY_PREFIX}{secret}");
+
+    let api_key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(&key[..DISPLAY_PREFIX_LEN])
+    .bind(hash_token(&key))
+    .bind(&scopes)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((api_key, key))
+}
+
+#[derive(FromRow)]
+struct KeyOwner {
+    #[sqlx(flatten)]
+    key: ApiKey,
+    username: String,
+    role: Role,
+}
+
+/// Looks up a live key belonging to an active user and records its use.
+/// Returns the key together with its owner's username and role.
+pub async fn authenticate(pool: &DbPool, key: &str) -> Result<(ApiKey, String, Role)> {
+    let owner = sqlx::query_as::<_, KeyOwner>(
+        r#"
+        SELECT k.*, u.username, u.role FROM api_keys k
+        JOIN users u ON u.id = k.user_id
+        WHERE k.key_hash = $1 AND k.revoked_at IS NULL
+          AND (k.expires_at IS NULL OR k.expires_at > NOW())
+          AND u.is_active = true
+        "#,
+    )
+    .bind(hash_token(key))
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid or expired API key".to_string()))?;
+
+    let stale = owner
+        .key
+        .last_used_at
+        .is_none_or(|at| Utc::now() - at > Duration::seconds(LAST_USED_RESOLUTION_SECS));
+    if stale {
+        sqlx::query("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1")
+            .bind(owner.key.id)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok((owner.key, owner.username, owner.role))
+}
+
+/// Lists the user's keys that have not been revoked, newest first.
+pub async fn list(pool: &DbPool, user_id: Uuid) -> Result<Vec<ApiKey>> {
+    let keys = sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys WHERE user_id = $1 AND revoked_at IS NULL ORDER BY created_at DESC"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(keys)
+}
+
+/// Revokes one of the user's keys, returning whether it existed.
+pub async fn revoke(pool: &DbPool, user_id: Uuid, id: Uuid) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
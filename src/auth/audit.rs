@@ -0,0 +1,41 @@
+//! Append-only log of security relevant events such as failed logins and
+//! lockouts. Everything recorded here is also published to Kafka by callers.
+
+use uuid::Uuid;
+
+use crate::{db::DbPool, error::Result};
+
+pub const LOGIN_FAILED: &str = "login_failed";
+pub const ACCOUNT_LOCKED: &str = "account_locked";
+pub const ACCOUNT_UNLOCKED: &str = "account_unlocked";
+pub const USER_DEACTIVATED: &str = "user_deactivated";
+pub const USER_REACTIVATED: &str = "user_reactivated";
+pub const ROLE_CHANGED: &str = "role_changed";
+pub const SESSIONS_REVOKED: &str = "sessions_revoked";
+
+#[derive(Debug, Default)]
+pub struct AuditEntry<'a> {
+    pub event_type: &'a str,
+    pub user_id: Option<Uuid>,
+    pub username: Option<&'a str>,
+    pub ip_address: Option<&'a str>,
+    pub details: Option<String>,
+}
+
+pub async fn record(pool: &DbPool, entry: AuditEntry<'_>) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO security_audit_log (event_type, user_id, username, ip_address, details)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(entry.event_type)
+    .bind(entry.user_id)
+    .bind(entry.username)
+    .bind(entry.ip_address)
+    .bind(entry.details)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
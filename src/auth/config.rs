@@ -0,0 +1,253 @@
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use std::env;
+
+const DEFAULT_JWT_SECRET: &str = "your-secret-key"; // Development only, set JWT_SECRET in production
+
+/// A single signing/verification key, selected by the `kid` JWT header.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    /// Shared secret for HS256 keys.
+    pub secret: Option<String>,
+    /// PEM private key for RS256/EdDSA. Keys without one can only verify.
+    pub private_key_path: Option<String>,
+    /// PEM public key for RS256/EdDSA, published through the JWKS endpoint.
+    pub public_key_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtConfig {
+    pub issuer: String,
+    pub audience: String,
+    pub access_token_ttl_secs: i64,
+    pub refresh_token_ttl_secs: i64,
+    /// `kid` of the key used to sign new tokens; every other key only verifies.
+    pub signing_kid: String,
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+impl JwtConfig {
+    /// Reads `JWT_KEYS=kid1,kid2` and the per-key `JWT_KEY_<KID>_*` variables.
+    /// Without `JWT_KEYS` a single HS256 key named `default` is built from `JWT_SECRET`.
+    pub fn from_env() -> Self {
+        let keys: Vec<JwtKeyConfig> = match env::var("JWT_KEYS") {
+            Ok(kids) => kids
+                .split(',')
+                .map(str::trim)
+                .filter(|kid| !kid.is_empty())
+                .map(key_from_env)
+                .collect(),
+            Err(_) => vec![JwtKeyConfig {
+                kid: "default".to_string(),
+                algorithm: Algorithm::HS256,
+                secret: Some(
+                    env::var("JWT_SECRET").unwrap_or_else(|_| DEFAULT_JWT_SECRET.to_string()),
+                ),
+                private_key_path: None,
+                public_key_path: None,
+            }],
+        };
+
+        let signing_kid = env::var("JWT_SIGNING_KID")
+            .ok()
+            .or_else(|| keys.first().map(|key| key.kid.clone()))
+            .unwrap_or_default();
+
+        JwtConfig {
+            issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| "axum-server".to_string()),
+            audience: env::var("JWT_AUDIENCE").unwrap_or_else(|_| "axum-server".to_string()),
+            access_token_ttl_secs: env::var("JWT_ACCESS_TOKEN_TTL_SECS")
+                .unwrap_or_else(|_| "900".to_string())
+                .parse()
+                .unwrap_or(900),
+            refresh_token_ttl_secs: env::var("JWT_REFRESH_TOKEN_TTL_SECS")
+                .unwrap_or_else(|_| "2592000".to_string())
+                .parse()
+                .unwrap_or(2592000),
+            signing_kid,
+            keys,
+        }
+    }
+}
+
+fn key_from_env(kid: &str) -> JwtKeyConfig {
+    let prefix = format!(
+        "JWT_KEY_{}_",
+        kid.to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    );
+    let var = |name: &str| env::var(format!("{}{}", prefix, name)).ok();
+
+    JwtKeyConfig {
+        kid: kid.to_string(),
+        algorithm: var("ALGORITHM")
+            .and_then(|alg| alg.parse().ok())
+            .unwrap_or(Algorithm::HS256),
+        secret: var("SECRET"),
+        private_key_path: var("PRIVATE_KEY_PATH"),
+        public_key_path: var("PUBLIC_KEY_PATH"),
+    }
+}
+
+/// Failed-login throttling. Every failure delays the next attempt by
+/// `base_delay_secs * 2^(failures - 1)`; reaching the limit locks the key for
+/// `lockout_secs`. Counters reset after `failure_window_secs` without failures.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockoutConfig {
+    pub max_failures_per_username: i32,
+    pub max_failures_per_ip: i32,
+    pub base_delay_secs: i64,
+    pub lockout_secs: i64,
+    pub failure_window_secs: i64,
+}
+
+impl LockoutConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: i64| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        LockoutConfig {
+            max_failures_per_username: var("LOCKOUT_MAX_FAILURES_PER_USERNAME", 5) as i32,
+            max_failures_per_ip: var("LOCKOUT_MAX_FAILURES_PER_IP", 20) as i32,
+            base_delay_secs: var("LOCKOUT_BASE_DELAY_SECS", 1),
+            lockout_secs: var("LOCKOUT_DURATION_SECS", 900),
+            failure_window_secs: var("LOCKOUT_FAILURE_WINDOW_SECS", 900),
+        }
+    }
+}
+
+/// Argon2id cost parameters for new password hashes. Stored hashes below these
+/// are upgraded on the next successful login.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasswordHashConfig {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl PasswordHashConfig {
+    /// Defaults follow the OWASP recommendation of 19 MiB, 2 iterations, 1 lane.
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: u32| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        PasswordHashConfig {
+            memory_kib: var("PASSWORD_HASH_MEMORY_KIB", 19456),
+            iterations: var("PASSWORD_HASH_ITERATIONS", 2),
+            parallelism: var("PASSWORD_HASH_PARALLELISM", 1),
+        }
+    }
+}
+
+/// An external OpenID Connect identity provider, selected by `name` in the
+/// login URL.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderConfig {
+    pub name: String,
+    /// Issuer URL; the discovery document is read from
+    /// `<issuer>/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    /// Omit for public clients, which rely on PKCE alone.
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+    /// Where the provider sends the browser back to. Defaults to this server's
+    /// callback route.
+    pub redirect_url: Option<String>,
+    /// Link a first-time login to an existing account with the same email
+    /// address, if the provider reports it as verified. Only enable this for
+    /// providers that own the email domain.
+    pub link_by_email: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcConfig {
+    pub providers: Vec<OidcProviderConfig>,
+    /// How long a started login may take to come back to the callback.
+    pub login_ttl_secs: i64,
+}
+
+impl OidcConfig {
+    /// Reads `OIDC_PROVIDERS=name1,name2` and the per-provider `OIDC_<NAME>_*` variables.
+    pub fn from_env() -> Self {
+        let providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(provider_from_env)
+            .collect();
+
+        OidcConfig {
+            providers,
+            login_ttl_secs: env::var("OIDC_LOGIN_TTL_SECS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(600),
+        }
+    }
+}
+
+fn provider_from_env(name: &str) -> OidcProviderConfig {
+    let prefix = format!(
+        "OIDC_{}_",
+        name.to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    );
+    let var = |key: &str| env::var(format!("{}{}", prefix, key)).ok();
+
+    OidcProviderConfig {
+        name: name.to_string(),
+        issuer: var("ISSUER").unwrap_or_default(),
+        client_id: var("CLIENT_ID").unwrap_or_default(),
+        client_secret: var("CLIENT_SECRET"),
+        scopes: var("SCOPES")
+            .unwrap_or_else(|| "openid email profile".to_string())
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        redirect_url: var("REDIRECT_URL"),
+        link_by_email: var("LINK_BY_EMAIL")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(false),
+    }
+}
+
+/// Session cookies for browser clients, as an alternative to keeping tokens
+/// in JavaScript.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CookieAuthConfig {
+    /// Set session cookies on login and accept them in place of a Bearer token.
+    pub enabled: bool,
+    /// Only send cookies over HTTPS. Disable for plain-HTTP local development.
+    pub secure: bool,
+    /// `Strict`, `Lax` or `None`; `None` requires `secure`.
+    pub same_site: String,
+    pub domain: Option<String>,
+}
+
+impl CookieAuthConfig {
+    pub fn from_env() -> Self {
+        let flag = |name: &str, default: bool| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        CookieAuthConfig {
+            enabled: flag("COOKIE_AUTH_ENABLED", false),
+            secure: flag("COOKIE_SECURE", true),
+            same_site: env::var("COOKIE_SAME_SITE").unwrap_or_else(|_| "Lax".to_string()),
+            domain: env::var("COOKIE_DOMAIN").ok().filter(|domain| !domain.is_empty()),
+        }
+    }
+}
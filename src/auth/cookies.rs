@@ -0,0 +1,140 @@
+//! Session cookies for browser clients. The access and refresh tokens travel
+//! in HttpOnly cookies, out of reach of scripts. Requests authenticated by
+//! cookie that change state must echo the readable CSRF cookie in the
+//! `X-CSRF-Token` header (double-submit), which other sites cannot do.
+//!
+//! An SSO login also carries its `state` in a short-lived cookie, so that the
+//! callback only completes in the browser that started the login.
+
+use axum::http::{
+    header::{COOKIE, SET_COOKIE},
+    HeaderMap, HeaderValue,
+};
+use cookie::{time::Duration, Cookie, CookieBuilder, SameSite};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    auth::{config::CookieAuthConfig, session::generate_token},
+    error::{AppError, Result},
+    models::AuthResponse,
+};
+
+pub const SESSION_COOKIE: &str = "todo_session";
+pub const REFRESH_COOKIE: &str = "todo_refresh";
+pub const CSRF_COOKIE: &str = "todo_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+pub const OIDC_STATE_COOKIE: &str = "todo_oidc_state";
+
+/// The refresh token is only sent to the endpoints that consume it.
+const REFRESH_COOKIE_PATH: &str = "/api/auth";
+const OIDC_STATE_COOKIE_PATH: &str = "/api/auth/oidc";
+
+/// Returns the value of the cookie called `name`.
+pub fn get(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(Cookie::split_parse)
+        .filter_map(|cookie| cookie.ok())
+        .find(|cookie| cookie.name() == name)
+        .map(|cookie| cookie.value().to_string())
+}
+
+/// `Set-Cookie` headers for a newly issued token pair, with a fresh CSRF token.
+pub fn issue(config: &CookieAuthConfig, auth: &AuthResponse, refresh_ttl_secs: i64) -> HeaderMap {
+    let (csrf_token, _) = generate_token();
+
+    let mut headers = HeaderMap::new();
+    append(&mut headers, build(config, SESSION_COOKIE, &auth.token, "/", auth.expires_in).http_only(true));
+    append(
+        &mut headers,
+        build(config, REFRESH_COOKIE, &auth.refresh_token, REFRESH_COOKIE_PATH, refresh_ttl_secs).http_only(true),
+    );
+    append(&mut headers, build(config, CSRF_COOKIE, &csrf_token, "/", refresh_ttl_secs));
+    headers
+}
+
+/// `Set-Cookie` headers that remove every session cookie.
+pub fn clear(config: &CookieAuthConfig) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    append(&mut headers, build(config, SESSION_COOKIE, "", "/", 0).http_only(true));
+    append(&mut headers, build(config, REFRESH_COOKIE, "", REFRESH_COOKIE_PATH, 0).http_only(true));
+    append(&mut headers, build(config, CSRF_COOKIE, "", "/", 0));
+    headers
+}
+
+/// `Set-Cookie` header binding an SSO login's `state` to the browser.
+pub fn issue_oidc_state(config: &CookieAuthConfig, state: &str, ttl_secs: i64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    append(&mut headers, oidc_state_cookie(config, state, ttl_secs));
+    headers
+}
+
+/// `Set-Cookie` header that removes the SSO login state cookie.
+pub fn clear_oidc_state(config: &CookieAuthConfig) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    append(&mut headers, oidc_state_cookie(config, "", 0));
+    headers
+}
+
+/// Checks that the callback's `state` is the one stored in this browser by
+/// the login that started it.
+pub fn verify_oidc_state(headers: &HeaderMap, state: &str) -> Result<()> {
+    match get(headers, OIDC_STATE_COOKIE).filter(|cookie| !cookie.is_empty()) {
+        Some(cookie) if bool::from(cookie.as_bytes().ct_eq(state.as_bytes())) => Ok(()),
+        _ => Err(AppError::BadRequest("Invalid or expired login state".to_string())),
+    }
+}
+
+/// Checks that the request carries the CSRF cookie's value in the
+/// `X-CSRF-Token` header. Required for cookie-authenticated requests that
+/// change state.
+pub fn verify_csrf(headers: &HeaderMap) -> Result<()> {
+    let cookie = get(headers, CSRF_COOKIE).filter(|token| !token.is_empty());
+    let header = headers.get(CSRF_HEADER).and_then(|value| value.to_str().ok());
+
+    match (cookie, header) {
+        (Some(cookie), Some(header)) if bool::from(cookie.as_bytes().ct_eq(header.as_bytes())) => Ok(()),
+        _ => Err(AppError::Forbidden("Missing or invalid CSRF token".to_string())),
+    }
+}
+
+fn oidc_state_cookie<'a>(config: &CookieAuthConfig, state: &'a str, ttl_secs: i64) -> CookieBuilder<'a> {
+    // The provider sends the browser back with a cross-site navigation, which
+    // carries `Lax` cookies but not `Strict` ones
+    build(config, OIDC_STATE_COOKIE, state, OIDC_STATE_COOKIE_PATH, ttl_secs)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+}
+
+fn build<'a>(
+    config: &CookieAuthConfig,
+    name: &'a str,
+    value: &'a str,
+    path: &'a str,
+    max_age_secs: i64,
+) -> CookieBuilder<'a> {
+    let same_site = match config.same_site.to_ascii_lowercase().as_str() {
+        "strict" => SameSite::Strict,
+        "none" => SameSite::None,
+        _ => SameSite::Lax,
+    };
+
+    let mut cookie = Cookie::build((name, value))
+        .path(path)
+        .secure(config.secure)
+        .same_site(same_site)
+        .max_age(Duration::seconds(max_age_secs));
+    if let Some(domain) = &config.domain {
+        cookie = cookie.domain(domain.clone());
+    }
+    cookie
+}
+
+fn append(headers: &mut HeaderMap, cookie: CookieBuilder<'_>) {
+    // Cookie values are base64url tokens or JWTs, which are always valid header values
+    if let Ok(value) = HeaderValue::from_str(&cookie.build().to_string()) {
+        headers.append(SET_COOKIE, value);
+    }
+}
@@ -0,0 +1,267 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use simple_asn1::{from_der, ASN1Block};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    auth::config::{JwtConfig, JwtKeyConfig},
+    error::{AppError, Result},
+    models::Role,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Claims {
+    pub sub: String, // User ID
+    pub username: String,
+    pub sid: Uuid, // Session the token was issued for
+    #[serde(default)] // Tokens issued before roles existed
+    pub role: Role,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub exp: usize, // Expiration time
+}
+
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// The active key set: one key signs new tokens, every configured key verifies.
+pub struct JwtKeys {
+    issuer: String,
+    audience: String,
+    access_token_ttl_secs: i64,
+    signing_kid: String,
+    signing_algorithm: Algorithm,
+    signing_key: EncodingKey,
+    verification_keys: HashMap<String, VerificationKey>,
+    jwks: JwkSet,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &JwtConfig) -> Result<Self> {
+        let mut verification_keys = HashMap::new();
+        let mut public_keys = Vec::new();
+        let mut signing = None;
+
+        for key in &config.keys {
+            let (encoding_key, decoding_key, jwk) = load_key(key)?;
+
+            if key.kid == config.signing_kid {
+                let encoding_key = encoding_key.ok_or_else(|| {
+                    AppError::Internal(format!("Signing key '{}' has no private key", key.kid))
+                })?;
+                signing = Some((key.algorithm, encoding_key));
+            }
+
+            verification_keys.insert(
+                key.kid.clone(),
+                VerificationKey {
+                    algorithm: key.algorithm,
+                    key: decoding_key,
+                },
+            );
+            public_keys.extend(jwk);
+        }
+
+        let (signing_algorithm, signing_key) = signing.ok_or_else(|| {
+            AppError::Internal(format!("Signing key '{}' is not configured", config.signing_kid))
+        })?;
+
+        Ok(Self {
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            access_token_ttl_secs: config.access_token_ttl_secs,
+            signing_kid: config.signing_kid.clone(),
+            signing_algorithm,
+            signing_key,
+            verification_keys,
+            jwks: JwkSet { keys: public_keys },
+        })
+    }
+
+    pub fn access_claims(&self, user_id: Uuid, username: &str, role: Role, session_id: Uuid) -> Claims {
+        let now = Utc::now();
+        Claims {
+            sub: user_id.to_string(),
+            username: username.to_string(),
+            sid: session_id,
+            role,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            iat: now.timestamp() as usize,
+            exp: (now + chrono::Duration::seconds(self.access_token_ttl_secs)).timestamp() as usize,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let mut header = Header::new(self.signing_algorithm);
+        header.kid = Some(self.signing_kid.clone());
+
+        encode(&header, claims, &self.signing_key)
+            .map_err(|e| AppError::Internal(format!("Failed to generate token: {}", e)))
+    }
+
+    /// Verifies a token with the key named by its `kid` header. Tokens without a
+    /// `kid` (issued before key rotation existed) fall back to the signing key.
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let invalid = || AppError::Unauthorized("Invalid or expired token".to_string());
+
+        let header = decode_header(token).map_err(|_| invalid())?;
+        let kid = header.kid.unwrap_or_else(|| self.signing_kid.clone());
+        let key = self.verification_keys.get(&kid).ok_or_else(invalid)?;
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+        decode::<T>(token, &key.key, &validation)
+            .map(|data| data.claims)
+            .map_err(|_| invalid())
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    pub fn access_token_ttl_secs(&self) -> i64 {
+        self.access_token_ttl_secs
+    }
+
+    /// Public keys for `/.well-known/jwks.json`. HS256 secrets are never published.
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+type LoadedKey = (Option<EncodingKey>, DecodingKey, Option<Jwk>);
+
+fn load_key(config: &JwtKeyConfig) -> Result<LoadedKey> {
+    let key_error = |e: jsonwebtoken::errors::Error| {
+        AppError::Internal(format!("Invalid key material for '{}': {}", config.kid, e))
+    };
+
+    match config.algorithm {
+        Algorithm::HS256 => {
+            let secret = config.secret.as_deref().ok_or_else(|| {
+                AppError::Internal(format!("HS256 key '{}' has no secret", config.kid))
+            })?;
+            Ok((
+                Some(EncodingKey::from_secret(secret.as_bytes())),
+                DecodingKey::from_secret(secret.as_bytes()),
+                None,
+            ))
+        }
+        Algorithm::RS256 | Algorithm::EdDSA => {
+            let public_pem = read_key_file(&config.kid, config.public_key_path.as_deref())?;
+            let private_pem = config
+                .private_key_path
+                .as_deref()
+                .map(|path| read_key_file(&config.kid, Some(path)))
+                .transpose()?;
+
+            let (encoding_key, decoding_key, parameters, key_algorithm) =
+                if config.algorithm == Algorithm::RS256 {
+                    let (n, e) = rsa_public_components(&public_pem)
+                        .ok_or_else(|| AppError::Internal(format!("Invalid RSA public key for '{}'", config.kid)))?;
+                    (
+                        private_pem.map(|pem| EncodingKey::from_rsa_pem(&pem)).transpose().map_err(key_error)?,
+                        DecodingKey::from_rsa_pem(&public_pem).map_err(key_error)?,
+                        AlgorithmParameters::RSA(RSAKeyParameters {
+                            key_type: RSAKeyType::RSA,
+                            n: URL_SAFE_NO_PAD.encode(n),
+                            e: URL_SAFE_NO_PAD.encode(e),
+                        }),
+                        KeyAlgorithm::RS256,
+                    )
+                } else {
+                    let x = ed25519_public_key(&public_pem)
+                        .ok_or_else(|| AppError::Internal(format!("Invalid Ed25519 public key for '{}'", config.kid)))?;
+                    (
+                        private_pem.map(|pem| EncodingKey::from_ed_pem(&pem)).transpose().map_err(key_error)?,
+                        DecodingKey::from_ed_pem(&public_pem).map_err(key_error)?,
+                        AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                            key_type: OctetKeyPairType::OctetKeyPair,
+                            curve: EllipticCurve::Ed25519,
+                            x: URL_SAFE_NO_PAD.encode(x),
+                        }),
+                        KeyAlgorithm::EdDSA,
+                    )
+                };
+
+            let jwk = Jwk {
+                common: CommonParameters {
+                    public_key_use: Some(PublicKeyUse::Signature),
+                    key_algorithm: Some(key_algorithm),
+                    key_id: Some(config.kid.clone()),
+                    ..Default::default()
+                },
+                algorithm: parameters,
+            };
+
+            Ok((encoding_key, decoding_key, Some(jwk)))
+        }
+        other => Err(AppError::Internal(format!(
+            "Unsupported JWT algorithm {:?} for key '{}'",
+            other, config.kid
+        ))),
+    }
+}
+
+fn read_key_file(kid: &str, path: Option<&str>) -> Result<Vec<u8>> {
+    let path = path
+        .ok_or_else(|| AppError::Internal(format!("Key '{}' has no public key path", kid)))?;
+    std::fs::read(path)
+        .map_err(|e| AppError::Internal(format!("Failed to read key file {}: {}", path, e)))
+}
+
+/// Returns the big-endian `(n, e)` of a PKCS#1 or SPKI encoded RSA public key.
+fn rsa_public_components(pem_bytes: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let der = pem::parse(pem_bytes).ok()?.into_contents();
+    let mut blocks = from_der(&der).ok()?;
+
+    // SubjectPublicKeyInfo wraps the PKCS#1 key in a BIT STRING
+    if let Some(ASN1Block::Sequence(_, items)) = blocks.first()
+        && let Some(ASN1Block::BitString(_, _, inner)) = items.get(1)
+    {
+        blocks = from_der(inner).ok()?;
+    }
+
+    match blocks.first()? {
+        ASN1Block::Sequence(_, items) => match (items.first()?, items.get(1)?) {
+            (ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)) => {
+                Some((n.to_bytes_be().1, e.to_bytes_be().1))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the raw 32-byte key from an SPKI encoded Ed25519 public key.
+fn ed25519_public_key(pem_bytes: &[u8]) -> Option<Vec<u8>> {
+    let der = pem::parse(pem_bytes).ok()?.into_contents();
+    match from_der(&der).ok()?.first()? {
+        ASN1Block::Sequence(_, items) => match items.get(1)? {
+            ASN1Block::BitString(_, _, key) if key.len() == 32 => Some(key.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
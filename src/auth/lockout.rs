@@ -0,0 +1,146 @@
+//! Brute-force protection for `login_user`. Failed attempts are counted per
+//! username and per client IP; see [`LockoutConfig`] for the backoff rules.
+
+use chrono::{DateTime, Duration, Utc};
+use crate::{
+    auth::config::LockoutConfig,
+    db::DbPool,
+    error::{AppError, Result},
+};
+
+pub const SCOPE_USERNAME: &str = "username";
+pub const SCOPE_IP: &str = "ip";
+
+/// A key that just reached its failure limit.
+#[derive(Debug, Clone)]
+pub struct Lockout {
+    pub scope: &'static str,
+    pub key: String,
+    pub failed_attempts: i32,
+    pub locked_until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FailureOutcome {
+    /// Consecutive failures for the username.
+    pub failed_attempts: i32,
+    pub lockouts: Vec<Lockout>,
+}
+
+/// Rejects the attempt while the username or the IP is backing off or locked.
+pub async fn check(pool: &DbPool, username: &str, ip_address: Option<&str>) -> Result<()> {
+    let locked_until: Option<DateTime<Utc>> = sqlx::query_scalar(
+        r#"
+        SELECT MAX(locked_until) FROM login_throttles
+        WHERE ((scope = $1 AND key = $2) OR (scope = $3 AND key = $4))
+          AND locked_until > NOW()
+        "#,
+    )
+    .bind(SCOPE_USERNAME)
+    .bind(username)
+    .bind(SCOPE_IP)
+    .bind(ip_address)
+    .fetch_one(pool)
+    .await?;
+
+    match locked_until {
+        Some(until) => Err(AppError::TooManyRequests {
+            message: "Too many failed login attempts, try again later".to_string(),
+            retry_after_secs: retry_after_secs(until),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Counts a failed attempt against the username and IP and applies backoff.
+pub async fn record_failure(
+    pool: &DbPool,
+    config: &LockoutConfig,
+    username: &str,
+    ip_address: Option<&str>,
+) -> Result<FailureOutcome> {
+    let mut outcome = FailureOutcome {
+        failed_attempts: 0,
+        lockouts: Vec::new(),
+    };
+
+    let mut keys = vec![(SCOPE_USERNAME, username, config.max_failures_per_username)];
+    if let Some(ip) = ip_address {
+        keys.push((SCOPE_IP, ip, config.max_failures_per_ip));
+    }
+
+    for (scope, key, max_failures) in keys {
+        let failed_attempts: i32 = sqlx::query_scalar(
+            r#"
+            INSERT INTO login_throttles (scope, key, failed_count, last_failed_at)
+            VALUES ($1, $2, 1, NOW())
+            ON CONFLICT (scope, key) DO UPDATE SET
+                failed_count = CASE
+                    WHEN login_throttles.last_failed_at < NOW() - make_interval(secs => $3) THEN 1
+                    ELSE login_throttles.failed_count + 1
+                END,
+                last_failed_at = NOW()
+            RETURNING failed_count
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(config.failure_window_secs as f64)
+        .fetch_one(pool)
+        .await?;
+
+        let locked_out = failed_attempts >= max_failures;
+        let delay_secs = if locked_out {
+            config.lockout_secs
+        } else {
+            let exponent = (failed_attempts - 1).clamp(0, 30) as u32;
+            config
+                .base_delay_secs
+                .saturating_mul(1_i64 << exponent)
+                .min(config.lockout_secs)
+        };
+
+        if delay_secs > 0 {
+            let locked_until = Utc::now() + Duration::seconds(delay_secs);
+            sqlx::query("UPDATE login_throttles SET locked_until = $1 WHERE scope = $2 AND key = $3")
+                .bind(locked_until)
+                .bind(scope)
+                .bind(key)
+                .execute(pool)
+                .await?;
+
+            if locked_out {
+                outcome.lockouts.push(Lockout {
+                    scope,
+                    key: key.to_string(),
+                    failed_attempts,
+                    locked_until,
+                });
+            }
+        }
+
+        if scope == SCOPE_USERNAME {
+            outcome.failed_attempts = failed_attempts;
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Forgets the failures recorded for a key, returning whether any existed.
+pub async fn clear(pool: &DbPool, scope: &str, key: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM login_throttles WHERE scope = $1 AND key = $2")
+        .bind(scope)
+        .bind(key)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+fn retry_after_secs(until: DateTime<Utc>) -> u64 {
+    let remaining = until - Utc::now();
+    // Round up so clients never retry a moment too early
+    (remaining.num_milliseconds().max(0) as u64).div_ceil(1000).max(1)
+}
+
@@ -0,0 +1,21 @@
+pub mod account_deletion;
+pub mod api_key;
+pub mod audit;
+pub mod config;
+pub mod cookies;
+pub mod email_verification;
+pub mod jwt;
+pub mod lockout;
+pub mod oidc;
+pub mod ownership;
+pub mod password;
+pub mod password_reset;
+pub mod roles;
+pub mod session;
+pub mod two_factor;
+
+pub use config::{
+    CookieAuthConfig, JwtConfig, JwtKeyConfig, LockoutConfig, OidcConfig, OidcProviderConfig,
+    PasswordHashConfig,
+};
+pub use jwt::{Claims, JwtKeys};
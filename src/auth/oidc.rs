@@ -0,0 +1,348 @@
+//! OpenID Connect login through external identity providers, using the
+//! authorization code flow with PKCE.
+//!
+//! [`OidcClient::begin`] stores a login state (CSRF `state`, `nonce` and PKCE
+//! verifier) and returns the provider's authorization URL along with the
+//! `state`, which the caller also binds to the browser. The provider sends
+//! the browser back with a code, which [`OidcClient::complete`] exchanges for
+//! an ID token and validates against the provider's published keys.
+//! Discovery documents and key sets are cached; an unknown `kid` refetches the
+//! key set once to pick up key rotation.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use url::Url;
+
+use crate::{
+    auth::{
+        config::{OidcConfig, OidcProviderConfig},
+        session::{generate_token, hash_token},
+    },
+    db::DbPool,
+    error::{AppError, Result},
+};
+
+/// The ID token claims used to identify and provision the user.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    nonce: Option<String>,
+    azp: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+struct Provider {
+    config: OidcProviderConfig,
+    metadata: RwLock<Option<Arc<ProviderMetadata>>>,
+    jwks: RwLock<JwkSet>,
+}
+
+pub struct OidcClient {
+    http: reqwest::Client,
+    providers: HashMap<String, Provider>,
+    default_redirect_base: String,
+    login_ttl_secs: i64,
+}
+
+impl OidcClient {
+    pub fn new(config: &OidcConfig, public_url: &str) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+        let providers = config
+            .providers
+            .iter()
+            .map(|provider| {
+                (
+                    provider.name.clone(),
+                    Provider {
+                        config: provider.clone(),
+                        metadata: RwLock::new(None),
+                        jwks: RwLock::new(JwkSet { keys: Vec::new() }),
+                    },
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            http,
+            providers,
+            default_redirect_base: public_url.trim_end_matches('/').to_string(),
+            login_ttl_secs: config.login_ttl_secs,
+        })
+    }
+
+    /// Names of the configured providers, sorted.
+    pub fn provider_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.providers.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    pub fn provider_config(&self, name: &str) -> Result<&OidcProviderConfig> {
+        Ok(&self.provider(name)?.config)
+    }
+
+    fn provider(&self, name: &str) -> Result<&Provider> {
+        self.providers
+            .get(name)
+            .ok_or_else(|| AppError::NotFound(format!("Identity provider '{}' not found", name)))
+    }
+
+    fn redirect_url(&self, provider: &Provider) -> String {
+        provider.config.redirect_url.clone().unwrap_or_else(|| {
+            format!(
+                "{}/api/auth/oidc/{}/callback",
+                self.default_redirect_base, provider.config.name
+            )
+        })
+    }
+
+    /// Starts a login and returns the URL to send the browser to, and the
+    /// `state` the provider will send back.
+    pub async fn begin(&self, pool: &DbPool, name: &str) -> Result<(String, String)> {
+        let provider = self.provider(name)?;
+        let metadata = self.metadata(provider).await?;
+        let redirect_url = self.redirect_url(provider);
+
+        let (state, state_hash) = generate_token();
+        let (nonce, _) = generate_token();
+        let (code_verifier, _) = generate_token();
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < NOW()")
+            .execute(pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oidc_login_states (state_hash, provider, nonce, code_verifier, redirect_url, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&state_hash)
+        .bind(name)
+        .bind(&nonce)
+        .bind(&code_verifier)
+        .bind(&redirect_url)
+        .bind(Utc::now() + Duration::seconds(self.login_ttl_secs))
+        .execute(pool)
+        .await?;
+
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", provider.config.client_id.as_str()),
+                ("redirect_uri", redirect_url.as_str()),
+                ("scope", provider.config.scopes.join(" ").as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| AppError::Internal(format!("Invalid authorization endpoint: {}", e)))?;
+
+        Ok((url.into(), state))
+    }
+
+    pub fn login_ttl_secs(&self) -> i64 {
+        self.login_ttl_secs
+    }
+
+    /// Finishes a login started by [`begin`](Self::begin): consumes the state,
+    /// redeems the code and returns the validated ID token claims.
+    pub async fn complete(
+        &self,
+        pool: &DbPool,
+        name: &str,
+        code: &str,
+        state: &str,
+    ) -> Result<IdTokenClaims> {
+        let provider = self.provider(name)?;
+
+        let login: Option<(String, String, String, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            DELETE FROM oidc_login_states WHERE state_hash = $1 AND provider = $2
+            RETURNING nonce, code_verifier, redirect_url, expires_at
+            "#,
+        )
+        .bind(hash_token(state))
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+
+        let (nonce, code_verifier, redirect_url, _) = login
+            .filter(|(_, _, _, expires_at)| *expires_at > Utc::now())
+            .ok_or_else(|| AppError::BadRequest("Invalid or expired login state".to_string()))?;
+
+        let metadata = self.metadata(provider).await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_url.as_str()),
+            ("client_id", provider.config.client_id.as_str()),
+            ("code_verifier", code_verifier.as_str()),
+        ];
+        if let Some(secret) = &provider.config.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| unreachable_provider(name, e))?;
+
+        if !response.status().is_success() {
+            tracing::warn!(provider = name, status = %response.status(), "OIDC token exchange failed");
+            return Err(AppError::Unauthorized("Identity provider rejected the login".to_string()));
+        }
+
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| unreachable_provider(name, e))?;
+
+        self.validate_id_token(provider, &metadata, &tokens.id_token, &nonce).await
+    }
+
+    async fn validate_id_token(
+        &self,
+        provider: &Provider,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims> {
+        let invalid = || AppError::Unauthorized("Invalid ID token".to_string());
+
+        let header = decode_header(id_token).map_err(|_| invalid())?;
+        // Symmetric algorithms would make the client secret the verification key
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(invalid());
+        }
+
+        let jwk = self.jwk(provider, metadata, header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&jwk).map_err(|_| invalid())?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&provider.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| {
+                tracing::warn!(provider = %provider.config.name, "ID token rejected: {}", e);
+                invalid()
+            })?;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid());
+        }
+        if claims.azp.as_deref().is_some_and(|azp| azp != provider.config.client_id) {
+            return Err(invalid());
+        }
+
+        Ok(claims)
+    }
+
+    async fn metadata(&self, provider: &Provider) -> Result<Arc<ProviderMetadata>> {
+        if let Some(metadata) = provider.metadata.read().unwrap().clone() {
+            return Ok(metadata);
+        }
+
+        let name = &provider.config.name;
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.config.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| unreachable_provider(name, e))?
+            .json()
+            .await
+            .map_err(|e| unreachable_provider(name, e))?;
+
+        // The discovery document must describe the issuer it was fetched from
+        if metadata.issuer.trim_end_matches('/') != provider.config.issuer.trim_end_matches('/') {
+            return Err(AppError::Internal(format!(
+                "Identity provider '{}' reported issuer '{}'",
+                name, metadata.issuer
+            )));
+        }
+
+        let metadata = Arc::new(metadata);
+        *provider.metadata.write().unwrap() = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    async fn jwk(&self, provider: &Provider, metadata: &ProviderMetadata, kid: Option<&str>) -> Result<Jwk> {
+        let find = |jwks: &JwkSet| match kid {
+            Some(kid) => jwks.find(kid).cloned(),
+            None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+            None => None,
+        };
+
+        if let Some(jwk) = find(&provider.jwks.read().unwrap()) {
+            return Ok(jwk);
+        }
+
+        let name = &provider.config.name;
+        let jwks: JwkSet = self
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| unreachable_provider(name, e))?
+            .json()
+            .await
+            .map_err(|e| unreachable_provider(name, e))?;
+
+        let jwk = find(&jwks);
+        *provider.jwks.write().unwrap() = jwks;
+        jwk.ok_or_else(|| AppError::Unauthorized("Invalid ID token".to_string()))
+    }
+}
+
+fn unreachable_provider(name: &str, error: reqwest::Error) -> AppError {
+    AppError::Internal(format!("Identity provider '{}' request failed: {}", name, error))
+}
@@ -0,0 +1,93 @@
+//! Resource ownership checks shared by every handler in `handlers/*`.
+//!
+//! Resources owned by someone else are reported as missing so that callers
+//! cannot probe for the existence of other users' data. User profiles are the
+//! exception: their ids are not secret, so access is refused with 403.
+//!
+//! Todos, categories and tags in the trash are treated as missing too; only
+//! `handlers::trash` reaches them.
+
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    middleware::auth::AuthUser,
+    models::{Category, Tag, Todo},
+};
+
+pub async fn owned_todo<'e, E: PgExecutor<'e>>(
+    executor: E,
+    auth: &AuthUser,
+    todo_id: Uuid,
+) -> Result<Todo> {
+    sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL")
+        .bind(todo_id)
+        .bind(auth.id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Todo with id {} not found", todo_id)))
+}
+
+/// Like [`owned_todo`], but also locks the todo until the transaction ends.
+pub async fn locked_todo<'e, E: PgExecutor<'e>>(
+    executor: E,
+    auth: &AuthUser,
+    todo_id: Uuid,
+) -> Result<Todo> {
+    sqlx::query_as::<_, Todo>(
+        "SELECT * FROM todos WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL FOR UPDATE"
+    )
+    .bind(todo_id)
+    .bind(auth.id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Todo with id {} not found", todo_id)))
+}
+
+pub async fn owned_category<'e, E: PgExecutor<'e>>(
+    executor: E,
+    auth: &AuthUser,
+    category_id: Uuid,
+) -> Result<Category> {
+    sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL")
+        .bind(category_id)
+        .bind(auth.id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Category with id {} not found", category_id)))
+}
+
+pub async fn owned_tag<'e, E: PgExecutor<'e>>(
+    executor: E,
+    auth: &AuthUser,
+    tag_id: Uuid,
+) -> Result<Tag> {
+    sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL")
+        .bind(tag_id)
+        .bind(auth.id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Tag with id {} not found", tag_id)))
+}
+
+/// Validates an optional `category_id` taken from a request body.
+pub async fn ensure_category_reference<'e, E: PgExecutor<'e>>(
+    executor: E,
+    auth: &AuthUser,
+    category_id: Option<Uuid>,
+) -> Result<()> {
+    if let Some(category_id) = category_id {
+        owned_category(executor, auth, category_id).await?;
+    }
+    Ok(())
+}
+
+pub fn ensure_self(auth: &AuthUser, user_id: Uuid) -> Result<()> {
+    if auth.id != user_id {
+        return Err(AppError::Forbidden(
+            "You do not have access to this user".to_string(),
+        ));
+    }
+    Ok(())
+}
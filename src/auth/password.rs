@@ -0,0 +1,158 @@
+//! Password hashing. Handlers only see the [`PasswordHasher`] trait, so the
+//! scheme or its cost can change without touching them: hashes are stored in
+//! PHC format with their parameters, and anything weaker than the current
+//! configuration is reported for rehashing on the next login.
+//!
+//! Hashing is slow on purpose, so handlers go through [`AsyncPasswordHasher`],
+//! which runs it on the blocking thread pool instead of the async workers.
+
+use std::sync::{Arc, OnceLock};
+
+use argon2::{
+    password_hash::{
+        rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString,
+    },
+    Algorithm, Argon2, Params, Version,
+};
+
+use crate::{
+    auth::config::PasswordHashConfig,
+    error::{AppError, Result},
+};
+
+/// The outcome of checking a password against a stored hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordCheck {
+    Invalid,
+    Valid,
+    /// Correct, but the hash uses an older scheme or weaker parameters and
+    /// should be replaced with a fresh one.
+    ValidNeedsRehash,
+}
+
+impl PasswordCheck {
+    pub fn is_valid(self) -> bool {
+        self != PasswordCheck::Invalid
+    }
+}
+
+pub trait PasswordHasher: Send + Sync {
+    fn hash(&self, password: &str) -> Result<String>;
+
+    fn verify(&self, password: &str, hash: &str) -> Result<PasswordCheck>;
+}
+
+/// Argon2id for new hashes. Bcrypt hashes from before the switch still verify
+/// and are always reported for rehashing.
+pub struct Argon2Hasher {
+    params: Params,
+}
+
+impl Argon2Hasher {
+    pub fn new(config: &PasswordHashConfig) -> Result<Self> {
+        let params = Params::new(config.memory_kib, config.iterations, config.parallelism, None)
+            .map_err(|e| AppError::Internal(format!("Invalid password hash parameters: {}", e)))?;
+
+        Ok(Self { params })
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    fn is_current(&self, hash: &PasswordHash) -> bool {
+        if hash.algorithm != Algorithm::Argon2id.ident() {
+            return false;
+        }
+
+        match Params::try_from(hash) {
+            Ok(params) => {
+                params.m_cost() >= self.params.m_cost()
+                    && params.t_cost() >= self.params.t_cost()
+                    && params.p_cost() >= self.params.p_cost()
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl PasswordHasher for Argon2Hasher {
+    fn hash(&self, password: &str) -> Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<PasswordCheck> {
+        if hash.starts_with("$2") {
+            let is_valid = bcrypt::verify(password, hash)
+                .map_err(|e| AppError::Internal(format!("Failed to verify password: {}", e)))?;
+
+            return Ok(if is_valid { PasswordCheck::ValidNeedsRehash } else { PasswordCheck::Invalid });
+        }
+
+        let parsed = PasswordHash::new(hash)
+            .map_err(|e| AppError::Internal(format!("Failed to parse password hash: {}", e)))?;
+
+        // Verification uses the parameters stored in the hash, not the configured ones
+        if self.argon2().verify_password(password.as_bytes(), &parsed).is_err() {
+            return Ok(PasswordCheck::Invalid);
+        }
+
+        Ok(if self.is_current(&parsed) { PasswordCheck::Valid } else { PasswordCheck::ValidNeedsRehash })
+    }
+}
+
+/// Runs a [`PasswordHasher`] on the blocking thread pool.
+#[derive(Clone)]
+pub struct AsyncPasswordHasher {
+    hasher: Arc<dyn PasswordHasher>,
+    /// Checked against when there is no account, so that unknown usernames
+    /// take as long to reject as wrong passwords. Hashed on first use.
+    dummy_hash: Arc<OnceLock<String>>,
+}
+
+impl AsyncPasswordHasher {
+    pub fn new(hasher: impl PasswordHasher + 'static) -> Self {
+        Self { hasher: Arc::new(hasher), dummy_hash: Arc::new(OnceLock::new()) }
+    }
+
+    pub async fn hash(&self, password: &str) -> Result<String> {
+        let hasher = self.hasher.clone();
+        let password = password.to_string();
+        blocking(move || hasher.hash(&password)).await
+    }
+
+    pub async fn verify(&self, password: &str, hash: &str) -> Result<PasswordCheck> {
+        let hasher = self.hasher.clone();
+        let (password, hash) = (password.to_string(), hash.to_string());
+        blocking(move || hasher.verify(&password, &hash)).await
+    }
+
+    /// Does the work of a failed check without an account to check against.
+    pub async fn verify_dummy(&self, password: &str) -> Result<()> {
+        let hasher = self.hasher.clone();
+        let dummy_hash = self.dummy_hash.clone();
+        let password = password.to_string();
+        blocking(move || {
+            let hash = match dummy_hash.get() {
+                Some(hash) => hash,
+                None => {
+                    let hash = hasher.hash("not a real password")?;
+                    dummy_hash.get_or_init(|| hash)
+                }
+            };
+            hasher.verify(&password, hash).map(|_| ())
+        })
+        .await
+    }
+}
+
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| AppError::Internal(format!("Password hashing task failed: {}", e)))?
+}
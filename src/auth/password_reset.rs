@@ -0,0 +1,49 @@
+//! Forgot-password tokens. Like refresh tokens they are opaque, stored only
+//! as hashes and single-use; requesting a new one invalidates older ones.
+
+use chrono::{Duration, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    auth::session::{generate_token, hash_token},
+    db::DbPool,
+    error::{AppError, Result},
+};
+
+/// Issues a reset token for `user_id` and returns it in plain text for the email.
+pub async fn issue(pool: &DbPool, user_id: Uuid, ttl_secs: i64) -> Result<String> {
+    let (token, token_hash) = generate_token();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)")
+        .bind(user_id)
+        .bind(&token_hash)
+        .bind(Utc::now() + Duration::seconds(ttl_secs))
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(token)
+}
+
+/// Marks a token as used and returns the user it was issued for. Run this in
+/// the same transaction that changes the password.
+pub async fn consume(conn: &mut PgConnection, token: &str) -> Result<Uuid> {
+    sqlx::query_scalar(
+        r#"
+        UPDATE password_reset_tokens SET used_at = NOW()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Invalid or expired reset token".to_string()))
+}
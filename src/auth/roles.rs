@@ -0,0 +1,54 @@
+//! Account roles are stored in `users.role` and carried in access tokens, so
+//! a change takes effect the next time the user's tokens are refreshed.
+
+use crate::{db::DbPool, error::Result, models::Role};
+
+/// What [`grant_bootstrap_admins`] did with the listed usernames.
+#[derive(Debug, Default)]
+pub struct BootstrapAdmins {
+    pub promoted: Vec<String>,
+    /// Listed names without an active account with a verified email address.
+    pub not_found: Vec<String>,
+}
+
+/// Grants the admin role to the listed usernames so that a fresh deployment
+/// gets its first administrator. Does nothing, returning `None`, once any
+/// admin exists: names registered later and admins who were demoted are never
+/// promoted by a restart. Only active accounts with a verified email address
+/// are promoted.
+pub async fn grant_bootstrap_admins(pool: &DbPool, usernames: &[String]) -> Result<Option<BootstrapAdmins>> {
+    if usernames.is_empty() {
+        return Ok(None);
+    }
+
+    let mut tx = pool.begin().await?;
+    // Instances starting together take turns, so only the first one promotes
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::text, 0))")
+        .bind("bootstrap_admins")
+        .execute(&mut *tx)
+        .await?;
+
+    let has_admin: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE role = $1)")
+        .bind(Role::Admin)
+        .fetch_one(&mut *tx)
+        .await?;
+    if has_admin {
+        return Ok(None);
+    }
+
+    let promoted: Vec<String> = sqlx::query_scalar(
+        r#"
+        UPDATE users SET role = $1
+        WHERE username = ANY($2) AND is_active AND email_verified_at IS NOT NULL
+        RETURNING username
+        "#,
+    )
+    .bind(Role::Admin)
+    .bind(usernames)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let not_found = usernames.iter().filter(|name| !promoted.contains(name)).cloned().collect();
+    Ok(Some(BootstrapAdmins { promoted, not_found }))
+}
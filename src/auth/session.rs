@@ -0,0 +1,239 @@
+//! Login sessions and rotating refresh tokens.
+//!
+//! A session is a refresh token family: every successful refresh marks the
+//! presented token as used and issues a new one in the same session. Presenting
+//! an already-used token means it was stolen or replayed, so the whole session
+//! is revoked, which also invalidates its access tokens.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    error::{AppError, Result},
+    middleware::client::ClientInfo,
+    models::{RefreshToken, Session},
+};
+
+pub const REVOKED_LOGOUT: &str = "logout";
+pub const REVOKED_REUSE_DETECTED: &str = "reuse_detected";
+pub const REVOKED_BY_USER: &str = "revoked_by_user";
+pub const REVOKED_PASSWORD_CHANGED: &str = "password_changed";
+pub const REVOKED_PASSWORD_RESET: &str = "password_reset";
+pub const REVOKED_BY_ADMIN: &str = "revoked_by_admin";
+pub const REVOKED_DEACTIVATED: &str = "account_deactivated";
+pub const REVOKED_ACCOUNT_DELETION: &str = "account_deletion";
+
+/// `last_seen_at` is refreshed at most this often to keep writes down.
+const LAST_SEEN_RESOLUTION_SECS: i64 = 60;
+
+/// Returns a new opaque token and the hash that gets stored.
+pub fn generate_token() -> (String, String) {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+    let hash = hash_token(&token);
+    (token, hash)
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Starts a new session and returns it with its first refresh token.
+pub async fn start(
+    pool: &DbPool,
+    user_id: Uuid,
+    client: &ClientInfo,
+    ttl_secs: i64,
+) -> Result<(Session, String)> {
+    let expires_at = Utc::now() + Duration::seconds(ttl_secs);
+    let mut tx = pool.begin().await?;
+
+    let session = sqlx::query_as::<_, Session>(
+        r#"
+        INSERT INTO sessions (user_id, expires_at, user_agent, ip_address)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(expires_at)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let (token, token_hash) = generate_token();
+    sqlx::query("INSERT INTO refresh_tokens (session_id, token_hash, expires_at) VALUES ($1, $2, $3)")
+        .bind(session.id)
+        .bind(&token_hash)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok((session, token))
+}
+
+/// Exchanges a refresh token for a new one in the same session.
+pub async fn rotate(pool: &DbPool, token: &str) -> Result<(Session, String)> {
+    let invalid = || AppError::Unauthorized("Invalid refresh token".to_string());
+    let mut tx = pool.begin().await?;
+
+    let refresh_token = sqlx::query_as::<_, RefreshToken>(
+        "SELECT * FROM refresh_tokens WHERE token_hash = $1 FOR UPDATE"
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(invalid)?;
+
+    let session = sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE id = $1 FOR UPDATE")
+        .bind(refresh_token.session_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if session.revoked_at.is_some() {
+        return Err(invalid());
+    }
+
+    if refresh_token.used_at.is_some() {
+        sqlx::query("UPDATE sessions SET revoked_at = NOW(), revoked_reason = $1 WHERE id = $2")
+            .bind(REVOKED_REUSE_DETECTED)
+            .bind(session.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        tracing::warn!(session_id = %session.id, user_id = %session.user_id, "Refresh token reuse detected, session revoked");
+        return Err(invalid());
+    }
+
+    let now = Utc::now();
+    if refresh_token.expires_at <= now || session.expires_at <= now {
+        return Err(AppError::Unauthorized("Refresh token expired".to_string()));
+    }
+
+    sqlx::query("UPDATE refresh_tokens SET used_at = $1 WHERE id = $2")
+        .bind(now)
+        .bind(refresh_token.id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE sessions SET last_seen_at = $1 WHERE id = $2")
+        .bind(now)
+        .bind(session.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let (new_token, new_hash) = generate_token();
+    sqlx::query("INSERT INTO refresh_tokens (session_id, token_hash, expires_at) VALUES ($1, $2, $3)")
+        .bind(session.id)
+        .bind(&new_hash)
+        .bind(session.expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok((session, new_token))
+}
+
+/// Revokes the session a refresh token belongs to. Unknown tokens are ignored.
+pub async fn revoke_by_token(pool: &DbPool, token: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = NOW(), revoked_reason = $1
+        WHERE revoked_at IS NULL
+          AND id = (SELECT session_id FROM refresh_tokens WHERE token_hash = $2)
+        "#,
+    )
+    .bind(REVOKED_LOGOUT)
+    .bind(hash_token(token))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records activity on a session and reports whether access tokens issued
+/// for it are still acceptable.
+pub async fn touch(pool: &DbPool, session_id: Uuid) -> Result<bool> {
+    let last_seen_at: Option<chrono::DateTime<Utc>> = sqlx::query_scalar(
+        "SELECT last_seen_at FROM sessions WHERE id = $1 AND revoked_at IS NULL AND expires_at > NOW()"
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(last_seen_at) = last_seen_at else {
+        return Ok(false);
+    };
+
+    if Utc::now() - last_seen_at > Duration::seconds(LAST_SEEN_RESOLUTION_SECS) {
+        sqlx::query("UPDATE sessions SET last_seen_at = NOW() WHERE id = $1")
+            .bind(session_id)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(true)
+}
+
+pub async fn list_active(pool: &DbPool, user_id: Uuid) -> Result<Vec<Session>> {
+    let sessions = sqlx::query_as::<_, Session>(
+        r#"
+        SELECT * FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY last_seen_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// Revokes one of the user's sessions, returning whether it existed.
+pub async fn revoke(pool: &DbPool, user_id: Uuid, session_id: Uuid, reason: &str) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = NOW(), revoked_reason = $1
+        WHERE id = $2 AND user_id = $3 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(reason)
+    .bind(session_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revokes every live session of the user except `keep`, returning how many were revoked.
+pub async fn revoke_all<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: Uuid,
+    keep: Option<Uuid>,
+    reason: &str,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = NOW(), revoked_reason = $1
+        WHERE user_id = $2 AND revoked_at IS NULL AND id IS DISTINCT FROM $3
+        "#,
+    )
+    .bind(reason)
+    .bind(user_id)
+    .bind(keep)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,166 @@
+//! RFC 6238 TOTP two-factor authentication and one-time recovery codes.
+//!
+//! When 2FA is enabled, a correct password only earns a short-lived challenge
+//! token; the session is started once a TOTP or recovery code is submitted
+//! with it.
+
+use chrono::{Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::{
+    auth::{session::hash_token, JwtKeys},
+    db::DbPool,
+    error::{AppError, Result},
+    models::User,
+};
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const CHALLENGE_PURPOSE: &str = "two_factor_challenge";
+const STEP_SECS: u64 = 30;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallengeClaims {
+    pub sub: String,
+    pub purpose: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+/// A new random base32 secret.
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+pub fn totp(secret: &str, issuer: &str, account_name: &str) -> Result<TOTP> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::Internal(format!("Invalid TOTP secret: {:?}", e)))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        STEP_SECS,
+        bytes,
+        Some(issuer.to_string()),
+        account_name.to_string(),
+    )
+    .map_err(|e| AppError::Internal(format!("Invalid TOTP parameters: {}", e)))
+}
+
+/// Returns the time step `code` belongs to, allowing one step of clock skew.
+fn matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let current = Utc::now().timestamp() as u64 / STEP_SECS;
+    [current - 1, current, current + 1]
+        .into_iter()
+        .find(|step| totp.generate(step * STEP_SECS) == code)
+        .map(|step| step as i64)
+}
+
+/// Checks a TOTP code against the user's secret and consumes its time step.
+pub async fn verify_totp(pool: &DbPool, user: &User, issuer: &str, code: &str) -> Result<bool> {
+    let Some(secret) = &user.totp_secret else {
+        return Ok(false);
+    };
+    let Some(step) = matching_step(&totp(secret, issuer, &user.username)?, code.trim()) else {
+        return Ok(false);
+    };
+
+    let result = sqlx::query(
+        r#"
+        UPDATE users SET totp_last_used_step = $1
+        WHERE id = $2 AND (totp_last_used_step IS NULL OR totp_last_used_step < $1)
+        "#,
+    )
+    .bind(step)
+    .bind(user.id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Accepts either a TOTP code or an unused recovery code.
+pub async fn verify_code(pool: &DbPool, user: &User, issuer: &str, code: &str) -> Result<bool> {
+    if verify_totp(pool, user, issuer, code).await? {
+        return Ok(true);
+    }
+
+    let result = sqlx::query(
+        "UPDATE recovery_codes SET used_at = NOW() WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"
+    )
+    .bind(user.id)
+    .bind(hash_recovery_code(code))
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Replaces all of the user's recovery codes and returns the new ones in plain text.
+pub async fn replace_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<String>> {
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+    for code in &codes {
+        sqlx::query("INSERT INTO recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(hash_recovery_code(code))
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(codes)
+}
+
+fn generate_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let chars: String = (0..10)
+        .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+/// Recovery codes are compared case-insensitively and without the dash.
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+pub fn issue_challenge(jwt: &JwtKeys, user_id: Uuid, ttl_secs: i64) -> Result<String> {
+    let now = Utc::now();
+    let claims = ChallengeClaims {
+        sub: user_id.to_string(),
+        purpose: CHALLENGE_PURPOSE.to_string(),
+        iss: jwt.issuer().to_string(),
+        aud: jwt.audience().to_string(),
+        iat: now.timestamp() as usize,
+        exp: (now + Duration::seconds(ttl_secs)).timestamp() as usize,
+    };
+    jwt.encode(&claims)
+}
+
+/// Returns the user a challenge token was issued for.
+pub fn verify_challenge(jwt: &JwtKeys, token: &str) -> Result<Uuid> {
+    let invalid = || AppError::Unauthorized("Invalid or expired two-factor challenge".to_string());
+
+    let claims = jwt.decode::<ChallengeClaims>(token).map_err(|_| invalid())?;
+    if claims.purpose != CHALLENGE_PURPOSE {
+        return Err(invalid());
+    }
+    claims.sub.parse::<Uuid>().map_err(|_| invalid())
+}
@@ -1,6 +1,42 @@
+use hmac::{Hmac, Mac};
 use serde::Deserialize;
-use std::env;
+use sha2::Sha256;
+use std::{env, fs};
+use crate::auth::{CookieAuthConfig, JwtConfig, LockoutConfig, OidcConfig, PasswordHashConfig};
 use crate::kafka::KafkaConfig;
+use crate::mail::MailConfig;
+use crate::models::SubtaskDeletion;
+
+/// How completing and deleting todos affects their subtasks.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubtaskConfig {
+    /// Completing a todo also completes all of its subtasks.
+    pub complete_children: bool,
+    /// A todo is completed when its last open subtask is, and reopened when
+    /// one of its subtasks is reopened or added.
+    pub auto_complete_parent: bool,
+    /// Applies when a delete request does not choose a rule.
+    pub on_parent_delete: SubtaskDeletion,
+}
+
+impl SubtaskConfig {
+    pub fn from_env() -> Self {
+        Self {
+            complete_children: env::var("SUBTASKS_COMPLETE_CHILDREN")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            auto_complete_parent: env::var("SUBTASKS_AUTO_COMPLETE_PARENT")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            on_parent_delete: match env::var("SUBTASKS_ON_PARENT_DELETE").as_deref() {
+                Ok("reparent") => SubtaskDeletion::Reparent,
+                _ => SubtaskDeletion::Cascade,
+            },
+        }
+    }
+}
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -8,7 +44,45 @@ pub struct Config {
     pub server_host: String,
     pub server_port: u16,
     pub rust_log: String,
+    pub trust_proxy_headers: bool,
     pub kafka: KafkaConfig,
+    pub jwt: JwtConfig,
+    pub mail: MailConfig,
+    pub password_reset_ttl_secs: i64,
+    /// Front-end page that accepts `?token=`; when unset emails contain only the token.
+    pub password_reset_url: Option<String>,
+    /// Base URL of this server as seen by users, used in emailed links.
+    pub public_url: String,
+    pub email_verification_ttl_secs: i64,
+    /// Refuse logins until the account's email address has been verified.
+    pub require_email_verification: bool,
+    pub lockout: LockoutConfig,
+    pub password_hash: PasswordHashConfig,
+    pub oidc: OidcConfig,
+    pub cookie_auth: CookieAuthConfig,
+    /// Origins allowed to make credentialed cross-origin requests. Without any,
+    /// every origin may call the API but browsers will not send cookies.
+    pub cors_allowed_origins: Vec<String>,
+    /// Issuer shown by authenticator apps for TOTP enrollments.
+    pub totp_issuer: String,
+    pub two_factor_challenge_ttl_secs: i64,
+    /// Usernames granted the admin role at startup while there is no admin
+    /// yet, to bootstrap the first administrators.
+    pub admin_usernames: Vec<String>,
+    /// How long a deleted account stays disabled before its data is purged.
+    pub account_deletion_grace_secs: i64,
+    pub account_purge_interval_secs: u64,
+    /// How often the scheduler looks for due reminders and overdue todos.
+    pub reminder_scan_interval_secs: u64,
+    /// How long deleted todos, categories and tags stay in the trash.
+    pub trash_retention_secs: i64,
+    pub trash_purge_interval_secs: u64,
+    /// Refuse todo updates and deletes that do not send `If-Match`.
+    pub require_if_match: bool,
+    /// Signs pagination cursors, so clients cannot forge them. Derived from
+    /// the JWT signing key when `CURSOR_SECRET` is not set.
+    pub cursor_secret: String,
+    pub subtasks: SubtaskConfig,
 }
 
 impl Config {
@@ -55,20 +129,114 @@ impl Config {
                 .unwrap_or(5000),
         };
 
+        let server_host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let server_port = env::var("SERVER_PORT")
+            .unwrap_or_else(|_| "3000".to_string())
+            .parse()
+            .unwrap_or(3000);
+
+        let jwt = JwtConfig::from_env();
+        let cursor_secret = match env::var("CURSOR_SECRET") {
+            Ok(secret) => secret,
+            Err(_) => derive_cursor_secret(&jwt).ok_or(env::VarError::NotPresent)?,
+        };
+
         Ok(Config {
             database_url: env::var("DATABASE_URL")
                 .unwrap_or_else(|_| "postgres://localhost/todos".to_string()),
-            server_host: env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-            server_port: env::var("SERVER_PORT")
-                .unwrap_or_else(|_| "3000".to_string())
-                .parse()
-                .unwrap_or(3000),
+            public_url: env::var("PUBLIC_URL")
+                .unwrap_or_else(|_| format!("http://{}:{}", server_host, server_port)),
+            server_host,
+            server_port,
             rust_log: env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+            trust_proxy_headers: env::var("TRUST_PROXY_HEADERS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
             kafka: kafka_config,
+            jwt,
+            mail: MailConfig::from_env(),
+            password_reset_ttl_secs: env::var("PASSWORD_RESET_TTL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            password_reset_url: env::var("PASSWORD_RESET_URL").ok(),
+            email_verification_ttl_secs: env::var("EMAIL_VERIFICATION_TTL_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+            require_email_verification: env::var("REQUIRE_EMAIL_VERIFICATION")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            lockout: LockoutConfig::from_env(),
+            password_hash: PasswordHashConfig::from_env(),
+            oidc: OidcConfig::from_env(),
+            cookie_auth: CookieAuthConfig::from_env(),
+            cors_allowed_origins: env::var("CORS_ALLOWED_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect(),
+            totp_issuer: env::var("TOTP_ISSUER").unwrap_or_else(|_| "axum-server".to_string()),
+            two_factor_challenge_ttl_secs: env::var("TWO_FACTOR_CHALLENGE_TTL_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            admin_usernames: env::var("ADMIN_USERNAMES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+            account_deletion_grace_secs: env::var("ACCOUNT_DELETION_GRACE_SECS")
+                .unwrap_or_else(|_| "2592000".to_string())
+                .parse()
+                .unwrap_or(2592000),
+            account_purge_interval_secs: env::var("ACCOUNT_PURGE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            reminder_scan_interval_secs: env::var("REMINDER_SCAN_INTERVAL_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            trash_retention_secs: env::var("TRASH_RETENTION_SECS")
+                .unwrap_or_else(|_| "2592000".to_string())
+                .parse()
+                .unwrap_or(2592000),
+            trash_purge_interval_secs: env::var("TRASH_PURGE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            require_if_match: env::var("REQUIRE_IF_MATCH")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            cursor_secret,
+            subtasks: SubtaskConfig::from_env(),
         })
     }
 
     pub fn server_address(&self) -> String {
         format!("{}:{}", self.server_host, self.server_port)
     }
+}
+
+/// A cursor secret bound to the JWT signing key's secret or private key, so
+/// that it is as hard to guess. `None` when the key has neither.
+fn derive_cursor_secret(jwt: &JwtConfig) -> Option<String> {
+    let key = jwt.keys.iter().find(|key| key.kid == jwt.signing_kid)?;
+    let material = match (&key.secret, &key.private_key_path) {
+        (Some(secret), _) => secret.as_bytes().to_vec(),
+        (None, Some(path)) => fs::read(path).ok()?,
+        (None, None) => return None,
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&material).expect("HMAC takes keys of any size");
+    mac.update(b"pagination-cursors");
+    Some(hex::encode(mac.finalize().into_bytes()))
 }
\ No newline at end of file
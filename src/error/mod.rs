@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -28,6 +28,21 @@ pub enum AppError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// An `If-Match` precondition did not hold.
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    /// A conditional request was required but none was made.
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
+
+    /// Throttled; the response carries `Retry-After: retry_after_secs`.
+    #[error("Too many requests: {message}")]
+    TooManyRequests { message: String, retry_after_secs: u64 },
+
     #[error("Internal server error: {0}")]
     Internal(String),
 
@@ -47,6 +62,17 @@ impl IntoResponse for AppError {
             AppError::BadRequest(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
             AppError::Conflict(ref msg) => (StatusCode::CONFLICT, msg.as_str()),
             AppError::Unauthorized(ref msg) => (StatusCode::UNAUTHORIZED, msg.as_str()),
+            AppError::Forbidden(ref msg) => (StatusCode::FORBIDDEN, msg.as_str()),
+            AppError::PreconditionFailed(ref msg) => (StatusCode::PRECONDITION_FAILED, msg.as_str()),
+            AppError::PreconditionRequired(ref msg) => (StatusCode::PRECONDITION_REQUIRED, msg.as_str()),
+            AppError::TooManyRequests { ref message, retry_after_secs } => {
+                let body = Json(json!({
+                    "error": message,
+                    "retry_after": retry_after_secs,
+                }));
+                let retry_after = [(header::RETRY_AFTER, retry_after_secs.to_string())];
+                return (StatusCode::TOO_MANY_REQUESTS, retry_after, body).into_response();
+            }
             AppError::Internal(ref msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
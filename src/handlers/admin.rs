@@ -0,0 +1,317 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        account_deletion,
+        audit::{self, AuditEntry},
+        lockout, session,
+    },
+    db::DbPool,
+    error::{AppError, Result},
+    kafka::{AccountUnlockedEvent, AdminActionEvent},
+    middleware::auth::AuthUser,
+    models::{
+        AdminUserQuery, Role, RoleCount, SystemStatsResponse, UpdateRoleRequest, User,
+        UserListResponse, UserResponse,
+    },
+    routes::AppState,
+};
+
+async fn find_user(pool: &DbPool, user_id: Uuid) -> Result<User> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))
+}
+
+/// Administrators manage other accounts; changing their own could lock
+/// everybody out.
+fn ensure_not_self(admin: &AuthUser, user_id: Uuid) -> Result<()> {
+    if admin.id == user_id {
+        return Err(AppError::BadRequest("You cannot perform this action on your own account".to_string()));
+    }
+    Ok(())
+}
+
+/// Writes an admin action to the audit log and publishes it.
+async fn record_action(
+    state: &AppState,
+    admin: &AuthUser,
+    user: &User,
+    event_type: &str,
+    action: &str,
+    details: Option<String>,
+) -> Result<()> {
+    audit::record(
+        &state.db_pool,
+        AuditEntry {
+            event_type,
+            user_id: Some(user.id),
+            username: Some(&user.username),
+            details: Some(match &details {
+                Some(details) => format!("{} by {}", details, admin.username),
+                None => format!("by {}", admin.username),
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let event = AdminActionEvent {
+        action: action.to_string(),
+        user_id: user.id,
+        username: user.username.clone(),
+        performed_by: admin.id,
+        details,
+        performed_at: Utc::now(),
+    };
+    if let Err(e) = state.kafka_producer.publish_admin_action(event).await {
+        tracing::warn!("Failed to publish admin action event: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Lists accounts, optionally filtered by a search term, role and status.
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(params): Query<AdminUserQuery>,
+) -> Result<Json<UserListResponse>> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let search = params
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|search| !search.is_empty())
+        .map(|search| format!("%{}%", search));
+
+    let filter = r#"
+        WHERE ($1::TEXT IS NULL OR username ILIKE $1 OR email ILIKE $1 OR full_name ILIKE $1)
+          AND ($2::user_role IS NULL OR role = $2)
+          AND ($3::BOOLEAN IS NULL OR is_active = $3)
+    "#;
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM users {}", filter))
+        .bind(&search)
+        .bind(params.role)
+        .bind(params.is_active)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    let users = sqlx::query_as::<_, User>(&format!(
+        "SELECT * FROM users {} ORDER BY created_at DESC LIMIT $4 OFFSET $5",
+        filter
+    ))
+    .bind(&search)
+    .bind(params.role)
+    .bind(params.is_active)
+    .bind(per_page)
+    .bind(offset)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok(Json(UserListResponse {
+        users: users.into_iter().map(UserResponse::from).collect(),
+        total,
+        page,
+        per_page,
+    }))
+}
+
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserResponse>> {
+    Ok(Json(find_user(&state.db_pool, user_id).await?.into()))
+}
+
+/// Disables an account and signs it out everywhere. API keys stop working
+/// while the account is inactive.
+pub async fn deactivate_user(
+    State(state): State<AppState>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserResponse>> {
+    ensure_not_self(&admin, user_id)?;
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET is_active = false, updated_at = $1 WHERE id = $2 RETURNING *"
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))?;
+
+    session::revoke_all(&mut *tx, user_id, None, session::REVOKED_DEACTIVATED).await?;
+
+    tx.commit().await?;
+
+    record_action(&state, &admin, &user, audit::USER_DEACTIVATED, "deactivated", None).await?;
+    Ok(Json(user.into()))
+}
+
+/// Re-enables an account. A pending deletion request is withdrawn.
+pub async fn reactivate_user(
+    State(state): State<AppState>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserResponse>> {
+    let mut tx = state.db_pool.begin().await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET is_active = true, updated_at = $1 WHERE id = $2 RETURNING *"
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))?;
+
+    let deletion_cancelled = account_deletion::cancel(&mut *tx, user_id).await?;
+
+    tx.commit().await?;
+
+    let details = deletion_cancelled.then(|| "pending deletion cancelled".to_string());
+    record_action(&state, &admin, &user, audit::USER_REACTIVATED, "reactivated", details).await?;
+    Ok(Json(user.into()))
+}
+
+/// Changes an account's role. It applies once the user's access token is
+/// refreshed; revoke their sessions to apply it immediately.
+pub async fn update_user_role(
+    State(state): State<AppState>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Result<Json<UserResponse>> {
+    ensure_not_self(&admin, user_id)?;
+    let previous = find_user(&state.db_pool, user_id).await?.role;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET role = $1, updated_at = $2 WHERE id = $3 RETURNING *"
+    )
+    .bind(payload.role)
+    .bind(Utc::now())
+    .bind(user_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    let details = format!("role changed from {} to {}", previous.as_str(), user.role.as_str());
+    record_action(&state, &admin, &user, audit::ROLE_CHANGED, "role_changed", Some(details)).await?;
+    Ok(Json(user.into()))
+}
+
+/// Signs a user out of every session.
+pub async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let user = find_user(&state.db_pool, user_id).await?;
+
+    let revoked = session::revoke_all(&state.db_pool, user_id, None, session::REVOKED_BY_ADMIN).await?;
+
+    let details = format!("{} session(s) revoked", revoked);
+    record_action(&state, &admin, &user, audit::SESSIONS_REVOKED, "sessions_revoked", Some(details)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lifts a login lockout on a user before it expires.
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    admin: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let user = find_user(&state.db_pool, user_id).await?;
+
+    lockout::clear(&state.db_pool, lockout::SCOPE_USERNAME, &user.username).await?;
+
+    audit::record(
+        &state.db_pool,
+        AuditEntry {
+            event_type: audit::ACCOUNT_UNLOCKED,
+            user_id: Some(user.id),
+            username: Some(&user.username),
+            details: Some(format!("unlocked by {}", admin.username)),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let event = AccountUnlockedEvent {
+        user_id: user.id,
+        username: user.username,
+        unlocked_by: admin.id,
+        unlocked_at: Utc::now(),
+    };
+    if let Err(e) = state.kafka_producer.publish_account_unlocked(event).await {
+        tracing::warn!("Failed to publish account unlocked event: {}", e);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Counts across every account, unlike `/api/stats/todos`.
+pub async fn system_stats(State(state): State<AppState>) -> Result<Json<SystemStatsResponse>> {
+    let (total_users, active_users, verified_users): (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            COUNT(*),
+            COUNT(*) FILTER (WHERE is_active),
+            COUNT(*) FILTER (WHERE email_verified_at IS NOT NULL)
+        FROM users
+        "#,
+    )
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    let role_rows: Vec<(Role, i64)> = sqlx::query_as(
+        "SELECT role, COUNT(*) FROM users GROUP BY role ORDER BY role"
+    )
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let (active_sessions, logins_last_24h): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE revoked_at IS NULL AND expires_at > NOW()),
+            COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '24 hours')
+        FROM sessions
+        "#,
+    )
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    let (total_todos, completed_todos): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COUNT(*) FILTER (WHERE completed) FROM todos WHERE deleted_at IS NULL"
+    )
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    Ok(Json(SystemStatsResponse {
+        total_users,
+        active_users,
+        verified_users,
+        users_by_role: role_rows
+            .into_iter()
+            .map(|(role, count)| RoleCount { role, count })
+            .collect(),
+        active_sessions,
+        logins_last_24h,
+        total_todos,
+        completed_todos,
+    }))
+}
@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::api_key,
+    error::{AppError, Result},
+    middleware::auth::AuthUser,
+    models::{ApiKey, ApiKeyResponse, CreateApiKeyRequest, CreatedApiKeyResponse},
+    routes::AppState,
+};
+
+fn api_key_response(key: ApiKey) -> ApiKeyResponse {
+    ApiKeyResponse {
+        id: key.id,
+        name: key.name,
+        key_prefix: key.key_prefix,
+        scopes: key.scopes,
+        expires_at: key.expires_at,
+        last_used_at: key.last_used_at,
+        created_at: key.created_at,
+    }
+}
+
+/// Creates an API key. The key itself is only ever returned here.
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiKeyResponse>)> {
+    payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let (key, plain_key) = api_key::create(
+        &state.db_pool,
+        auth.id,
+        &payload.name,
+        &payload.scopes,
+        payload.expires_at,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKeyResponse {
+            api_key: api_key_response(key),
+            key: plain_key,
+        }),
+    ))
+}
+
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<ApiKeyResponse>>> {
+    let keys = api_key::list(&state.db_pool, auth.id).await?;
+
+    Ok(Json(keys.into_iter().map(api_key_response).collect()))
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !api_key::revoke(&state.db_pool, auth.id, id).await? {
+        return Err(AppError::NotFound(format!("API key with id {} not found", id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,415 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use jsonwebtoken::jwk::JwkSet;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        audit::{self, AuditEntry},
+        cookies, email_verification, lockout, password_reset, session, two_factor,
+    },
+    error::{AppError, Result},
+    kafka::{AccountLockedEvent, LoginFailedEvent, UserLoggedInEvent},
+    mail::{self, Email},
+    middleware::{auth::AuthUser, client::ClientInfo},
+    models::{
+        AuthResponse, ForgotPasswordRequest, LoginResponse, RefreshTokenRequest,
+        ResetPasswordRequest, TwoFactorChallengeResponse, User, UserResponse, VerifyEmailQuery,
+    },
+    routes::AppState,
+};
+
+/// Records a failed login, applies backoff and publishes the security events.
+/// Returns the error to answer the attempt with.
+pub(crate) async fn reject_login(
+    state: &AppState,
+    username: &str,
+    user_id: Option<Uuid>,
+    client: &ClientInfo,
+    reason: &str,
+) -> AppError {
+    match record_failed_login(state, username, user_id, client, reason).await {
+        Ok(()) => AppError::Unauthorized("Invalid credentials".to_string()),
+        Err(e) => e,
+    }
+}
+
+async fn record_failed_login(
+    state: &AppState,
+    username: &str,
+    user_id: Option<Uuid>,
+    client: &ClientInfo,
+    reason: &str,
+) -> Result<()> {
+    let ip_address = client.ip_address.as_deref();
+    let outcome = lockout::record_failure(
+        &state.db_pool,
+        &state.config.lockout,
+        username,
+        ip_address,
+    )
+    .await?;
+
+    audit::record(
+        &state.db_pool,
+        AuditEntry {
+            event_type: audit::LOGIN_FAILED,
+            user_id,
+            username: Some(username),
+            ip_address,
+            details: Some(reason.to_string()),
+        },
+    )
+    .await?;
+
+    let event = LoginFailedEvent {
+        username: username.to_string(),
+        user_id,
+        ip_address: client.ip_address.clone(),
+        reason: reason.to_string(),
+        failed_attempts: outcome.failed_attempts,
+        attempted_at: Utc::now(),
+    };
+    if let Err(e) = state.kafka_producer.publish_login_failed(event).await {
+        tracing::warn!("Failed to publish login failed event: {}", e);
+    }
+
+    for locked in outcome.lockouts {
+        let locked_user_id = if locked.scope == lockout::SCOPE_USERNAME { user_id } else { None };
+        tracing::warn!(scope = locked.scope, key = %locked.key, "Login locked out after {} failures", locked.failed_attempts);
+
+        audit::record(
+            &state.db_pool,
+            AuditEntry {
+                event_type: audit::ACCOUNT_LOCKED,
+                user_id: locked_user_id,
+                username: Some(username),
+                ip_address,
+                details: Some(format!("{} locked until {}", locked.scope, locked.locked_until)),
+            },
+        )
+        .await?;
+
+        let event = AccountLockedEvent {
+            scope: locked.scope.to_string(),
+            key: locked.key,
+            user_id: locked_user_id,
+            failed_attempts: locked.failed_attempts,
+            locked_until: locked.locked_until,
+        };
+        if let Err(e) = state.kafka_producer.publish_account_locked(event).await {
+            tracing::warn!("Failed to publish account locked event: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Continues a login whose first factor succeeded: accounts with 2FA get a
+/// challenge, everyone else is logged in.
+pub(crate) async fn login_response(
+    state: &AppState,
+    user: User,
+    client: &ClientInfo,
+) -> Result<LoginResponse> {
+    if user.totp_enabled_at.is_some() {
+        let ttl = state.config.two_factor_challenge_ttl_secs;
+        return Ok(LoginResponse::TwoFactorRequired(TwoFactorChallengeResponse {
+            two_factor_required: true,
+            challenge_token: two_factor::issue_challenge(&state.jwt, user.id, ttl)?,
+            expires_in: ttl,
+        }));
+    }
+
+    Ok(LoginResponse::Authenticated(complete_login(state, user, client).await?))
+}
+
+/// Finishes a login once every required factor has been checked: resets the
+/// failure counter, records the login and issues tokens.
+pub(crate) async fn complete_login(
+    state: &AppState,
+    user: User,
+    client: &ClientInfo,
+) -> Result<AuthResponse> {
+    lockout::clear(&state.db_pool, lockout::SCOPE_USERNAME, &user.username).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET last_login_at = NOW() WHERE id = $1 RETURNING *"
+    )
+    .bind(user.id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    let event = UserLoggedInEvent {
+        user_id: user.id,
+        username: user.username.clone(),
+        login_timestamp: user.last_login_at.unwrap_or_else(Utc::now),
+    };
+    if let Err(e) = state.kafka_producer.publish_user_logged_in(event).await {
+        tracing::warn!("Failed to publish user logged in event: {}", e);
+    }
+
+    issue_tokens(state, user, client).await
+}
+
+/// Starts a new session for `user` and returns its access and refresh tokens.
+pub(crate) async fn issue_tokens(
+    state: &AppState,
+    user: User,
+    client: &ClientInfo,
+) -> Result<AuthResponse> {
+    let (session, refresh_token) = session::start(
+        &state.db_pool,
+        user.id,
+        client,
+        state.config.jwt.refresh_token_ttl_secs,
+    )
+    .await?;
+
+    auth_response(state, user, session.id, refresh_token)
+}
+
+fn auth_response(
+    state: &AppState,
+    user: User,
+    session_id: Uuid,
+    refresh_token: String,
+) -> Result<AuthResponse> {
+    let claims = state.jwt.access_claims(user.id, &user.username, user.role, session_id);
+    let token = state.jwt.encode(&claims)?;
+
+    Ok(AuthResponse {
+        user: user.into(),
+        token,
+        refresh_token,
+        expires_in: state.jwt.access_token_ttl_secs(),
+    })
+}
+
+/// `Set-Cookie` headers carrying newly issued tokens, when cookie auth is enabled.
+pub(crate) fn session_cookies(state: &AppState, auth: &AuthResponse) -> HeaderMap {
+    if !state.config.cookie_auth.enabled {
+        return HeaderMap::new();
+    }
+    cookies::issue(&state.config.cookie_auth, auth, state.config.jwt.refresh_token_ttl_secs)
+}
+
+/// Like [`session_cookies`], for login responses that may be a 2FA challenge.
+pub(crate) fn login_cookies(state: &AppState, response: &LoginResponse) -> HeaderMap {
+    match response {
+        LoginResponse::Authenticated(auth) => session_cookies(state, auth),
+        LoginResponse::TwoFactorRequired(_) => HeaderMap::new(),
+    }
+}
+
+/// The refresh token from the request body or, for browser clients, from the
+/// refresh cookie. Cookie-borne tokens are subject to the CSRF check.
+fn presented_refresh_token(
+    state: &AppState,
+    headers: &HeaderMap,
+    payload: Option<Json<RefreshTokenRequest>>,
+) -> Result<String> {
+    if let Some(Json(payload)) = payload {
+        return Ok(payload.refresh_token);
+    }
+
+    let token = state
+        .config
+        .cookie_auth
+        .enabled
+        .then(|| cookies::get(headers, cookies::REFRESH_COOKIE))
+        .flatten()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| AppError::Unauthorized("Missing refresh token".to_string()))?;
+    cookies::verify_csrf(headers)?;
+    Ok(token)
+}
+
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Json<RefreshTokenRequest>>,
+) -> Result<(HeaderMap, Json<AuthResponse>)> {
+    let presented = presented_refresh_token(&state, &headers, payload)?;
+    let (session, refresh_token) = session::rotate(&state.db_pool, &presented).await?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(session.user_id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid refresh token".to_string()))?;
+
+    if !user.is_active {
+        return Err(AppError::Unauthorized("Account is disabled".to_string()));
+    }
+
+    let auth = auth_response(&state, user, session.id, refresh_token)?;
+    Ok((session_cookies(&state, &auth), Json(auth)))
+}
+
+/// Revokes the session behind a refresh token, together with its access tokens.
+pub async fn logout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Option<Json<RefreshTokenRequest>>,
+) -> Result<(HeaderMap, StatusCode)> {
+    let presented = presented_refresh_token(&state, &headers, payload)?;
+    session::revoke_by_token(&state.db_pool, &presented).await?;
+
+    let cleared = if state.config.cookie_auth.enabled {
+        cookies::clear(&state.config.cookie_auth)
+    } else {
+        HeaderMap::new()
+    };
+    Ok((cleared, StatusCode::NO_CONTENT))
+}
+
+/// Emails a password reset token to a verified address. Always answers
+/// `202 Accepted` so the response does not reveal whether it is registered.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<StatusCode> {
+    payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1 AND is_active = true AND email_verified_at IS NOT NULL")
+        .bind(&payload.email)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+    if let Some(user) = user {
+        let token = password_reset::issue(
+            &state.db_pool,
+            user.id,
+            state.config.password_reset_ttl_secs,
+        )
+        .await?;
+
+        let mut body = format!(
+            "Hi {},\n\nUse this code to reset your password:\n\n{}\n",
+            user.username, token
+        );
+        if let Some(url) = &state.config.password_reset_url {
+            body.push_str(&format!("\nOr open this link:\n{}?token={}\n", url, token));
+        }
+        body.push_str(&format!(
+            "\nThe code expires in {} minutes. If you did not ask for it, ignore this email.\n",
+            state.config.password_reset_ttl_secs / 60
+        ));
+
+        // Sent in the background so response timing does not reveal the account exists
+        mail::send_in_background(
+            state.mailer.clone(),
+            Email {
+                to: user.email,
+                subject: "Reset your password".to_string(),
+                body,
+            },
+        );
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Sets a new password using a reset token and signs the user out everywhere.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<StatusCode> {
+    payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let password_hash = state.password_hasher.hash(&payload.new_password).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    let user_id = password_reset::consume(&mut tx, &payload.token).await?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = $2 WHERE id = $3")
+        .bind(&password_hash)
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    session::revoke_all(&mut *tx, user_id, None, session::REVOKED_PASSWORD_RESET).await?;
+
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Emails a signed link that confirms `user` owns their current address.
+pub(crate) fn send_verification_email(state: &AppState, user: &User) -> Result<()> {
+    let token = email_verification::issue(
+        &state.jwt,
+        user.id,
+        &user.email,
+        state.config.email_verification_ttl_secs,
+    )?;
+
+    let body = format!(
+        "Hi {},\n\nPlease confirm your email address by opening this link:\n\n{}/api/auth/verify-email?token={}\n\nIf you did not create an account, ignore this email.\n",
+        user.username, state.config.public_url, token
+    );
+
+    mail::send_in_background(
+        state.mailer.clone(),
+        Email {
+            to: user.email.clone(),
+            subject: "Verify your email address".to_string(),
+            body,
+        },
+    );
+    Ok(())
+}
+
+/// Target of the emailed verification link.
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<Json<UserResponse>> {
+    let (user_id, email) = email_verification::verify(&state.jwt, &query.token)?;
+
+    // Only the address the link was sent to can be verified by it
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW())
+        WHERE id = $1 AND email = $2
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(&email)
+    .fetch_optional(&state.db_pool)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Invalid or expired verification link".to_string()))?;
+
+    Ok(Json(user.into()))
+}
+
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", auth.id)))?;
+
+    if user.email_verified_at.is_some() {
+        return Err(AppError::Conflict("Email is already verified".to_string()));
+    }
+
+    send_verification_email(&state, &user)?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Publishes the public half of every asymmetric signing key so other
+/// services can verify tokens issued by `login_user`.
+pub async fn jwks(State(state): State<AppState>) -> Json<JwkSet> {
+    Json(state.jwt.jwks().clone())
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
     Json,
 };
@@ -7,13 +7,18 @@ use chrono::Utc;
 use uuid::Uuid;
 
 use crate::{
+    auth::ownership,
     routes::AppState,
     error::{AppError, Result},
-    models::{BatchUpdateTodosRequest, TodoResponse},
+    handlers::{apply_todo_rules, dependencies, get_todo_with_relations, history, recurrence, subtasks},
+    middleware::auth::VerifiedUser,
+    models::{BatchUpdateTodosRequest, TodoResponse, UpdateTodoQuery},
 };
 
 pub async fn batch_update_todos(
     State(state): State<AppState>,
+    VerifiedUser(auth): VerifiedUser,
+    Query(query): Query<UpdateTodoQuery>,
     Json(payload): Json<BatchUpdateTodosRequest>,
 ) -> Result<Json<Vec<TodoResponse>>> {
     if payload.todo_ids.is_empty() {
@@ -25,17 +30,34 @@ pub async fn batch_update_todos(
     }
 
     let mut tx = state.db_pool.begin().await?;
-    let mut updated_todos = Vec::new();
+    ownership::ensure_category_reference(&mut *tx, &auth, payload.category_id).await?;
+    let mut updated_ids = Vec::new();
+    let mut completions = Vec::new();
+    let mut next_occurrences = Vec::new();
+    let mut newly_completed = Vec::new();
 
     for todo_id in &payload.todo_ids {
-        // Get existing todo
-        let existing_todo = match sqlx::query_as::<_, crate::models::Todo>("SELECT * FROM todos WHERE id = $1")
-            .bind(todo_id)
-            .fetch_optional(&mut *tx)
-            .await? {
-                Some(todo) => todo,
-                None => continue, // Skip if todo doesn't exist
-            };
+        // Get existing todo, scoped to the caller
+        let (existing_todo, before) = match history::lock(&mut tx, &auth, *todo_id).await {
+            Ok(locked) => locked,
+            Err(AppError::NotFound(_)) => continue, // Skip if todo doesn't exist or belongs to someone else
+            Err(e) => return Err(e),
+        };
+        match payload.versions.get(todo_id) {
+            Some(&expected) if expected != existing_todo.version => {
+                return Err(AppError::PreconditionFailed(format!(
+                    "Todo {} has changed; its current version is {}",
+                    todo_id, existing_todo.version
+                )));
+            }
+            None if state.config.require_if_match => {
+                return Err(AppError::PreconditionRequired(format!(
+                    "Send the expected version of todo {} in versions",
+                    todo_id
+                )));
+            }
+            _ => {}
+        }
 
         // Apply updates
         let completed = payload.completed.unwrap_or(existing_todo.completed);
@@ -53,18 +75,41 @@ pub async fn batch_update_todos(
         .fetch_one(&mut *tx)
         .await?;
 
-        // Get full todo with relations
-        let full_todo = get_todo_with_relations(&mut *tx, updated_todo.id).await?;
-        updated_todos.push(full_todo);
+        // Blockers are checked once the whole batch is applied, below
+        let (changes, next_occurrence) =
+            apply_todo_rules(&mut tx, &state, &existing_todo, &updated_todo, true).await?;
+        completions.extend(changes);
+        next_occurrences.extend(next_occurrence);
+        if updated_todo.completed && !existing_todo.completed {
+            newly_completed.push(updated_todo.id);
+        }
+        history::record(&mut tx, auth.id, updated_todo.id, &before).await?;
+        updated_ids.push(updated_todo.id);
     }
 
+    // Checked once everything is updated, so a blocker completed in the same
+    // batch counts as done whatever its position in the list
+    if !query.force {
+        dependencies::ensure_unblocked(&mut tx, &newly_completed).await?;
+    }
     tx.commit().await?;
 
+    subtasks::publish_completions(&state, auth.id, completions).await;
+    for occurrence in next_occurrences {
+        recurrence::publish_created(&state, auth.id, occurrence).await;
+    }
+
+    let mut updated_todos = Vec::new();
+    for todo_id in updated_ids {
+        updated_todos.push(get_todo_with_relations(&state.db_pool, todo_id).await?);
+    }
+
     Ok(Json(updated_todos))
 }
 
 pub async fn batch_delete_todos(
     State(state): State<AppState>,
+    VerifiedUser(auth): VerifiedUser,
     Json(todo_ids): Json<Vec<Uuid>>,
 ) -> Result<StatusCode> {
     if todo_ids.is_empty() {
@@ -75,76 +120,31 @@ pub async fn batch_delete_todos(
         return Err(AppError::Validation("Too many todos (max 100)".to_string()));
     }
 
-    let placeholders: Vec<String> = (1..=todo_ids.len())
-        .map(|i| format!("${}", i))
-        .collect();
-
-    let query = format!(
-        "DELETE FROM todos WHERE id IN ({})",
-        placeholders.join(", ")
-    );
-
-    let mut q = sqlx::query(&query);
-    for id in &todo_ids {
-        q = q.bind(id);
+    // Each todo's subtasks follow the configured rule; ids already removed
+    // with an earlier todo's subtree are skipped
+    let mut tx = state.db_pool.begin().await?;
+    let mut deleted_ids = Vec::new();
+    let mut completions = Vec::new();
+    for todo_id in &todo_ids {
+        let todo = match ownership::locked_todo(&mut *tx, &auth, *todo_id).await {
+            Ok(todo) => todo,
+            Err(AppError::NotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        let policy = &state.config.subtasks;
+        let (deleted, changes) = subtasks::delete_tree(&mut tx, policy, &todo, policy.on_parent_delete).await?;
+        deleted_ids.extend(deleted);
+        completions.extend(changes);
     }
+    tx.commit().await?;
 
-    let result = q.execute(&state.db_pool).await?;
-
-    if result.rows_affected() == 0 {
+    if deleted_ids.is_empty() {
         return Err(AppError::NotFound("No todos found to delete".to_string()));
     }
 
-    Ok(StatusCode::NO_CONTENT)
-}
-
-// Helper function to get todo with related data
-async fn get_todo_with_relations(
-    executor: &mut sqlx::PgConnection,
-    todo_id: Uuid,
-) -> Result<TodoResponse> {
-    use crate::models::{Todo, Category, Tag, CategoryResponse, TagResponse};
+    // Ancestors deleted later in the batch are gone, not completed or reopened
+    completions.retain(|(todo_id, _)| !deleted_ids.contains(todo_id));
+    subtasks::publish_completions(&state, auth.id, completions).await;
 
-    let todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = $1")
-        .bind(todo_id)
-        .fetch_one(&mut *executor)
-        .await?;
-
-    let category = if let Some(category_id) = todo.category_id {
-        sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1")
-            .bind(category_id)
-            .fetch_optional(&mut *executor)
-            .await?
-            .map(CategoryResponse::from)
-    } else {
-        None
-    };
-
-    let tags = sqlx::query_as::<_, Tag>(
-        r#"
-        SELECT t.* FROM tags t
-        JOIN todo_tags tt ON t.id = tt.tag_id
-        WHERE tt.todo_id = $1
-        ORDER BY t.name
-        "#
-    )
-    .bind(todo_id)
-    .fetch_all(&mut *executor)
-    .await?;
-
-    let tag_responses: Vec<TagResponse> = tags.into_iter().map(TagResponse::from).collect();
-
-    Ok(TodoResponse {
-        id: todo.id,
-        title: todo.title,
-        description: todo.description,
-        completed: todo.completed,
-        user_id: todo.user_id,
-        category,
-        priority: todo.priority,
-        due_date: todo.due_date,
-        tags: tag_responses,
-        created_at: todo.created_at,
-        updated_at: todo.updated_at,
-    })
+    Ok(StatusCode::NO_CONTENT)
 }
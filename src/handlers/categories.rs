@@ -1,6 +1,6 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
     Json,
 };
 use chrono::Utc;
@@ -8,31 +8,29 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    auth::ownership,
     routes::AppState,
     error::{AppError, Result},
+    handlers::preconditions::{Cached, Validators},
+    middleware::auth::AuthUser,
     models::{
         Category, CategoryResponse, CreateCategoryRequest, UpdateCategoryRequest,
     },
 };
 
-#[derive(serde::Deserialize)]
-pub struct CategoryQuery {
-    pub user_id: Uuid,
-}
-
 pub async fn create_category(
     State(state): State<AppState>,
-    Query(query): Query<CategoryQuery>,
+    auth: AuthUser,
     Json(payload): Json<CreateCategoryRequest>,
 ) -> Result<(StatusCode, Json<CategoryResponse>)> {
     payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
     // Check if category name already exists for this user
     let existing = sqlx::query_as::<_, Category>(
-        "SELECT * FROM categories WHERE name = $1 AND user_id = $2"
+        "SELECT * FROM categories WHERE name = $1 AND user_id = $2 AND deleted_at IS NULL"
     )
     .bind(&payload.name)
-    .bind(query.user_id)
+    .bind(auth.id)
     .fetch_optional(&state.db_pool)
     .await?;
 
@@ -51,7 +49,7 @@ pub async fn create_category(
     .bind(&payload.name)
     .bind(&payload.description)
     .bind(&payload.color)
-    .bind(query.user_id)
+    .bind(auth.id)
     .bind(now)
     .bind(now)
     .fetch_one(&state.db_pool)
@@ -62,64 +60,69 @@ pub async fn create_category(
 
 pub async fn get_categories(
     State(state): State<AppState>,
-    Query(query): Query<CategoryQuery>,
-) -> Result<Json<Vec<CategoryResponse>>> {
+    auth: AuthUser,
+    headers: HeaderMap,
+) -> Result<Cached<Json<Vec<CategoryResponse>>>> {
+    let validators = Validators::for_user(&state.db_pool, auth.id).await?;
+    if validators.is_current(&headers) {
+        return Ok(Cached::NotModified(validators));
+    }
+
     let categories = sqlx::query_as::<_, Category>(
-        "SELECT * FROM categories WHERE user_id = $1 ORDER BY name"
+        "SELECT * FROM categories WHERE user_id = $1 AND deleted_at IS NULL ORDER BY name"
     )
-    .bind(query.user_id)
+    .bind(auth.id)
     .fetch_all(&state.db_pool)
     .await?;
 
     let response: Vec<CategoryResponse> = categories.into_iter().map(CategoryResponse::from).collect();
-    Ok(Json(response))
+    Ok(Cached::Fresh(validators, Json(response)))
 }
 
 pub async fn get_category(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(category_id): Path<Uuid>,
-) -> Result<Json<CategoryResponse>> {
-    let category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1")
-        .bind(category_id)
-        .fetch_optional(&state.db_pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Category with id {} not found", category_id)))?;
+    headers: HeaderMap,
+) -> Result<Cached<Json<CategoryResponse>>> {
+    let category = ownership::owned_category(&state.db_pool, &auth, category_id).await?;
+    let validators = Validators::for_item(category.updated_at);
+    if validators.is_current(&headers) {
+        return Ok(Cached::NotModified(validators));
+    }
 
-    Ok(Json(category.into()))
+    Ok(Cached::Fresh(validators, Json(category.into())))
 }
 
 pub async fn update_category(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(category_id): Path<Uuid>,
     Json(payload): Json<UpdateCategoryRequest>,
 ) -> Result<Json<CategoryResponse>> {
     payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
-    let existing_category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1")
-        .bind(category_id)
-        .fetch_optional(&state.db_pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Category with id {} not found", category_id)))?;
+    let existing_category = ownership::owned_category(&state.db_pool, &auth, category_id).await?;
 
-    let name = payload.name.as_ref().map(|n| n.clone()).unwrap_or(existing_category.name.clone());
+    let name = payload.name.clone().unwrap_or(existing_category.name.clone());
     let description = payload.description.or(existing_category.description);
     let color = payload.color.or(existing_category.color);
 
     // Check if new name conflicts with existing categories for this user
-    if let Some(ref new_name) = payload.name {
-        if new_name != &existing_category.name {
-            let existing = sqlx::query_as::<_, Category>(
-                "SELECT * FROM categories WHERE name = $1 AND user_id = $2 AND id != $3"
-            )
-            .bind(new_name)
-            .bind(existing_category.user_id)
-            .bind(category_id)
-            .fetch_optional(&state.db_pool)
-            .await?;
-
-            if existing.is_some() {
-                return Err(AppError::Conflict("Category name already exists".to_string()));
-            }
+    if let Some(ref new_name) = payload.name
+        && new_name != &existing_category.name
+    {
+        let existing = sqlx::query_as::<_, Category>(
+            "SELECT * FROM categories WHERE name = $1 AND user_id = $2 AND id != $3 AND deleted_at IS NULL"
+        )
+        .bind(new_name)
+        .bind(existing_category.user_id)
+        .bind(category_id)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+        if existing.is_some() {
+            return Err(AppError::Conflict("Category name already exists".to_string()));
         }
     }
 
@@ -144,12 +147,17 @@ pub async fn update_category(
 
 pub async fn delete_category(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(category_id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    let result = sqlx::query("DELETE FROM categories WHERE id = $1")
-        .bind(category_id)
-        .execute(&state.db_pool)
-        .await?;
+    // Todos keep the reference, so restoring the category brings it back on them
+    let result = sqlx::query(
+        "UPDATE categories SET deleted_at = NOW() WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL"
+    )
+    .bind(category_id)
+    .bind(auth.id)
+    .execute(&state.db_pool)
+    .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound(format!("Category with id {} not found", category_id)));
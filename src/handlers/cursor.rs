@@ -0,0 +1,57 @@
+//! Opaque cursors for keyset pagination of the todo list. A cursor holds the
+//! sort key and id of the todo it was taken from, and is signed so that
+//! clients cannot craft positions of their own.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Which way a cursor pages from the todo it was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Direction {
+    Next,
+    Prev,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Cursor {
+    /// The sort the cursor was issued for; it is refused under any other.
+    pub sort: String,
+    /// The todo's sort key values, in sort order.
+    pub keys: Vec<serde_json::Value>,
+    pub id: Uuid,
+    pub direction: Direction,
+}
+
+impl Cursor {
+    pub(crate) fn encode(&self, secret: &str) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"));
+        let signature = URL_SAFE_NO_PAD.encode(mac(secret, &payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Returns `400` for cursors that were tampered with or not issued here.
+    pub(crate) fn decode(cursor: &str, secret: &str) -> Result<Self> {
+        let invalid = || AppError::BadRequest("Invalid cursor".to_string());
+
+        let (payload, signature) = cursor.split_once('.').ok_or_else(invalid)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        mac(secret, payload).verify_slice(&signature).map_err(|_| invalid())?;
+
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+        serde_json::from_slice(&payload).map_err(|_| invalid())
+    }
+}
+
+fn mac(secret: &str, payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(payload.as_bytes());
+    mac
+}
@@ -0,0 +1,140 @@
+//! Dependencies between todos: a todo is blocked while any todo it depends
+//! on is open, and completing it is refused unless forced.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::{
+    auth::ownership,
+    error::{AppError, Result},
+    middleware::auth::AuthUser,
+    models::TodoResponse,
+    routes::AppState,
+};
+
+use super::get_todo_with_relations;
+
+/// SQL condition, on the `todos` table, that holds for todos with an open blocker.
+pub(crate) const HAS_OPEN_BLOCKER: &str = "EXISTS (SELECT 1 FROM todo_dependencies d \
+     JOIN todos b ON b.id = d.blocked_by_id \
+     WHERE d.todo_id = todos.id AND NOT b.completed AND b.deleted_at IS NULL)";
+
+/// Makes `id` wait for `blocker_id`. Returns `409` if `blocker_id` already
+/// depends on `id`, directly or through other todos.
+pub async fn add_dependency(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, blocker_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<TodoResponse>> {
+    let mut tx = state.db_pool.begin().await?;
+
+    // Concurrent links could otherwise each pass the cycle check and
+    // together create a loop
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::text, 0))")
+        .bind(auth.id)
+        .execute(&mut *tx)
+        .await?;
+
+    ownership::owned_todo(&mut *tx, &auth, id).await?;
+    ownership::owned_todo(&mut *tx, &auth, blocker_id).await?;
+
+    let creates_cycle: bool = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE blockers AS (
+            SELECT $1::uuid AS id
+            UNION
+            SELECT d.blocked_by_id FROM todo_dependencies d JOIN blockers b ON d.todo_id = b.id
+        )
+        SELECT EXISTS (SELECT 1 FROM blockers WHERE id = $2)
+        "#,
+    )
+    .bind(blocker_id)
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if creates_cycle {
+        return Err(AppError::Conflict(
+            "This dependency would make the todos wait for each other".to_string(),
+        ));
+    }
+
+    sqlx::query(
+        "INSERT INTO todo_dependencies (todo_id, blocked_by_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+    )
+    .bind(id)
+    .bind(blocker_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(get_todo_with_relations(&state.db_pool, id).await?))
+}
+
+pub async fn remove_dependency(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, blocker_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    ownership::owned_todo(&state.db_pool, &auth, id).await?;
+
+    let result = sqlx::query("DELETE FROM todo_dependencies WHERE todo_id = $1 AND blocked_by_id = $2")
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Dependency not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `(blocked_by, blocks)` for a todo, oldest link first. Todos in the trash
+/// are left out.
+pub(crate) async fn links<'e, E: PgExecutor<'e>>(executor: E, todo_id: Uuid) -> Result<(Vec<Uuid>, Vec<Uuid>)> {
+    let links = sqlx::query_as(
+        r#"
+        SELECT
+            ARRAY(
+                SELECT d.blocked_by_id FROM todo_dependencies d JOIN todos t ON t.id = d.blocked_by_id
+                WHERE d.todo_id = $1 AND t.deleted_at IS NULL ORDER BY d.created_at
+            ),
+            ARRAY(
+                SELECT d.todo_id FROM todo_dependencies d JOIN todos t ON t.id = d.todo_id
+                WHERE d.blocked_by_id = $1 AND t.deleted_at IS NULL ORDER BY d.created_at
+            )
+        "#,
+    )
+    .bind(todo_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(links)
+}
+
+/// Refuses to complete todos that still wait for an open todo. Called after
+/// the update, so blockers completed in the same transaction count as done.
+pub(crate) async fn ensure_unblocked(conn: &mut PgConnection, todo_ids: &[Uuid]) -> Result<()> {
+    let blocked: Vec<Uuid> = sqlx::query_scalar(&format!(
+        "SELECT id FROM todos WHERE id = ANY($1) AND {}",
+        HAS_OPEN_BLOCKER
+    ))
+    .bind(todo_ids)
+    .fetch_all(conn)
+    .await?;
+
+    if blocked.is_empty() {
+        return Ok(());
+    }
+    let ids: Vec<String> = blocked.iter().map(Uuid::to_string).collect();
+    Err(AppError::Conflict(format!(
+        "Todo(s) {} are blocked by open todos; pass force=true to complete them anyway",
+        ids.join(", ")
+    )))
+}
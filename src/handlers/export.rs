@@ -0,0 +1,197 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::io::{Cursor, Write};
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    db::DbPool,
+    error::{AppError, Result},
+    middleware::auth::AuthUser,
+    models::{Category, Session, Tag, Todo, TodoReminder, TodoRevision, TodoSeries, User, UserResponse},
+    routes::AppState,
+};
+
+#[derive(Serialize, FromRow)]
+struct TagAssignment {
+    todo_id: Uuid,
+    tag_id: Uuid,
+}
+
+#[derive(Serialize, FromRow)]
+struct Dependency {
+    todo_id: Uuid,
+    blocked_by_id: Uuid,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+struct ExportedApiKey {
+    id: Uuid,
+    name: String,
+    key_prefix: String,
+    scopes: Vec<String>,
+    expires_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+struct ExportedIdentity {
+    provider: String,
+    subject: String,
+    email: Option<String>,
+    created_at: DateTime<Utc>,
+    last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, FromRow)]
+struct AuditLogEntry {
+    event_type: String,
+    ip_address: Option<String>,
+    details: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Everything stored about the caller as a ZIP archive of JSON files.
+/// Credentials (password hash, TOTP secret, token hashes) are left out.
+pub async fn export_account(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Response> {
+    let pool = &state.db_pool;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", auth.id)))?;
+
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    add_json(&mut archive, "profile.json", &UserResponse::from(user))?;
+    add_json(&mut archive, "todos.json", &owned::<Todo>(pool, "todos", auth.id).await?)?;
+    add_json(&mut archive, "categories.json", &owned::<Category>(pool, "categories", auth.id).await?)?;
+    add_json(&mut archive, "tags.json", &owned::<Tag>(pool, "tags", auth.id).await?)?;
+    add_json(&mut archive, "recurring_series.json", &owned::<TodoSeries>(pool, "todo_series", auth.id).await?)?;
+    add_json(&mut archive, "reminders.json", &owned::<TodoReminder>(pool, "todo_reminders", auth.id).await?)?;
+
+    let assignments = sqlx::query_as::<_, TagAssignment>(
+        r#"
+        SELECT tt.todo_id, tt.tag_id FROM todo_tags tt
+        JOIN todos t ON t.id = tt.todo_id
+        WHERE t.user_id = $1
+        ORDER BY tt.todo_id, tt.tag_id
+        "#,
+    )
+    .bind(auth.id)
+    .fetch_all(pool)
+    .await?;
+    add_json(&mut archive, "tag_assignments.json", &assignments)?;
+
+    let dependencies = sqlx::query_as::<_, Dependency>(
+        r#"
+        SELECT d.todo_id, d.blocked_by_id, d.created_at FROM todo_dependencies d
+        JOIN todos t ON t.id = d.todo_id
+        WHERE t.user_id = $1
+        ORDER BY d.created_at
+        "#,
+    )
+    .bind(auth.id)
+    .fetch_all(pool)
+    .await?;
+    add_json(&mut archive, "dependencies.json", &dependencies)?;
+
+    let revisions = sqlx::query_as::<_, TodoRevision>(
+        r#"
+        SELECT r.* FROM todo_revisions r
+        JOIN todos t ON t.id = r.todo_id
+        WHERE t.user_id = $1
+        ORDER BY r.todo_id, r.revision
+        "#,
+    )
+    .bind(auth.id)
+    .fetch_all(pool)
+    .await?;
+    add_json(&mut archive, "revisions.json", &revisions)?;
+
+    add_json(&mut archive, "sessions.json", &owned::<Session>(pool, "sessions", auth.id).await?)?;
+
+    let api_keys = sqlx::query_as::<_, ExportedApiKey>(
+        "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at"
+    )
+    .bind(auth.id)
+    .fetch_all(pool)
+    .await?;
+    add_json(&mut archive, "api_keys.json", &api_keys)?;
+
+    let identities = sqlx::query_as::<_, ExportedIdentity>(
+        "SELECT * FROM user_identities WHERE user_id = $1 ORDER BY created_at"
+    )
+    .bind(auth.id)
+    .fetch_all(pool)
+    .await?;
+    add_json(&mut archive, "identities.json", &identities)?;
+
+    let audit_log = sqlx::query_as::<_, AuditLogEntry>(
+        "SELECT * FROM security_audit_log WHERE user_id = $1 ORDER BY created_at"
+    )
+    .bind(auth.id)
+    .fetch_all(pool)
+    .await?;
+    add_json(&mut archive, "security_log.json", &audit_log)?;
+
+    let bytes = archive
+        .finish()
+        .map_err(|e| AppError::Internal(format!("Failed to build export archive: {}", e)))?
+        .into_inner();
+
+    let disposition = format!(
+        "attachment; filename=\"todo-export-{}.zip\"",
+        Utc::now().format("%Y%m%d")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// All rows of `table` owned by the user, oldest first.
+async fn owned<T>(pool: &DbPool, table: &str, user_id: Uuid) -> Result<Vec<T>>
+where
+    T: for<'r> FromRow<'r, sqlx::postgres::PgRow> + Send + Unpin,
+{
+    let rows = sqlx::query_as::<_, T>(&format!(
+        "SELECT * FROM {} WHERE user_id = $1 ORDER BY created_at",
+        table
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+fn add_json<T: Serialize>(
+    archive: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    value: &T,
+) -> Result<()> {
+    let json = serde_json::to_vec_pretty(value)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize {}: {}", name, e)))?;
+
+    archive
+        .start_file(name, SimpleFileOptions::default())
+        .and_then(|_| archive.write_all(&json).map_err(Into::into))
+        .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", name, e)))
+}
@@ -0,0 +1,174 @@
+//! Revision history: every change made through `update_todo`, the batch
+//! update and the tag endpoints is recorded with the todo as it was before
+//! and after, and can be reverted.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::Utc;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    auth::ownership,
+    error::{AppError, Result},
+    middleware::auth::AuthUser,
+    models::{RevisionResponse, Todo, TodoRevision, TodoSnapshot, UpdateTodoQuery},
+    routes::AppState,
+};
+
+use super::{
+    apply_todo_rules, get_todo_with_relations,
+    preconditions::{self, TaggedTodo},
+    recurrence, subtasks, tags, todo_updated_event,
+};
+
+/// The caller's todo, locked for the rest of the transaction so that
+/// concurrent changes are recorded one after the other, and its snapshot.
+pub(crate) async fn lock(conn: &mut PgConnection, auth: &AuthUser, todo_id: Uuid) -> Result<(Todo, TodoSnapshot)> {
+    let todo = ownership::locked_todo(&mut *conn, auth, todo_id).await?;
+    let snapshot = snapshot(conn, todo_id).await?;
+    Ok((todo, snapshot))
+}
+
+/// Records the change from `before` to the todo's current state as its next
+/// revision. Nothing is recorded if the todo did not actually change.
+pub(crate) async fn record(conn: &mut PgConnection, actor_id: Uuid, todo_id: Uuid, before: &TodoSnapshot) -> Result<()> {
+    let after = snapshot(&mut *conn, todo_id).await?;
+    if after == *before {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO todo_revisions (todo_id, revision, actor_id, before, after)
+        SELECT $1, COALESCE(MAX(revision), 0) + 1, $2, $3, $4
+        FROM todo_revisions WHERE todo_id = $1
+        "#,
+    )
+    .bind(todo_id)
+    .bind(actor_id)
+    .bind(sqlx::types::Json(before))
+    .bind(sqlx::types::Json(&after))
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// The todo's revisions, newest first, as the fields each one changed.
+pub async fn get_history(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<RevisionResponse>>> {
+    ownership::owned_todo(&state.db_pool, &auth, id).await?;
+
+    let revisions = sqlx::query_as::<_, TodoRevision>(
+        "SELECT * FROM todo_revisions WHERE todo_id = $1 ORDER BY revision DESC"
+    )
+    .bind(id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok(Json(revisions.into_iter().map(RevisionResponse::from).collect()))
+}
+
+/// Puts the todo back the way `revision` left it, tags included; tags that
+/// have since gone to the trash come back out of it. The revert is itself
+/// recorded as a new revision, and follows the same rules as an update:
+/// completing a blocked todo needs `force=true`.
+pub async fn revert_todo(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, revision)): Path<(Uuid, i32)>,
+    Query(query): Query<UpdateTodoQuery>,
+    headers: HeaderMap,
+) -> Result<TaggedTodo> {
+    let mut tx = state.db_pool.begin().await?;
+    let (existing_todo, before) = lock(&mut tx, &auth, id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, existing_todo.version)?;
+
+    let sqlx::types::Json(target) = sqlx::query_scalar::<_, sqlx::types::Json<TodoSnapshot>>(
+        "SELECT after FROM todo_revisions WHERE todo_id = $1 AND revision = $2"
+    )
+    .bind(id)
+    .bind(revision)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Revision {} of todo {} not found", revision, id)))?;
+
+    if let Some(category_id) = target.category_id {
+        match ownership::owned_category(&mut *tx, &auth, category_id).await {
+            Ok(_) => {}
+            Err(AppError::NotFound(_)) => {
+                return Err(AppError::Conflict(format!(
+                    "Category {} no longer exists; restore it before reverting",
+                    category_id
+                )));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let updated_todo = sqlx::query_as::<_, Todo>(
+        r#"
+        UPDATE todos
+        SET title = $1, description = $2, completed = $3, category_id = $4,
+            priority = $5, due_date = $6, updated_at = $7
+        WHERE id = $8
+        RETURNING *
+        "#,
+    )
+    .bind(&target.title)
+    .bind(&target.description)
+    .bind(target.completed)
+    .bind(target.category_id)
+    .bind(target.priority)
+    .bind(target.due_date)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let (completions, next_occurrence) =
+        apply_todo_rules(&mut tx, &state, &existing_todo, &updated_todo, query.force).await?;
+    tags::restore_trashed(&mut tx, auth.id, &target.tags).await?;
+    tags::replace_tags(&mut tx, auth.id, id, &target.tags).await?;
+    record(&mut tx, auth.id, id, &before).await?;
+    tx.commit().await?;
+
+    let changed_tags = (target.tags != before.tags).then_some(target.tags);
+    let event = todo_updated_event(&existing_todo, &updated_todo, changed_tags);
+    if let Err(e) = state.kafka_producer.publish_todo_updated(event, auth.id).await {
+        tracing::warn!("Failed to publish todo updated event: {}", e);
+    }
+    subtasks::publish_completions(&state, auth.id, completions).await;
+    if let Some(occurrence) = next_occurrence {
+        recurrence::publish_created(&state, auth.id, occurrence).await;
+    }
+
+    Ok(preconditions::tagged(get_todo_with_relations(&state.db_pool, id).await?))
+}
+
+/// The tracked fields of a todo as they are now; tags in the trash are left out.
+async fn snapshot(conn: &mut PgConnection, todo_id: Uuid) -> Result<TodoSnapshot> {
+    let snapshot = sqlx::query_as::<_, TodoSnapshot>(
+        r#"
+        SELECT title, description, completed, category_id, priority, due_date,
+            ARRAY(
+                SELECT tg.name::text FROM todo_tags tt JOIN tags tg ON tg.id = tt.tag_id
+                WHERE tt.todo_id = t.id AND tg.deleted_at IS NULL ORDER BY tg.name
+            ) AS tags
+        FROM todos t
+        WHERE t.id = $1
+        "#,
+    )
+    .bind(todo_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(snapshot)
+}
@@ -1,28 +1,50 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     Json,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use cursor::{Cursor, Direction};
+use preconditions::{Cached, TaggedTodo, Validators};
+use sorting::Sort;
+use sqlx::{postgres::PgArguments, Arguments, PgConnection};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    auth::ownership,
     db::DbPool,
     error::{AppError, Result},
     kafka::{TodoCreatedEvent, TodoUpdatedEvent, TodoDeletedEvent},
+    middleware::auth::AuthUser,
     models::{
-        CreateTodoRequest, Todo, TodoListResponse, TodoQuery, TodoResponse, UpdateTodoRequest,
-        Category, Tag, CategoryResponse, TagResponse,
+        CreateTodoRequest, DeleteTodoQuery, Todo, TodoListResponse, TodoQuery, TodoResponse,
+        UpdateTodoQuery, UpdateTodoRequest, Category, Tag, CategoryResponse, TagResponse,
     },
     routes::AppState,
 };
 
+pub mod admin;
+pub mod api_keys;
+pub mod auth;
+pub mod export;
+pub mod oidc;
+pub mod sessions;
+pub mod two_factor;
 pub mod users;
 pub mod categories;
 pub mod tags;
 pub mod stats;
 pub mod batch;
+pub mod cursor;
+pub mod dependencies;
+pub mod history;
+pub mod preconditions;
+pub mod recurrence;
+pub mod reminders;
+pub mod sorting;
+pub mod subtasks;
+pub mod trash;
 
 // Helper function to get todo with related data
 async fn get_todo_with_relations(
@@ -35,7 +57,7 @@ async fn get_todo_with_relations(
         .await?;
 
     let category = if let Some(category_id) = todo.category_id {
-        sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1")
+        sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1 AND deleted_at IS NULL")
             .bind(category_id)
             .fetch_optional(pool)
             .await?
@@ -48,7 +70,7 @@ async fn get_todo_with_relations(
         r#"
         SELECT t.* FROM tags t
         JOIN todo_tags tt ON t.id = tt.tag_id
-        WHERE tt.todo_id = $1
+        WHERE tt.todo_id = $1 AND t.deleted_at IS NULL
         ORDER BY t.name
         "#
     )
@@ -57,6 +79,9 @@ async fn get_todo_with_relations(
     .await?;
 
     let tag_responses: Vec<TagResponse> = tags.into_iter().map(TagResponse::from).collect();
+    let (subtask_count, completed_subtask_count) = subtasks::counts(pool, todo_id).await?;
+    let recurrence = recurrence::summary(pool, &todo).await?;
+    let (blocked_by, blocks) = dependencies::links(pool, todo_id).await?;
 
     Ok(TodoResponse {
         id: todo.id,
@@ -68,6 +93,13 @@ async fn get_todo_with_relations(
         priority: todo.priority,
         due_date: todo.due_date,
         tags: tag_responses,
+        parent_id: todo.parent_id,
+        subtask_count,
+        completed_subtask_count,
+        recurrence,
+        blocked_by,
+        blocks,
+        version: todo.version,
         created_at: todo.created_at,
         updated_at: todo.updated_at,
     })
@@ -75,40 +107,59 @@ async fn get_todo_with_relations(
 
 pub async fn create_todo(
     State(state): State<AppState>,
+    auth: AuthUser,
     Json(payload): Json<CreateTodoRequest>,
 ) -> Result<(StatusCode, Json<TodoResponse>)> {
     payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    ownership::ensure_category_reference(&state.db_pool, &auth, payload.category_id).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    if let Some(parent_id) = payload.parent_id {
+        subtasks::ensure_parent(&mut tx, &auth, None, parent_id).await?;
+    }
+    let series_id = match &payload.recurrence {
+        Some(schedule) => Some(recurrence::create_series(&mut tx, &auth, &payload, schedule).await?),
+        None => None,
+    };
 
     let now = Utc::now();
     let todo = sqlx::query_as::<_, Todo>(
         r#"
-        INSERT INTO todos (title, description, completed, category_id, priority, due_date, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        INSERT INTO todos (title, description, completed, user_id, category_id, priority, due_date, parent_id,
+                           series_id, occurrence_at, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
         RETURNING *
         "#,
     )
     .bind(&payload.title)
     .bind(&payload.description)
     .bind(false)
-    .bind(&payload.category_id)
-    .bind(&payload.priority)
-    .bind(&payload.due_date)
+    .bind(auth.id)
+    .bind(payload.category_id)
+    .bind(payload.priority)
+    .bind(payload.due_date)
+    .bind(payload.parent_id)
+    .bind(series_id)
+    .bind(series_id.and(payload.due_date))
     .bind(now)
     .bind(now)
-    .fetch_one(&state.db_pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    // An open subtask reopens a parent that follows its children
+    let completions = subtasks::settle_ancestors(&mut tx, &state.config.subtasks, todo.parent_id).await?;
+    tx.commit().await?;
+
     // Handle tags if provided
     if let Some(tag_names) = &payload.tags {
         for tag_name in tag_names {
-            // For now, we'll assume user_id is required - this would be extracted from auth in real implementation
             let tag = sqlx::query_as::<_, Tag>(
                 "INSERT INTO tags (name, user_id, created_at) VALUES ($1, $2, $3) 
-                 ON CONFLICT (name, user_id) DO UPDATE SET name = EXCLUDED.name
+                 ON CONFLICT (name, user_id) WHERE deleted_at IS NULL DO UPDATE SET name = EXCLUDED.name
                  RETURNING *"
             )
             .bind(tag_name)
-            .bind(todo.user_id.unwrap_or_default()) // This should come from auth
+            .bind(auth.id)
             .bind(now)
             .fetch_one(&state.db_pool)
             .await?;
@@ -127,15 +178,17 @@ pub async fn create_todo(
         todo_id: todo.id,
         title: todo.title.clone(),
         description: todo.description.clone(),
-        user_id: todo.user_id.unwrap_or_default(),
+        user_id: auth.id,
         category_id: todo.category_id,
         priority: todo.priority,
         due_date: todo.due_date,
         tags: payload.tags.unwrap_or_default(),
+        parent_id: todo.parent_id,
     };
     if let Err(e) = state.kafka_producer.publish_todo_created(event).await {
         tracing::warn!("Failed to publish todo created event: {}", e);
     }
+    subtasks::publish_completions(&state, auth.id, completions).await;
 
     let todo_response = get_todo_with_relations(&state.db_pool, todo.id).await?;
     Ok((StatusCode::CREATED, Json(todo_response)))
@@ -143,107 +196,137 @@ pub async fn create_todo(
 
 pub async fn get_todos(
     State(state): State<AppState>,
+    auth: AuthUser,
     Query(params): Query<TodoQuery>,
-) -> Result<Json<TodoListResponse>> {
+    headers: HeaderMap,
+) -> Result<Cached<Json<TodoListResponse>>> {
+    let sort = Sort::parse(params.sort.as_deref())?;
+    let secret = &state.config.cursor_secret;
+    let cursor = params.cursor.as_deref().map(|cursor| Cursor::decode(cursor, secret)).transpose()?;
+    if cursor.is_some() && params.page.is_some() {
+        return Err(AppError::BadRequest("Use either page or cursor, not both".to_string()));
+    }
+    if cursor.as_ref().is_some_and(|cursor| cursor.sort != sort.spec()) {
+        return Err(AppError::BadRequest("The cursor was issued for a different sort".to_string()));
+    }
+
     let page = params.page.unwrap_or(1).max(1);
     let per_page = params.per_page.unwrap_or(10).clamp(1, 100);
-    let offset = (page - 1) * per_page;
+
+    // Each page, filter and sort is validated on its own, with defaults filled in
+    let normalized = TodoQuery {
+        page: cursor.is_none().then_some(page),
+        per_page: Some(per_page),
+        sort: Some(sort.spec()),
+        ..params.clone()
+    };
+    let validators = Validators::for_user(&state.db_pool, auth.id)
+        .await?
+        .varying_by(&format!("{:?}", normalized));
+    if validators.is_current(&headers) {
+        return Ok(Cached::NotModified(validators));
+    }
+    let offset = if cursor.is_some() { 0 } else { (page - 1) * per_page };
 
     let mut query = String::from("SELECT * FROM todos");
     let mut count_query = String::from("SELECT COUNT(*) FROM todos");
-    let mut conditions = Vec::new();
-    let mut query_params = Vec::new();
-    let mut param_index = 1;
+    let mut conditions = vec!["user_id = $1".to_string(), "deleted_at IS NULL".to_string()];
+    let mut query_params = vec![Param::Uuid(auth.id)];
+    let mut param_index = 2;
 
     if let Some(completed) = params.completed {
         conditions.push(format!("completed = ${}", param_index));
-        query_params.push(completed.to_string());
+        query_params.push(Param::Bool(completed));
         param_index += 1;
     }
 
     if let Some(category_id) = params.category_id {
         conditions.push(format!("category_id = ${}", param_index));
-        query_params.push(category_id.to_string());
+        query_params.push(Param::Uuid(category_id));
         param_index += 1;
     }
 
     if let Some(priority) = params.priority {
         conditions.push(format!("priority = ${}", param_index));
-        query_params.push(priority.to_string());
+        query_params.push(Param::Int(priority));
         param_index += 1;
     }
 
-    if let Some(search) = &params.search {
-        if !search.trim().is_empty() {
-            conditions.push(format!("(title ILIKE ${} OR description ILIKE ${})", param_index, param_index));
-            query_params.push(format!("%{}%", search));
-            param_index += 1;
-        }
+    if let Some(search) = &params.search
+        && !search.trim().is_empty()
+    {
+        conditions.push(format!("(title ILIKE ${} OR description ILIKE ${})", param_index, param_index));
+        query_params.push(Param::Text(format!("%{}%", search)));
+        param_index += 1;
     }
 
     if let Some(tag) = &params.tag {
         conditions.push(format!(
-            "id IN (SELECT tt.todo_id FROM todo_tags tt JOIN tags t ON tt.tag_id = t.id WHERE t.name ILIKE ${})",
+            "id IN (SELECT tt.todo_id FROM todo_tags tt JOIN tags t ON tt.tag_id = t.id WHERE t.user_id = $1 AND t.deleted_at IS NULL AND t.name ILIKE ${})",
             param_index
         ));
-        query_params.push(format!("%{}%", tag));
+        query_params.push(Param::Text(format!("%{}%", tag)));
         param_index += 1;
     }
 
+    if let Some(blocked) = params.blocked {
+        let negation = if blocked { "" } else { "NOT " };
+        conditions.push(format!("{}{}", negation, dependencies::HAS_OPEN_BLOCKER));
+    }
+
+    if let Some(actionable) = params.actionable {
+        conditions.push(if actionable {
+            format!("completed = false AND NOT {}", dependencies::HAS_OPEN_BLOCKER)
+        } else {
+            format!("(completed = true OR {})", dependencies::HAS_OPEN_BLOCKER)
+        });
+    }
+
     if params.overdue == Some(true) {
         conditions.push(format!("due_date < ${} AND completed = false", param_index));
-        query_params.push(Utc::now().to_rfc3339());
+        query_params.push(Param::Time(Utc::now()));
     }
 
-    if !conditions.is_empty() {
-        let where_clause = format!(" WHERE {}", conditions.join(" AND "));
-        query.push_str(&where_clause);
-        count_query.push_str(&where_clause);
-    }
+    count_query.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    let count_params = query_params.clone();
 
-    query.push_str(&format!(" ORDER BY created_at DESC LIMIT {} OFFSET {}", per_page, offset));
+    // Keyset pagination: continue after (or before) the cursor's todo
+    let backward = cursor.as_ref().is_some_and(|cursor| cursor.direction == Direction::Prev);
+    if let Some(cursor) = &cursor {
+        conditions.push(sort.beyond(&cursor.keys, cursor.id, backward, &mut query_params)?);
+    }
 
-    let total: i64 = if query_params.is_empty() {
-        sqlx::query_scalar("SELECT COUNT(*) FROM todos")
-            .fetch_one(&state.db_pool)
-            .await?
+    query.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    query.push_str(&format!(
+        " ORDER BY {} LIMIT {} OFFSET {}",
+        sort.order_by(backward),
+        per_page + 1,
+        offset
+    ));
+
+    let total: Option<i64> = if params.with_total {
+        let count = sqlx::query_scalar_with(&count_query, arguments(count_params)?);
+        Some(count.fetch_one(&state.db_pool).await?)
     } else {
-        let mut count_q = sqlx::query_scalar(&count_query);
-        for param in &query_params {
-            if param == "true" || param == "false" {
-                count_q = count_q.bind(param.parse::<bool>().unwrap());
-            } else if let Ok(uuid) = param.parse::<Uuid>() {
-                count_q = count_q.bind(uuid);
-            } else if let Ok(num) = param.parse::<i32>() {
-                count_q = count_q.bind(num);
-            } else if let Ok(datetime) = param.parse::<chrono::DateTime<Utc>>() {
-                count_q = count_q.bind(datetime);
-            } else {
-                count_q = count_q.bind(param);
-            }
-        }
-        count_q.fetch_one(&state.db_pool).await?
+        None
     };
 
-    let todos: Vec<Todo> = if query_params.is_empty() {
-        sqlx::query_as(&query).fetch_all(&state.db_pool).await?
-    } else {
-        let mut q = sqlx::query_as(&query);
-        for param in &query_params {
-            if param == "true" || param == "false" {
-                q = q.bind(param.parse::<bool>().unwrap());
-            } else if let Ok(uuid) = param.parse::<Uuid>() {
-                q = q.bind(uuid);
-            } else if let Ok(num) = param.parse::<i32>() {
-                q = q.bind(num);
-            } else if let Ok(datetime) = param.parse::<chrono::DateTime<Utc>>() {
-                q = q.bind(datetime);
-            } else {
-                q = q.bind(param);
-            }
-        }
-        q.fetch_all(&state.db_pool).await?
+    let mut todos: Vec<Todo> = sqlx::query_as_with(&query, arguments(query_params)?)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    // One more than a page was fetched, to tell whether there is another
+    let has_more = todos.len() as i64 > per_page;
+    todos.truncate(per_page as usize);
+    if backward {
+        todos.reverse();
+    }
+    let (has_next, has_prev) = if backward { (true, has_more) } else { (has_more, cursor.is_some() || offset > 0) };
+    let cursor_for = |todo: &Todo, direction| {
+        Cursor { sort: sort.spec(), keys: sort.values(todo), id: todo.id, direction }.encode(secret)
     };
+    let next_cursor = todos.last().filter(|_| has_next).map(|todo| cursor_for(todo, Direction::Next));
+    let prev_cursor = todos.first().filter(|_| has_prev).map(|todo| cursor_for(todo, Direction::Prev));
 
     // Convert todos with relations
     let mut todo_responses = Vec::new();
@@ -255,35 +338,70 @@ pub async fn get_todos(
     let response = TodoListResponse {
         todos: todo_responses,
         total,
-        page,
+        page: cursor.is_none().then_some(page),
         per_page,
+        next_cursor,
+        prev_cursor,
     };
 
-    Ok(Json(response))
+    Ok(Cached::Fresh(validators, Json(response)))
+}
+
+/// A value bound to the todo list queries, which are assembled at runtime.
+#[derive(Debug, Clone)]
+pub(crate) enum Param {
+    Bool(bool),
+    Int(i32),
+    Uuid(Uuid),
+    Time(DateTime<Utc>),
+    Text(String),
+}
+
+fn arguments(params: Vec<Param>) -> Result<PgArguments> {
+    let mut arguments = PgArguments::default();
+    for param in params {
+        match param {
+            Param::Bool(value) => arguments.add(value),
+            Param::Int(value) => arguments.add(value),
+            Param::Uuid(value) => arguments.add(value),
+            Param::Time(value) => arguments.add(value),
+            Param::Text(value) => arguments.add(value),
+        }
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    Ok(arguments)
 }
 
 pub async fn get_todo(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<TodoResponse>> {
-    let todo_response = get_todo_with_relations(&state.db_pool, id).await
-        .map_err(|_| AppError::NotFound(format!("Todo with id {} not found", id)))?;
+    headers: HeaderMap,
+) -> Result<Cached<Json<TodoResponse>>> {
+    let todo = ownership::owned_todo(&state.db_pool, &auth, id).await?;
+    let validators = Validators::for_todo(&state.db_pool, &todo).await?;
+    if validators.is_current(&headers) {
+        return Ok(Cached::NotModified(validators));
+    }
+    let todo_response = get_todo_with_relations(&state.db_pool, id).await?;
 
-    Ok(Json(todo_response))
+    Ok(Cached::Fresh(validators, Json(todo_response)))
 }
 
 pub async fn update_todo(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(id): Path<Uuid>,
+    Query(query): Query<UpdateTodoQuery>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateTodoRequest>,
-) -> Result<Json<TodoResponse>> {
+) -> Result<TaggedTodo> {
     payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    ownership::ensure_category_reference(&state.db_pool, &auth, payload.category_id).await?;
 
-    let existing_todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&state.db_pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Todo with id {} not found", id)))?;
+    let mut tx = state.db_pool.begin().await?;
+    let (existing_todo, before) = history::lock(&mut tx, &auth, id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, existing_todo.version)?;
 
     let title = payload.title.unwrap_or(existing_todo.title.clone());
     let description = payload.description.or(existing_todo.description.clone());
@@ -304,88 +422,106 @@ pub async fn update_todo(
     .bind(&title)
     .bind(&description)
     .bind(completed)
-    .bind(&category_id)
-    .bind(&priority)
-    .bind(&due_date)
+    .bind(category_id)
+    .bind(priority)
+    .bind(due_date)
     .bind(Utc::now())
     .bind(id)
-    .fetch_one(&state.db_pool)
+    .fetch_one(&mut *tx)
     .await?;
 
-    // Handle tags update if provided
+    let (completions, next_occurrence) =
+        apply_todo_rules(&mut tx, &state, &existing_todo, &updated_todo, query.force).await?;
     if let Some(tag_names) = &payload.tags {
-        // Remove existing tags
-        sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1")
-            .bind(id)
-            .execute(&state.db_pool)
-            .await?;
-
-        // Add new tags
-        for tag_name in tag_names {
-            let tag = sqlx::query_as::<_, Tag>(
-                "INSERT INTO tags (name, user_id, created_at) VALUES ($1, $2, $3) 
-                 ON CONFLICT (name, user_id) DO UPDATE SET name = EXCLUDED.name
-                 RETURNING *"
-            )
-            .bind(tag_name)
-            .bind(updated_todo.user_id.unwrap_or_default())
-            .bind(Utc::now())
-            .fetch_one(&state.db_pool)
-            .await?;
-
-            sqlx::query("INSERT INTO todo_tags (todo_id, tag_id) VALUES ($1, $2)")
-                .bind(id)
-                .bind(tag.id)
-                .execute(&state.db_pool)
-                .await?;
-        }
+        tags::replace_tags(&mut tx, auth.id, id, tag_names).await?;
     }
+    history::record(&mut tx, auth.id, id, &before).await?;
+    tx.commit().await?;
 
     // Publish Kafka event
-    let event = TodoUpdatedEvent {
-        todo_id: updated_todo.id,
-        title: if updated_todo.title != existing_todo.title { Some(updated_todo.title.clone()) } else { None },
-        description: if updated_todo.description != existing_todo.description { updated_todo.description.clone() } else { None },
-        completed: if updated_todo.completed != existing_todo.completed { Some(updated_todo.completed) } else { None },
-        category_id: if updated_todo.category_id != existing_todo.category_id { updated_todo.category_id } else { None },
-        priority: if updated_todo.priority != existing_todo.priority { updated_todo.priority } else { None },
-        due_date: if updated_todo.due_date != existing_todo.due_date { updated_todo.due_date } else { None },
-        tags: payload.tags,
-    };
-    if let Err(e) = state.kafka_producer.publish_todo_updated(event, updated_todo.user_id.unwrap_or_default()).await {
+    let event = todo_updated_event(&existing_todo, &updated_todo, payload.tags);
+    if let Err(e) = state.kafka_producer.publish_todo_updated(event, auth.id).await {
         tracing::warn!("Failed to publish todo updated event: {}", e);
     }
+    subtasks::publish_completions(&state, auth.id, completions).await;
+    if let Some(occurrence) = next_occurrence {
+        recurrence::publish_created(&state, auth.id, occurrence).await;
+    }
 
     let todo_response = get_todo_with_relations(&state.db_pool, id).await?;
-    Ok(Json(todo_response))
+    Ok(preconditions::tagged(todo_response))
 }
 
-pub async fn delete_todo(State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<StatusCode> {
-    // Get todo before deletion for event
-    let existing_todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&state.db_pool)
-        .await?;
+/// The rules that follow a change to a todo's completion state or due date:
+/// blockers, subtask roll-up, reminders and the next occurrence of a series.
+/// Runs in the caller's transaction after `existing` was rewritten as `updated`.
+pub(crate) async fn apply_todo_rules(
+    conn: &mut PgConnection,
+    state: &AppState,
+    existing: &Todo,
+    updated: &Todo,
+    force: bool,
+) -> Result<(Vec<subtasks::CompletionChange>, Option<recurrence::NewOccurrence>)> {
+    let newly_completed = updated.completed && !existing.completed;
+
+    if newly_completed && !force {
+        dependencies::ensure_unblocked(&mut *conn, &[updated.id]).await?;
+    }
+    let completions = if updated.completed != existing.completed {
+        subtasks::propagate_completion(&mut *conn, &state.config.subtasks, updated).await?
+    } else {
+        Vec::new()
+    };
+    if updated.due_date != existing.due_date {
+        reminders::rearm(&mut *conn, updated.id).await?;
+    }
+    let next_occurrence = if newly_completed {
+        recurrence::spawn_next(&mut *conn, updated).await?
+    } else {
+        None
+    };
 
-    let result = sqlx::query("DELETE FROM todos WHERE id = $1")
-        .bind(id)
-        .execute(&state.db_pool)
-        .await?;
+    Ok((completions, next_occurrence))
+}
 
-    if result.rows_affected() == 0 {
-        return Err(AppError::NotFound(format!("Todo with id {} not found", id)));
+/// A `TodoUpdated` event carrying the fields that differ between the two versions.
+pub(crate) fn todo_updated_event(existing: &Todo, updated: &Todo, tags: Option<Vec<String>>) -> TodoUpdatedEvent {
+    TodoUpdatedEvent {
+        todo_id: updated.id,
+        title: if updated.title != existing.title { Some(updated.title.clone()) } else { None },
+        description: if updated.description != existing.description { updated.description.clone() } else { None },
+        completed: if updated.completed != existing.completed { Some(updated.completed) } else { None },
+        category_id: if updated.category_id != existing.category_id { updated.category_id } else { None },
+        priority: if updated.priority != existing.priority { updated.priority } else { None },
+        due_date: if updated.due_date != existing.due_date { updated.due_date } else { None },
+        tags,
     }
+}
 
-    // Publish Kafka event
-    if let Some(todo) = existing_todo {
-        let event = TodoDeletedEvent {
-            todo_id: id,
-            deleted_at: Utc::now(),
-        };
-        if let Err(e) = state.kafka_producer.publish_todo_deleted(event, todo.user_id.unwrap_or_default()).await {
+pub async fn delete_todo(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(params): Query<DeleteTodoQuery>,
+    headers: HeaderMap,
+) -> Result<StatusCode> {
+    let rule = params.subtasks.unwrap_or(state.config.subtasks.on_parent_delete);
+
+    let mut tx = state.db_pool.begin().await?;
+    let todo = ownership::locked_todo(&mut *tx, &auth, id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, todo.version)?;
+    let (deleted, completions) = subtasks::delete_tree(&mut tx, &state.config.subtasks, &todo, rule).await?;
+    tx.commit().await?;
+
+    // Publish Kafka events, including for cascaded subtasks
+    let deleted_at = Utc::now();
+    for todo_id in deleted {
+        let event = TodoDeletedEvent { todo_id, deleted_at };
+        if let Err(e) = state.kafka_producer.publish_todo_deleted(event, auth.id).await {
             tracing::warn!("Failed to publish todo deleted event: {}", e);
         }
     }
+    subtasks::publish_completions(&state, auth.id, completions).await;
 
     Ok(StatusCode::NO_CONTENT)
 }
\ No newline at end of file
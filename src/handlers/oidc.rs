@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Redirect,
+    Json,
+};
+use chrono::Utc;
+
+use crate::{
+    auth::{
+        config::OidcProviderConfig,
+        cookies,
+        oidc::IdTokenClaims,
+        session::generate_token,
+    },
+    error::{AppError, Result},
+    middleware::client::ClientInfo,
+    models::{LoginResponse, OidcCallbackQuery, OidcProviderResponse, User},
+    routes::AppState,
+};
+
+pub async fn list_providers(State(state): State<AppState>) -> Json<Vec<OidcProviderResponse>> {
+    let base = state.config.public_url.trim_end_matches('/');
+
+    Json(
+        state
+            .oidc
+            .provider_names()
+            .into_iter()
+            .map(|name| OidcProviderResponse {
+                name: name.to_string(),
+                login_url: format!("{}/api/auth/oidc/{}/login", base, name),
+            })
+            .collect(),
+    )
+}
+
+/// Redirects the browser to the identity provider, remembering the login's
+/// `state` in a cookie so that only this browser can complete it.
+pub async fn login(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> Result<(HeaderMap, Redirect)> {
+    let (url, login_state) = state.oidc.begin(&state.db_pool, &provider).await?;
+    let cookie = cookies::issue_oidc_state(&state.config.cookie_auth, &login_state, state.oidc.login_ttl_secs());
+    Ok((cookie, Redirect::to(&url)))
+}
+
+/// Completes a login at the identity provider. The linked account is signed
+/// in, provisioned on first use if needed, and gets the same response as a
+/// password login, including the 2FA challenge.
+pub async fn callback(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Query(params): Query<OidcCallbackQuery>,
+) -> Result<(HeaderMap, Json<LoginResponse>)> {
+    if let Some(error) = params.error {
+        let description = params.error_description.unwrap_or_default();
+        return Err(AppError::Unauthorized(format!("Identity provider returned {}: {}", error, description)));
+    }
+    let (Some(code), Some(login_state)) = (params.code, params.state) else {
+        return Err(AppError::BadRequest("Missing code or state".to_string()));
+    };
+    // A callback from another browser would sign this one in to the attacker's account
+    cookies::verify_oidc_state(&headers, &login_state)?;
+
+    let claims = state.oidc.complete(&state.db_pool, &provider, &code, &login_state).await?;
+    let config = state.oidc.provider_config(&provider)?;
+
+    let user = find_or_provision_user(&state, config, &claims).await?;
+    if !user.is_active {
+        return Err(AppError::Unauthorized("Account is disabled".to_string()));
+    }
+
+    sqlx::query(
+        "UPDATE user_identities SET last_login_at = NOW(), email = $1 WHERE provider = $2 AND subject = $3"
+    )
+    .bind(&claims.email)
+    .bind(&provider)
+    .bind(&claims.sub)
+    .execute(&state.db_pool)
+    .await?;
+
+    let response = super::auth::login_response(&state, user, &client).await?;
+    let mut headers = super::auth::login_cookies(&state, &response);
+    headers.extend(cookies::clear_oidc_state(&state.config.cookie_auth));
+    Ok((headers, Json(response)))
+}
+
+/// Maps an external subject to a user. Unknown subjects are linked to the
+/// account with the same email when the provider allows it and both the
+/// provider and the account have verified the address, and otherwise get a
+/// new account. An unverified local account may have been registered by
+/// someone else ahead of its owner, who would then share it with them.
+async fn find_or_provision_user(
+    state: &AppState,
+    provider: &OidcProviderConfig,
+    claims: &IdTokenClaims,
+) -> Result<User> {
+    let linked = sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.* FROM users u
+        JOIN user_identities i ON i.user_id = u.id
+        WHERE i.provider = $1 AND i.subject = $2
+        "#,
+    )
+    .bind(&provider.name)
+    .bind(&claims.sub)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    let email = claims
+        .email
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("Identity provider did not return an email address".to_string()))?;
+    let email_verified = claims.email_verified == Some(true);
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let existing = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = LOWER($1)")
+        .bind(email)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let user = match existing {
+        Some(user) if provider.link_by_email && email_verified && user.email_verified_at.is_some() => user,
+        Some(_) => {
+            return Err(AppError::Conflict(
+                "An account with this email address already exists; log in with its password".to_string(),
+            ));
+        }
+        None => {
+            let username = available_username(&mut tx, claims, email).await?;
+            // The account has no usable password until the user resets it
+            let (random_password, _) = generate_token();
+            let password_hash = state.password_hasher.hash(&random_password).await?;
+            let now = Utc::now();
+
+            sqlx::query_as::<_, User>(
+                r#"
+                INSERT INTO users (username, email, password_hash, full_name, email_verified_at, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING *
+                "#,
+            )
+            .bind(&username)
+            .bind(email)
+            .bind(&password_hash)
+            .bind(&claims.name)
+            .bind(email_verified.then_some(now))
+            .bind(now)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    sqlx::query(
+        "INSERT INTO user_identities (user_id, provider, subject, email) VALUES ($1, $2, $3, $4)"
+    )
+    .bind(user.id)
+    .bind(&provider.name)
+    .bind(&claims.sub)
+    .bind(email)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(user)
+}
+
+/// Derives a username from the token's preferred username or the email's
+/// local part, adding a random suffix if it is taken.
+async fn available_username(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    claims: &IdTokenClaims,
+    email: &str,
+) -> Result<String> {
+    let source = claims
+        .preferred_username
+        .as_deref()
+        .unwrap_or_else(|| email.split('@').next().unwrap_or_default());
+    let mut base: String = source
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .take(40)
+        .collect();
+    if base.len() < 3 {
+        base = format!("user{}", base);
+    }
+
+    let mut candidate = base.clone();
+    for _ in 0..5 {
+        let taken: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)")
+            .bind(&candidate)
+            .fetch_one(&mut **tx)
+            .await?;
+        if !taken {
+            return Ok(candidate);
+        }
+
+        let (suffix, _) = generate_token();
+        candidate = format!("{}_{}", base, &suffix[..6]);
+    }
+
+    Err(AppError::Conflict("Could not choose a username for the new account".to_string()))
+}
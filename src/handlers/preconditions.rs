@@ -0,0 +1,198 @@
+//! Conditional requests. A todo's `ETag` carries its version, and updates
+//! sending `If-Match` with an older one are refused with `412`. Reads carry `ETag` and
+//! `Last-Modified`, and answer `304` when the client's copy is still current.
+
+use axum::{
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    error::{AppError, Result},
+    models::{Todo, TodoResponse},
+};
+
+/// Responses depend on who asks, so only the client may cache them, and
+/// must revalidate before each use.
+const CACHE_CONTROL: &str = "private, no-cache";
+const VARY: &str = "Authorization, Cookie";
+
+/// A todo together with its `ETag` header.
+pub(crate) type TaggedTodo = ([(HeaderName, String); 1], Json<TodoResponse>);
+
+/// The strong entity tag for a todo version.
+pub(crate) fn etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+pub(crate) fn tagged(todo: TodoResponse) -> TaggedTodo {
+    ([(header::ETAG, etag(todo.version))], Json(todo))
+}
+
+/// Checks `If-Match` against the todo's current version, which the caller has
+/// locked. Tags compare strongly, so weak ones never match. Without the header
+/// the request goes ahead, unless `required` is set.
+pub(crate) fn check_if_match(headers: &HeaderMap, required: bool, version: i64) -> Result<()> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        if required {
+            return Err(AppError::PreconditionRequired(
+                "Send If-Match with the todo's ETag".to_string(),
+            ));
+        }
+        return Ok(());
+    };
+
+    let current = etag(version);
+    let matches = value
+        .to_str()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag == current);
+    if !matches {
+        return Err(AppError::PreconditionFailed(format!(
+            "The todo has changed; its current ETag is {}",
+            current
+        )));
+    }
+    Ok(())
+}
+
+/// What a read response is validated by, sent as `ETag` and `Last-Modified`.
+pub struct Validators {
+    etag: String,
+    last_modified: DateTime<Utc>,
+    /// `last_modified` also covers data the `ETag` does not, so a matching
+    /// `If-None-Match` only counts if `If-Modified-Since`, when sent, is current too.
+    modified_beyond_etag: bool,
+}
+
+impl Validators {
+    /// Validators covering everything a user sees in their todo, category, tag
+    /// and stats responses. Besides every change to that data, they move on
+    /// when an open todo becomes overdue.
+    pub(crate) async fn for_user(pool: &DbPool, user_id: Uuid) -> Result<Self> {
+        let (revision, changed_at, overdue_since): (i64, DateTime<Utc>, Option<DateTime<Utc>>) = sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE(m.revision, 0),
+                COALESCE(m.changed_at, u.created_at),
+                (
+                    SELECT MAX(due_date) FROM todos
+                    WHERE user_id = u.id AND completed = FALSE AND deleted_at IS NULL AND due_date <= NOW()
+                )
+            FROM users u
+            LEFT JOIN user_change_markers m ON m.user_id = u.id
+            WHERE u.id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+        // The user id keeps a copy cached for one user from validating for another
+        let overdue_secs = overdue_since.map_or(0, |since| since.timestamp());
+        Ok(Self {
+            etag: format!("W/\"{}-{}-{}\"", user_id.simple(), revision, overdue_secs),
+            last_modified: overdue_since.map_or(changed_at, |since| since.max(changed_at)),
+            modified_beyond_etag: false,
+        })
+    }
+
+    /// Validators for a single todo. The `ETag` is its version, the same strong
+    /// tag updates return. Its response also shows related data, such as
+    /// subtask counts, dependencies and its category, which change without
+    /// bumping the version, so `Last-Modified` follows the user's change marker.
+    pub(crate) async fn for_todo(pool: &DbPool, todo: &Todo) -> Result<Self> {
+        let changed_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT MAX(changed_at) FROM user_change_markers WHERE user_id = $1"
+        )
+        .bind(todo.user_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(Self {
+            etag: etag(todo.version),
+            last_modified: changed_at.map_or(todo.updated_at, |changed_at| changed_at.max(todo.updated_at)),
+            modified_beyond_etag: true,
+        })
+    }
+
+    /// Narrows the validators to one `variant` of a response, such as a page
+    /// or filter of a list, so that a copy of one never validates another.
+    pub(crate) fn varying_by(mut self, variant: &str) -> Self {
+        let digest = Sha256::digest(variant.as_bytes());
+        let opaque = self.etag.strip_suffix('"').unwrap_or(&self.etag);
+        self.etag = format!("{}-{}\"", opaque, hex::encode(&digest[..8]));
+        self
+    }
+
+    /// Validators for an item that changes only along with `last_modified`.
+    pub(crate) fn for_item(last_modified: DateTime<Utc>) -> Self {
+        Self {
+            etag: format!("W/\"{}\"", last_modified.timestamp_micros()),
+            last_modified,
+            modified_beyond_etag: false,
+        }
+    }
+
+    /// Whether the client's copy is current, going by `If-None-Match` or,
+    /// without it, `If-Modified-Since`. Entity tags compare weakly.
+    pub(crate) fn is_current(&self, headers: &HeaderMap) -> bool {
+        let unmodified = headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .map(|since| self.last_modified.timestamp() <= since.timestamp());
+
+        if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+            let current = opaque_tag(&self.etag);
+            let matches = value
+                .to_str()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || opaque_tag(tag) == current);
+            return matches && (!self.modified_beyond_etag || unmodified.unwrap_or(true));
+        }
+
+        unmodified.unwrap_or(false)
+    }
+}
+
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+/// A read response, sent in full or as `304 Not Modified`, with its
+/// validators and caching headers either way.
+pub enum Cached<T> {
+    Fresh(Validators, T),
+    NotModified(Validators),
+}
+
+impl<T: IntoResponse> IntoResponse for Cached<T> {
+    fn into_response(self) -> Response {
+        let (validators, mut response) = match self {
+            Cached::Fresh(validators, body) => (validators, body.into_response()),
+            Cached::NotModified(validators) => (validators, StatusCode::NOT_MODIFIED.into_response()),
+        };
+
+        let last_modified = validators.last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let headers = response.headers_mut();
+        if let Ok(etag) = HeaderValue::from_str(&validators.etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Ok(last_modified) = HeaderValue::from_str(&last_modified) {
+            headers.insert(header::LAST_MODIFIED, last_modified);
+        }
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL));
+        headers.insert(header::VARY, HeaderValue::from_static(VARY));
+        response
+    }
+}
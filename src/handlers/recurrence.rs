@@ -0,0 +1,358 @@
+//! Recurring todos. Each occurrence is an ordinary todo linked to a series;
+//! completing it creates the next occurrence from the series' template, so a
+//! regular `PATCH /api/todos/{id}` only changes that one occurrence.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::Utc;
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::ownership,
+    error::{AppError, Result},
+    handlers::{reminders, subtasks},
+    kafka::{TodoCreatedEvent, TodoDeletedEvent, TodoUpdatedEvent},
+    middleware::auth::AuthUser,
+    models::{
+        CreateTodoRequest, RecurrenceRequest, RecurrenceResponse, SkipOccurrenceResponse, Tag, Todo,
+        TodoResponse, TodoSeries, UpdateSeriesRequest,
+    },
+    recurrence::{parse_timezone, RecurrenceRule},
+    routes::AppState,
+};
+
+use super::{
+    get_todo_with_relations,
+    preconditions::{self, TaggedTodo},
+};
+
+/// An occurrence created by a series, with the tags copied onto it.
+pub(crate) type NewOccurrence = (Todo, Vec<String>);
+
+/// Skips an open occurrence: the next one is created and this one deleted.
+pub async fn skip_occurrence(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<SkipOccurrenceResponse>> {
+    let policy = &state.config.subtasks;
+    let mut tx = state.db_pool.begin().await?;
+
+    let todo = ownership::locked_todo(&mut *tx, &auth, id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, todo.version)?;
+    series_of(&mut tx, &todo).await?;
+    if todo.completed {
+        return Err(AppError::BadRequest("Only open occurrences can be skipped".to_string()));
+    }
+
+    let next = spawn_next(&mut tx, &todo).await?;
+    let (deleted, completions) = subtasks::delete_tree(&mut tx, policy, &todo, policy.on_parent_delete).await?;
+    tx.commit().await?;
+
+    let deleted_at = Utc::now();
+    for todo_id in deleted {
+        let event = TodoDeletedEvent { todo_id, deleted_at };
+        if let Err(e) = state.kafka_producer.publish_todo_deleted(event, auth.id).await {
+            tracing::warn!("Failed to publish todo deleted event: {}", e);
+        }
+    }
+    subtasks::publish_completions(&state, auth.id, completions).await;
+
+    let next = match next {
+        Some(occurrence) => {
+            let todo_id = occurrence.0.id;
+            publish_created(&state, auth.id, occurrence).await;
+            Some(get_todo_with_relations(&state.db_pool, todo_id).await?)
+        }
+        None => None,
+    };
+
+    Ok(Json(SkipOccurrenceResponse { next }))
+}
+
+/// Edits the series a todo belongs to. The template of future occurrences
+/// and every open occurrence are updated; completed ones are left as they were.
+pub async fn update_series(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateSeriesRequest>,
+) -> Result<TaggedTodo> {
+    payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    ownership::ensure_category_reference(&state.db_pool, &auth, payload.category_id).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    let todo = ownership::locked_todo(&mut *tx, &auth, id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, todo.version)?;
+    let series = series_of(&mut tx, &todo).await?;
+
+    let rrule = match &payload.rrule {
+        Some(rrule) => rrule.parse::<RecurrenceRule>()?.to_string(),
+        None => series.rrule,
+    };
+    let timezone = match &payload.timezone {
+        Some(timezone) => parse_timezone(timezone)?.name().to_string(),
+        None => series.timezone,
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE todo_series
+        SET rrule = $1, timezone = $2, title = COALESCE($3, title),
+            description = COALESCE($4, description), category_id = COALESCE($5, category_id),
+            priority = COALESCE($6, priority), tags = COALESCE($7, tags)
+        WHERE id = $8
+        "#,
+    )
+    .bind(&rrule)
+    .bind(&timezone)
+    .bind(&payload.title)
+    .bind(&payload.description)
+    .bind(payload.category_id)
+    .bind(payload.priority)
+    .bind(&payload.tags)
+    .bind(series.id)
+    .execute(&mut *tx)
+    .await?;
+
+    let open: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        UPDATE todos
+        SET title = COALESCE($1, title), description = COALESCE($2, description),
+            category_id = COALESCE($3, category_id), priority = COALESCE($4, priority), updated_at = NOW()
+        WHERE series_id = $5 AND completed = FALSE AND deleted_at IS NULL
+        RETURNING id
+        "#,
+    )
+    .bind(&payload.title)
+    .bind(&payload.description)
+    .bind(payload.category_id)
+    .bind(payload.priority)
+    .bind(series.id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if let Some(tags) = &payload.tags {
+        for todo_id in &open {
+            sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1")
+                .bind(todo_id)
+                .execute(&mut *tx)
+                .await?;
+            attach_tags(&mut tx, auth.id, *todo_id, tags).await?;
+        }
+    }
+    tx.commit().await?;
+
+    for todo_id in open {
+        let event = TodoUpdatedEvent {
+            todo_id,
+            title: payload.title.clone(),
+            description: payload.description.clone(),
+            completed: None,
+            category_id: payload.category_id,
+            priority: payload.priority,
+            due_date: None,
+            tags: payload.tags.clone(),
+        };
+        if let Err(e) = state.kafka_producer.publish_todo_updated(event, auth.id).await {
+            tracing::warn!("Failed to publish todo updated event: {}", e);
+        }
+    }
+
+    Ok(preconditions::tagged(get_todo_with_relations(&state.db_pool, id).await?))
+}
+
+/// Ends the series a todo belongs to. Existing occurrences are kept, but
+/// completing them no longer creates new ones.
+pub async fn stop_series(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TodoResponse>> {
+    let mut tx = state.db_pool.begin().await?;
+    let todo = ownership::owned_todo(&mut *tx, &auth, id).await?;
+    let series = series_of(&mut tx, &todo).await?;
+
+    sqlx::query("UPDATE todo_series SET ended_at = NOW() WHERE id = $1 AND ended_at IS NULL")
+        .bind(series.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Json(get_todo_with_relations(&state.db_pool, id).await?))
+}
+
+/// Starts a series for a todo about to be created from `payload`, whose due
+/// date becomes the first occurrence.
+pub(crate) async fn create_series(
+    conn: &mut PgConnection,
+    auth: &AuthUser,
+    payload: &CreateTodoRequest,
+    recurrence: &RecurrenceRequest,
+) -> Result<Uuid> {
+    let starts_at = payload
+        .due_date
+        .ok_or_else(|| AppError::Validation("Recurring todos need a due_date".to_string()))?;
+    let rule: RecurrenceRule = recurrence.rrule.parse()?;
+    let timezone = parse_timezone(recurrence.timezone.as_deref().unwrap_or("UTC"))?;
+
+    let series_id = sqlx::query_scalar(
+        r#"
+        INSERT INTO todo_series (user_id, rrule, timezone, starts_at, title, description, category_id, priority, tags)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id
+        "#,
+    )
+    .bind(auth.id)
+    .bind(rule.to_string())
+    .bind(timezone.name())
+    .bind(starts_at)
+    .bind(&payload.title)
+    .bind(&payload.description)
+    .bind(payload.category_id)
+    .bind(payload.priority)
+    .bind(payload.tags.clone().unwrap_or_default())
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(series_id)
+}
+
+/// Creates the occurrence following `todo` in its series, unless the series
+/// has ended or run out, or a later occurrence already exists (the todo was
+/// reopened and completed again).
+pub(crate) async fn spawn_next(conn: &mut PgConnection, todo: &Todo) -> Result<Option<NewOccurrence>> {
+    let Some(series_id) = todo.series_id else { return Ok(None) };
+
+    // Locking the series serializes completions of its occurrences
+    let series = sqlx::query_as::<_, TodoSeries>("SELECT * FROM todo_series WHERE id = $1 FOR UPDATE")
+        .bind(series_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if series.ended_at.is_some() {
+        return Ok(None);
+    }
+
+    let current = todo.occurrence_at.unwrap_or(series.starts_at);
+    let has_successor: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM todos WHERE series_id = $1 AND occurrence_at > $2)"
+    )
+    .bind(series_id)
+    .bind(current)
+    .fetch_one(&mut *conn)
+    .await?;
+    if has_successor {
+        return Ok(None);
+    }
+
+    let rule: RecurrenceRule = series.rrule.parse()?;
+    let timezone = parse_timezone(&series.timezone)?;
+    let Some(next) = rule.next_after(series.starts_at, timezone, current) else {
+        sqlx::query("UPDATE todo_series SET ended_at = NOW() WHERE id = $1")
+            .bind(series_id)
+            .execute(&mut *conn)
+            .await?;
+        return Ok(None);
+    };
+
+    let occurrence = sqlx::query_as::<_, Todo>(
+        r#"
+        INSERT INTO todos (title, description, completed, user_id, category_id, priority, due_date, parent_id, series_id, occurrence_at)
+        VALUES ($1, $2, FALSE, $3, $4, $5, $6, $7, $8, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(&series.title)
+    .bind(&series.description)
+    .bind(series.user_id)
+    .bind(series.category_id)
+    .bind(series.priority)
+    .bind(next)
+    .bind(todo.parent_id)
+    .bind(series_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    attach_tags(conn, series.user_id, occurrence.id, &series.tags).await?;
+    reminders::copy_relative(conn, todo.id, occurrence.id).await?;
+    Ok(Some((occurrence, series.tags)))
+}
+
+/// The series of a recurring todo, locked for the rest of the transaction.
+async fn series_of(conn: &mut PgConnection, todo: &Todo) -> Result<TodoSeries> {
+    let series_id = todo
+        .series_id
+        .ok_or_else(|| AppError::BadRequest(format!("Todo {} is not recurring", todo.id)))?;
+
+    let series = sqlx::query_as::<_, TodoSeries>("SELECT * FROM todo_series WHERE id = $1 FOR UPDATE")
+        .bind(series_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(series)
+}
+
+/// The recurrence shown on a todo, if it belongs to a series.
+pub(crate) async fn summary<'e, E: PgExecutor<'e>>(executor: E, todo: &Todo) -> Result<Option<RecurrenceResponse>> {
+    let Some(series_id) = todo.series_id else { return Ok(None) };
+
+    let series = sqlx::query_as::<_, TodoSeries>("SELECT * FROM todo_series WHERE id = $1")
+        .bind(series_id)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(Some(RecurrenceResponse {
+        series_id,
+        rrule: series.rrule,
+        timezone: series.timezone,
+        occurrence_at: todo.occurrence_at,
+        ended_at: series.ended_at,
+    }))
+}
+
+async fn attach_tags(conn: &mut PgConnection, user_id: Uuid, todo_id: Uuid, tag_names: &[String]) -> Result<()> {
+    for tag_name in tag_names {
+        let tag = sqlx::query_as::<_, Tag>(
+            "INSERT INTO tags (name, user_id, created_at) VALUES ($1, $2, $3)
+             ON CONFLICT (name, user_id) WHERE deleted_at IS NULL DO UPDATE SET name = EXCLUDED.name
+             RETURNING *"
+        )
+        .bind(tag_name)
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query("INSERT INTO todo_tags (todo_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(todo_id)
+            .bind(tag.id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Publishes the creation of an occurrence made by its series.
+pub(crate) async fn publish_created(state: &AppState, user_id: Uuid, (todo, tags): NewOccurrence) {
+    let event = TodoCreatedEvent {
+        todo_id: todo.id,
+        title: todo.title,
+        description: todo.description,
+        user_id,
+        category_id: todo.category_id,
+        priority: todo.priority,
+        due_date: todo.due_date,
+        tags,
+        parent_id: todo.parent_id,
+    };
+    if let Err(e) = state.kafka_producer.publish_todo_created(event).await {
+        tracing::warn!("Failed to publish todo created event: {}", e);
+    }
+}
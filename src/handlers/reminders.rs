@@ -0,0 +1,148 @@
+//! Reminders on todos. They are delivered by the scheduler in
+//! `jobs::reminders`; these handlers only manage them.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::ownership,
+    error::{AppError, Result},
+    middleware::auth::AuthUser,
+    models::{CreateReminderRequest, ReminderResponse, TodoReminder},
+    routes::AppState,
+};
+
+pub async fn create_reminder(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreateReminderRequest>,
+) -> Result<(StatusCode, Json<ReminderResponse>)> {
+    payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    let todo = ownership::owned_todo(&state.db_pool, &auth, id).await?;
+
+    match (payload.remind_at, payload.before_due_secs) {
+        (Some(_), None) => {}
+        (None, Some(_)) if todo.due_date.is_none() => {
+            return Err(AppError::BadRequest(
+                "A reminder relative to the due date needs a todo with a due date".to_string(),
+            ));
+        }
+        (None, Some(_)) => {}
+        _ => {
+            return Err(AppError::Validation(
+                "Exactly one of remind_at and before_due_secs is required".to_string(),
+            ));
+        }
+    }
+
+    let reminder = sqlx::query_as::<_, TodoReminder>(
+        r#"
+        INSERT INTO todo_reminders (todo_id, user_id, remind_at, before_due_secs)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(auth.id)
+    .bind(payload.remind_at)
+    .bind(payload.before_due_secs)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(to_response(reminder, todo.due_date))))
+}
+
+pub async fn list_reminders(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ReminderResponse>>> {
+    let todo = ownership::owned_todo(&state.db_pool, &auth, id).await?;
+
+    let reminders = sqlx::query_as::<_, TodoReminder>(
+        "SELECT * FROM todo_reminders WHERE todo_id = $1 ORDER BY created_at"
+    )
+    .bind(id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok(Json(
+        reminders
+            .into_iter()
+            .map(|reminder| to_response(reminder, todo.due_date))
+            .collect(),
+    ))
+}
+
+pub async fn delete_reminder(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, reminder_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    ownership::owned_todo(&state.db_pool, &auth, id).await?;
+
+    let result = sqlx::query("DELETE FROM todo_reminders WHERE id = $1 AND todo_id = $2")
+        .bind(reminder_id)
+        .bind(id)
+        .execute(&state.db_pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Reminder with id {} not found", reminder_id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Makes reminders relative to a todo's due date fire again after the due
+/// date has moved.
+pub(crate) async fn rearm(conn: &mut PgConnection, todo_id: Uuid) -> Result<()> {
+    sqlx::query(
+        "UPDATE todo_reminders SET sent_at = NULL WHERE todo_id = $1 AND before_due_secs IS NOT NULL"
+    )
+    .bind(todo_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Gives a new occurrence of a recurring todo the due-date reminders of the
+/// one it follows.
+pub(crate) async fn copy_relative(conn: &mut PgConnection, from: Uuid, to: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO todo_reminders (todo_id, user_id, before_due_secs)
+        SELECT $2, user_id, before_due_secs FROM todo_reminders
+        WHERE todo_id = $1 AND before_due_secs IS NOT NULL
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+fn to_response(reminder: TodoReminder, due_date: Option<DateTime<Utc>>) -> ReminderResponse {
+    let fire_at = match reminder.before_due_secs {
+        Some(secs) => due_date.map(|due| due - Duration::seconds(secs.into())),
+        None => reminder.remind_at,
+    };
+    ReminderResponse {
+        id: reminder.id,
+        todo_id: reminder.todo_id,
+        remind_at: reminder.remind_at,
+        before_due_secs: reminder.before_due_secs,
+        fire_at,
+        sent_at: reminder.sent_at,
+        created_at: reminder.created_at,
+    }
+}
@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::session,
+    error::{AppError, Result},
+    middleware::auth::AuthUser,
+    models::{Session, SessionResponse},
+    routes::AppState,
+};
+
+fn session_response(session: Session, current_session: Uuid) -> SessionResponse {
+    SessionResponse {
+        current: session.id == current_session,
+        id: session.id,
+        user_agent: session.user_agent,
+        ip_address: session.ip_address,
+        created_at: session.created_at,
+        last_seen_at: session.last_seen_at,
+        expires_at: session.expires_at,
+    }
+}
+
+/// Lists the caller's live sessions, most recently used first.
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<SessionResponse>>> {
+    let current_session = auth.session_id()?;
+    let sessions = session::list_active(&state.db_pool, auth.id).await?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|s| session_response(s, current_session))
+            .collect(),
+    ))
+}
+
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !session::revoke(&state.db_pool, auth.id, id, session::REVOKED_BY_USER).await? {
+        return Err(AppError::NotFound(format!("Session with id {} not found", id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs out every other device, keeping the session the request was made with.
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode> {
+    session::revoke_all(
+        &state.db_pool,
+        auth.id,
+        Some(auth.session_id()?),
+        session::REVOKED_BY_USER,
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
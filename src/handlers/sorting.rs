@@ -0,0 +1,251 @@
+//! Sorting of the todo list. `?sort=-priority,due_date,title` names fields
+//! from a fixed set, each descending when prefixed with `-`. Todos without a
+//! due date or priority come last in either direction, unless the field is
+//! followed by `:nulls_first`. Ties are broken by id so that the order is
+//! stable from one page to the next.
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    models::Todo,
+};
+
+use super::Param;
+
+/// Newest first.
+const DEFAULT_SORT: &str = "-created_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortField {
+    CreatedAt,
+    UpdatedAt,
+    DueDate,
+    Priority,
+    Title,
+    Completed,
+}
+
+impl SortField {
+    const ALL: [SortField; 6] = [
+        SortField::CreatedAt,
+        SortField::UpdatedAt,
+        SortField::DueDate,
+        SortField::Priority,
+        SortField::Title,
+        SortField::Completed,
+    ];
+
+    /// The field's name in `sort`, which is also its column.
+    fn name(self) -> &'static str {
+        match self {
+            SortField::CreatedAt => "created_at",
+            SortField::UpdatedAt => "updated_at",
+            SortField::DueDate => "due_date",
+            SortField::Priority => "priority",
+            SortField::Title => "title",
+            SortField::Completed => "completed",
+        }
+    }
+
+    fn nullable(self) -> bool {
+        matches!(self, SortField::DueDate | SortField::Priority)
+    }
+
+    /// The todo's value of the field, as recorded in cursors.
+    fn value(self, todo: &Todo) -> Value {
+        match self {
+            SortField::CreatedAt => json!(todo.created_at),
+            SortField::UpdatedAt => json!(todo.updated_at),
+            SortField::DueDate => json!(todo.due_date),
+            SortField::Priority => json!(todo.priority),
+            SortField::Title => json!(todo.title),
+            SortField::Completed => json!(todo.completed),
+        }
+    }
+
+    /// A value taken from a cursor, to bind in its place; `None` for NULL.
+    fn param(self, value: &Value) -> Result<Option<Param>> {
+        let param = match self {
+            SortField::CreatedAt | SortField::UpdatedAt | SortField::DueDate => {
+                decode::<Option<DateTime<Utc>>>(value)?.map(Param::Time)
+            }
+            SortField::Priority => decode::<Option<i32>>(value)?.map(Param::Int),
+            SortField::Title => Some(Param::Text(decode(value)?)),
+            SortField::Completed => Some(Param::Bool(decode(value)?)),
+        };
+
+        if param.is_none() && !self.nullable() {
+            return Err(invalid_cursor());
+        }
+        Ok(param)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SortKey {
+    field: SortField,
+    descending: bool,
+    /// Only ever set for nullable fields.
+    nulls_first: bool,
+}
+
+impl SortKey {
+    /// Whether NULLs come after the other values when reading forward, or
+    /// when `backward` is set, backward.
+    fn nulls_after(&self, backward: bool) -> bool {
+        self.nulls_first == backward
+    }
+}
+
+/// The order of a todo list, as given by its `sort` parameter.
+#[derive(Debug)]
+pub(crate) struct Sort {
+    keys: Vec<SortKey>,
+}
+
+impl Sort {
+    /// Returns `400` for unknown or repeated fields, and for NULL placements
+    /// that are unknown or given for a field that is never NULL.
+    pub(crate) fn parse(spec: Option<&str>) -> Result<Self> {
+        let spec = spec.filter(|spec| !spec.trim().is_empty()).unwrap_or(DEFAULT_SORT);
+        let mut keys: Vec<SortKey> = Vec::new();
+
+        for item in spec.split(',').map(str::trim) {
+            let (item, nulls) = match item.split_once(':') {
+                Some((item, nulls)) => (item, Some(nulls)),
+                None => (item, None),
+            };
+            let (name, descending) = match item.strip_prefix('-') {
+                Some(name) => (name, true),
+                None => (item, false),
+            };
+            let field = SortField::ALL.into_iter().find(|field| field.name() == name).ok_or_else(|| {
+                let names: Vec<&str> = SortField::ALL.iter().map(|field| field.name()).collect();
+                AppError::BadRequest(format!("Cannot sort by '{}'; use {}", name, names.join(", ")))
+            })?;
+            if keys.iter().any(|key| key.field == field) {
+                return Err(AppError::BadRequest(format!("'{}' appears more than once in sort", name)));
+            }
+            let nulls_first = match nulls {
+                None | Some("nulls_last") => false,
+                Some("nulls_first") => true,
+                Some(nulls) => {
+                    return Err(AppError::BadRequest(format!(
+                        "Unknown NULL placement '{}'; use nulls_first or nulls_last",
+                        nulls
+                    )));
+                }
+            };
+            if nulls.is_some() && !field.nullable() {
+                return Err(AppError::BadRequest(format!("'{}' is never empty, so takes no NULL placement", name)));
+            }
+            keys.push(SortKey { field, descending, nulls_first });
+        }
+
+        Ok(Self { keys })
+    }
+
+    /// The canonical form of the sort, recorded in cursors.
+    pub(crate) fn spec(&self) -> String {
+        let items: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| {
+                let nulls = if key.nulls_first { ":nulls_first" } else { "" };
+                format!("{}{}{}", if key.descending { "-" } else { "" }, key.field.name(), nulls)
+            })
+            .collect();
+        items.join(",")
+    }
+
+    /// The `ORDER BY` list, reversed for reading a page backwards from a cursor.
+    pub(crate) fn order_by(&self, backward: bool) -> String {
+        let mut terms: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| {
+                let mut term = format!("{} {}", key.field.name(), direction(key.descending != backward));
+                if key.field.nullable() {
+                    term.push_str(if key.nulls_after(backward) { " NULLS LAST" } else { " NULLS FIRST" });
+                }
+                term
+            })
+            .collect();
+        terms.push(format!("id {}", direction(self.id_descending() != backward)));
+        terms.join(", ")
+    }
+
+    /// The todo's sort key values, as recorded in cursors.
+    pub(crate) fn values(&self, todo: &Todo) -> Vec<Value> {
+        self.keys.iter().map(|key| key.field.value(todo)).collect()
+    }
+
+    /// The condition selecting the todos after, or when `backward` before, the
+    /// todo with sort key `values` and `id`. The values it compares with are
+    /// appended to `params`, which must hold the query's parameters so far.
+    pub(crate) fn beyond(&self, values: &[Value], id: Uuid, backward: bool, params: &mut Vec<Param>) -> Result<String> {
+        if values.len() != self.keys.len() {
+            return Err(invalid_cursor());
+        }
+
+        // Lexicographic: some key lies beyond the cursor's and all before it are equal
+        let mut alternatives = Vec::new();
+        let mut equal: Vec<String> = Vec::new();
+        for (key, value) in self.keys.iter().zip(values) {
+            let column = key.field.name();
+            match key.field.param(value)? {
+                // Every non-NULL value lies beyond a NULL, or none does
+                None => {
+                    if !key.nulls_after(backward) {
+                        alternatives.push(all(&equal, format!("{} IS NOT NULL", column)));
+                    }
+                    equal.push(format!("{} IS NULL", column));
+                }
+                Some(param) => {
+                    params.push(param);
+                    let placeholder = params.len();
+                    let op = if key.descending != backward { "<" } else { ">" };
+                    let mut beyond = format!("{} {} ${}", column, op, placeholder);
+                    if key.field.nullable() && key.nulls_after(backward) {
+                        beyond = format!("({} OR {} IS NULL)", beyond, column);
+                    }
+                    alternatives.push(all(&equal, beyond));
+                    equal.push(format!("{} = ${}", column, placeholder));
+                }
+            }
+        }
+
+        params.push(Param::Uuid(id));
+        let op = if self.id_descending() != backward { "<" } else { ">" };
+        alternatives.push(all(&equal, format!("id {} ${}", op, params.len())));
+
+        Ok(format!("({})", alternatives.join(" OR ")))
+    }
+
+    /// The id tiebreaker runs in the direction of the last key.
+    fn id_descending(&self) -> bool {
+        self.keys.last().is_some_and(|key| key.descending)
+    }
+}
+
+fn direction(descending: bool) -> &'static str {
+    if descending { "DESC" } else { "ASC" }
+}
+
+fn all(equal: &[String], last: String) -> String {
+    let mut conditions = equal.to_vec();
+    conditions.push(last);
+    format!("({})", conditions.join(" AND "))
+}
+
+fn decode<T: DeserializeOwned>(value: &Value) -> Result<T> {
+    serde_json::from_value(value.clone()).map_err(|_| invalid_cursor())
+}
+
+fn invalid_cursor() -> AppError {
+    AppError::BadRequest("Invalid cursor".to_string())
+}
@@ -1,5 +1,6 @@
 use axum::{
-    extract::{Query, State},
+    extract::State,
+    http::HeaderMap,
     Json,
 };
 use chrono::Utc;
@@ -8,72 +9,53 @@ use uuid::Uuid;
 use crate::{
     routes::AppState,
     error::Result,
+    handlers::preconditions::{Cached, Validators},
+    middleware::auth::AuthUser,
     models::{
         TodoStatsResponse, PriorityCount, CategoryCount,
     },
 };
 
-#[derive(serde::Deserialize)]
-pub struct StatsQuery {
-    pub user_id: Option<Uuid>,
-}
-
 pub async fn get_todo_statistics(
     State(state): State<AppState>,
-    Query(query): Query<StatsQuery>,
-) -> Result<Json<TodoStatsResponse>> {
-    let user_filter = if let Some(user_id) = query.user_id {
-        format!("WHERE user_id = '{}'", user_id)
-    } else {
-        String::new()
-    };
+    auth: AuthUser,
+    headers: HeaderMap,
+) -> Result<Cached<Json<TodoStatsResponse>>> {
+    let validators = Validators::for_user(&state.db_pool, auth.id).await?;
+    if validators.is_current(&headers) {
+        return Ok(Cached::NotModified(validators));
+    }
 
     // Get basic counts
-    let total_todos: i64 = sqlx::query_scalar(&format!(
-        "SELECT COUNT(*) FROM todos {}",
-        user_filter
-    ))
-    .fetch_one(&state.db_pool)
-    .await?;
-
-    let completed_filter = if user_filter.is_empty() {
-        "WHERE".to_string()
-    } else {
-        format!("{} AND", user_filter)
-    };
+    let total_todos: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos WHERE user_id = $1 AND deleted_at IS NULL")
+        .bind(auth.id)
+        .fetch_one(&state.db_pool)
+        .await?;
 
-    let completed_todos: i64 = sqlx::query_scalar(&format!(
-        "SELECT COUNT(*) FROM todos {} completed = true",
-        completed_filter
-    ))
+    let completed_todos: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM todos WHERE user_id = $1 AND deleted_at IS NULL AND completed = true"
+    )
+    .bind(auth.id)
     .fetch_one(&state.db_pool)
     .await?;
 
     let pending_todos = total_todos - completed_todos;
 
-    let overdue_filter = if user_filter.is_empty() {
-        "WHERE".to_string()
-    } else {
-        format!("{} AND", user_filter)
-    };
-
-    let overdue_todos: i64 = sqlx::query_scalar(&format!(
-        "SELECT COUNT(*) FROM todos {} due_date < $1 AND completed = false",
-        overdue_filter
-    ))
+    let overdue_todos: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM todos WHERE user_id = $1 AND deleted_at IS NULL AND due_date < $2 AND completed = false"
+    )
+    .bind(auth.id)
     .bind(Utc::now())
     .fetch_one(&state.db_pool)
     .await?;
 
     // Get todos by priority
-    let priority_query = format!(
-        "SELECT priority, COUNT(*) as count FROM todos {} GROUP BY priority ORDER BY priority",
-        user_filter
-    );
-    
-    let priority_rows: Vec<(Option<i32>, i64)> = sqlx::query_as(&priority_query)
-        .fetch_all(&state.db_pool)
-        .await?;
+    let priority_rows: Vec<(Option<i32>, i64)> = sqlx::query_as(
+        "SELECT priority, COUNT(*) as count FROM todos WHERE user_id = $1 AND deleted_at IS NULL GROUP BY priority ORDER BY priority"
+    )
+    .bind(auth.id)
+    .fetch_all(&state.db_pool)
+    .await?;
 
     let todos_by_priority: Vec<PriorityCount> = priority_rows
         .into_iter()
@@ -84,24 +66,22 @@ pub async fn get_todo_statistics(
         .collect();
 
     // Get todos by category
-    let category_query = format!(
+    let category_rows: Vec<(Option<Uuid>, Option<String>, i64)> = sqlx::query_as(
         r#"
         SELECT 
-            t.category_id, 
+            c.id as category_id, 
             c.name as category_name, 
             COUNT(*) as count
         FROM todos t
-        LEFT JOIN categories c ON t.category_id = c.id
-        {}
-        GROUP BY t.category_id, c.name
+        LEFT JOIN categories c ON t.category_id = c.id AND c.deleted_at IS NULL
+        WHERE t.user_id = $1 AND t.deleted_at IS NULL
+        GROUP BY c.id, c.name
         ORDER BY count DESC
-        "#,
-        user_filter
-    );
-    
-    let category_rows: Vec<(Option<Uuid>, Option<String>, i64)> = sqlx::query_as(&category_query)
-        .fetch_all(&state.db_pool)
-        .await?;
+        "#
+    )
+    .bind(auth.id)
+    .fetch_all(&state.db_pool)
+    .await?;
 
     let todos_by_category: Vec<CategoryCount> = category_rows
         .into_iter()
@@ -121,5 +101,5 @@ pub async fn get_todo_statistics(
         todos_by_category,
     };
 
-    Ok(Json(stats))
+    Ok(Cached::Fresh(validators, Json(stats)))
 }
@@ -0,0 +1,286 @@
+//! Subtasks: todos nested under another todo of the same user, to any depth.
+//!
+//! Completion rolls along the tree according to `SubtaskConfig`: completing a
+//! todo can complete its whole subtree, and a parent can follow the state of
+//! its children. Every completion changed this way is returned to the caller
+//! so it can be published like a direct update.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::{
+    auth::ownership,
+    config::SubtaskConfig,
+    error::{AppError, Result},
+    kafka::{TodoMovedEvent, TodoUpdatedEvent},
+    middleware::auth::AuthUser,
+    models::{MoveTodoRequest, SubtaskDeletion, Todo, TodoResponse},
+    routes::AppState,
+};
+
+use super::{
+    get_todo_with_relations,
+    preconditions::{self, TaggedTodo},
+};
+
+/// A todo whose `completed` flag was changed by a subtask rule.
+pub(crate) type CompletionChange = (Uuid, bool);
+
+pub async fn list_subtasks(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<TodoResponse>>> {
+    ownership::owned_todo(&state.db_pool, &auth, id).await?;
+
+    let child_ids: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM todos WHERE parent_id = $1 AND deleted_at IS NULL ORDER BY created_at"
+    )
+    .bind(id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let mut subtasks = Vec::with_capacity(child_ids.len());
+    for child_id in child_ids {
+        subtasks.push(get_todo_with_relations(&state.db_pool, child_id).await?);
+    }
+
+    Ok(Json(subtasks))
+}
+
+/// Moves a todo, together with its subtasks, under another todo or to the top level.
+pub async fn move_todo(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<MoveTodoRequest>,
+) -> Result<TaggedTodo> {
+    let policy = &state.config.subtasks;
+    let mut tx = state.db_pool.begin().await?;
+
+    // Concurrent moves could otherwise each pass the cycle check and
+    // together create a loop
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::text, 0))")
+        .bind(auth.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let todo = ownership::locked_todo(&mut *tx, &auth, id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, todo.version)?;
+    if todo.parent_id == payload.parent_id {
+        tx.commit().await?;
+        return Ok(preconditions::tagged(get_todo_with_relations(&state.db_pool, id).await?));
+    }
+    if let Some(parent_id) = payload.parent_id {
+        ensure_parent(&mut tx, &auth, Some(id), parent_id).await?;
+    }
+
+    sqlx::query("UPDATE todos SET parent_id = $1, updated_at = NOW() WHERE id = $2")
+        .bind(payload.parent_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut completions = settle_ancestors(&mut tx, policy, todo.parent_id).await?;
+    completions.extend(settle_ancestors(&mut tx, policy, payload.parent_id).await?);
+    tx.commit().await?;
+
+    let event = TodoMovedEvent {
+        todo_id: id,
+        parent_id: payload.parent_id,
+        previous_parent_id: todo.parent_id,
+    };
+    if let Err(e) = state.kafka_producer.publish_todo_moved(event, auth.id).await {
+        tracing::warn!("Failed to publish todo moved event: {}", e);
+    }
+    publish_completions(&state, auth.id, completions).await;
+
+    Ok(preconditions::tagged(get_todo_with_relations(&state.db_pool, id).await?))
+}
+
+/// Direct subtasks of a todo: `(total, completed)`.
+pub(crate) async fn counts<'e, E: PgExecutor<'e>>(executor: E, todo_id: Uuid) -> Result<(i64, i64)> {
+    let counts = sqlx::query_as(
+        "SELECT COUNT(*), COUNT(*) FILTER (WHERE completed) FROM todos WHERE parent_id = $1 AND deleted_at IS NULL"
+    )
+    .bind(todo_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(counts)
+}
+
+/// Checks that `parent_id` is one of the caller's todos and, when moving
+/// `todo_id`, that it is neither that todo nor one of its subtasks.
+pub(crate) async fn ensure_parent(
+    conn: &mut PgConnection,
+    auth: &AuthUser,
+    todo_id: Option<Uuid>,
+    parent_id: Uuid,
+) -> Result<()> {
+    ownership::owned_todo(&mut *conn, auth, parent_id).await?;
+
+    if let Some(todo_id) = todo_id {
+        let creates_cycle: bool = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT id, parent_id FROM todos WHERE id = $1
+                UNION
+                SELECT t.id, t.parent_id FROM todos t JOIN ancestors a ON t.id = a.parent_id
+            )
+            SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $2)
+            "#,
+        )
+        .bind(parent_id)
+        .bind(todo_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if creates_cycle {
+            return Err(AppError::BadRequest(
+                "A todo cannot be nested under itself or one of its subtasks".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies the subtask rules after `todo`'s completion state has changed.
+pub(crate) async fn propagate_completion(
+    conn: &mut PgConnection,
+    policy: &SubtaskConfig,
+    todo: &Todo,
+) -> Result<Vec<CompletionChange>> {
+    let mut changes = Vec::new();
+
+    if todo.completed && policy.complete_children {
+        let completed: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE subtree AS (
+                SELECT id FROM todos WHERE parent_id = $1 AND deleted_at IS NULL
+                UNION
+                SELECT t.id FROM todos t JOIN subtree s ON t.parent_id = s.id WHERE t.deleted_at IS NULL
+            )
+            UPDATE todos SET completed = TRUE, updated_at = NOW()
+            WHERE id IN (SELECT id FROM subtree) AND completed = FALSE
+            RETURNING id
+            "#,
+        )
+        .bind(todo.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        changes.extend(completed.into_iter().map(|id| (id, true)));
+    }
+
+    changes.extend(settle_ancestors(conn, policy, todo.parent_id).await?);
+    Ok(changes)
+}
+
+/// With `auto_complete_parent`, brings `parent_id` and its ancestors in line
+/// with their subtasks: complete when all of them are, open otherwise.
+pub(crate) async fn settle_ancestors(
+    conn: &mut PgConnection,
+    policy: &SubtaskConfig,
+    parent_id: Option<Uuid>,
+) -> Result<Vec<CompletionChange>> {
+    let mut changes = Vec::new();
+    if !policy.auto_complete_parent {
+        return Ok(changes);
+    }
+
+    let mut next = parent_id;
+    while let Some(id) = next {
+        let changed: Option<(Option<Uuid>, bool)> = sqlx::query_as(
+            r#"
+            UPDATE todos p SET completed = c.all_done, updated_at = NOW()
+            FROM (SELECT bool_and(completed) AS all_done FROM todos WHERE parent_id = $1 AND deleted_at IS NULL) c
+            WHERE p.id = $1 AND c.all_done IS NOT NULL AND p.completed <> c.all_done
+            RETURNING p.parent_id, p.completed
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        // Ancestors further up only change if this one did
+        let Some((grandparent_id, completed)) = changed else { break };
+        changes.push((id, completed));
+        next = grandparent_id;
+    }
+
+    Ok(changes)
+}
+
+/// Moves `todo` to the trash and applies `rule` to its subtasks. Returns the
+/// ids of the deleted todos and the completion changes of its former ancestors.
+///
+/// A cascade gives the whole subtree the same `deleted_at`, which is how
+/// restoring the todo finds the subtasks deleted with it.
+pub(crate) async fn delete_tree(
+    conn: &mut PgConnection,
+    policy: &SubtaskConfig,
+    todo: &Todo,
+    rule: SubtaskDeletion,
+) -> Result<(Vec<Uuid>, Vec<CompletionChange>)> {
+    let deleted: Vec<Uuid> = match rule {
+        SubtaskDeletion::Cascade => {
+            sqlx::query_scalar(
+                r#"
+                WITH RECURSIVE subtree AS (
+                    SELECT id FROM todos WHERE id = $1
+                    UNION
+                    SELECT t.id FROM todos t JOIN subtree s ON t.parent_id = s.id WHERE t.deleted_at IS NULL
+                )
+                UPDATE todos SET deleted_at = NOW(), updated_at = NOW() WHERE id IN (SELECT id FROM subtree)
+                RETURNING id
+                "#,
+            )
+            .bind(todo.id)
+            .fetch_all(&mut *conn)
+            .await?
+        }
+        SubtaskDeletion::Reparent => {
+            sqlx::query(
+                "UPDATE todos SET parent_id = $1, updated_at = NOW() WHERE parent_id = $2 AND deleted_at IS NULL"
+            )
+            .bind(todo.parent_id)
+            .bind(todo.id)
+            .execute(&mut *conn)
+            .await?;
+
+            sqlx::query_scalar("UPDATE todos SET deleted_at = NOW(), updated_at = NOW() WHERE id = $1 RETURNING id")
+                .bind(todo.id)
+                .fetch_all(&mut *conn)
+                .await?
+        }
+    };
+
+    let changes = settle_ancestors(conn, policy, todo.parent_id).await?;
+    Ok((deleted, changes))
+}
+
+/// Publishes an update event for each todo changed by a subtask rule.
+pub(crate) async fn publish_completions(state: &AppState, user_id: Uuid, changes: Vec<CompletionChange>) {
+    for (todo_id, completed) in changes {
+        let event = TodoUpdatedEvent {
+            todo_id,
+            title: None,
+            description: None,
+            completed: Some(completed),
+            category_id: None,
+            priority: None,
+            due_date: None,
+            tags: None,
+        };
+        if let Err(e) = state.kafka_producer.publish_todo_updated(event, user_id).await {
+            tracing::warn!("Failed to publish todo updated event: {}", e);
+        }
+    }
+}
@@ -1,38 +1,40 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
     Json,
 };
 use chrono::Utc;
+use sqlx::PgConnection;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    auth::ownership,
     routes::AppState,
     error::{AppError, Result},
+    handlers::{
+        history,
+        preconditions::{self, Cached, Validators},
+    },
+    middleware::auth::AuthUser,
     models::{
         Tag, TagResponse, CreateTagRequest,
     },
 };
 
-#[derive(serde::Deserialize)]
-pub struct TagQuery {
-    pub user_id: Uuid,
-}
-
 pub async fn create_tag(
     State(state): State<AppState>,
-    Query(query): Query<TagQuery>,
+    auth: AuthUser,
     Json(payload): Json<CreateTagRequest>,
 ) -> Result<(StatusCode, Json<TagResponse>)> {
     payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
     // Check if tag name already exists for this user
     let existing = sqlx::query_as::<_, Tag>(
-        "SELECT * FROM tags WHERE name = $1 AND user_id = $2"
+        "SELECT * FROM tags WHERE name = $1 AND user_id = $2 AND deleted_at IS NULL"
     )
     .bind(&payload.name)
-    .bind(query.user_id)
+    .bind(auth.id)
     .fetch_optional(&state.db_pool)
     .await?;
 
@@ -48,7 +50,7 @@ pub async fn create_tag(
         "#,
     )
     .bind(&payload.name)
-    .bind(query.user_id)
+    .bind(auth.id)
     .bind(Utc::now())
     .fetch_one(&state.db_pool)
     .await?;
@@ -58,40 +60,54 @@ pub async fn create_tag(
 
 pub async fn get_tags(
     State(state): State<AppState>,
-    Query(query): Query<TagQuery>,
-) -> Result<Json<Vec<TagResponse>>> {
+    auth: AuthUser,
+    headers: HeaderMap,
+) -> Result<Cached<Json<Vec<TagResponse>>>> {
+    let validators = Validators::for_user(&state.db_pool, auth.id).await?;
+    if validators.is_current(&headers) {
+        return Ok(Cached::NotModified(validators));
+    }
+
     let tags = sqlx::query_as::<_, Tag>(
-        "SELECT * FROM tags WHERE user_id = $1 ORDER BY name"
+        "SELECT * FROM tags WHERE user_id = $1 AND deleted_at IS NULL ORDER BY name"
     )
-    .bind(query.user_id)
+    .bind(auth.id)
     .fetch_all(&state.db_pool)
     .await?;
 
     let response: Vec<TagResponse> = tags.into_iter().map(TagResponse::from).collect();
-    Ok(Json(response))
+    Ok(Cached::Fresh(validators, Json(response)))
 }
 
 pub async fn get_tag(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(tag_id): Path<Uuid>,
-) -> Result<Json<TagResponse>> {
-    let tag = sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE id = $1")
-        .bind(tag_id)
-        .fetch_optional(&state.db_pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Tag with id {} not found", tag_id)))?;
+    headers: HeaderMap,
+) -> Result<Cached<Json<TagResponse>>> {
+    // Tags cannot be changed, only deleted
+    let tag = ownership::owned_tag(&state.db_pool, &auth, tag_id).await?;
+    let validators = Validators::for_item(tag.created_at);
+    if validators.is_current(&headers) {
+        return Ok(Cached::NotModified(validators));
+    }
 
-    Ok(Json(tag.into()))
+    Ok(Cached::Fresh(validators, Json(tag.into())))
 }
 
 pub async fn delete_tag(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(tag_id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    let result = sqlx::query("DELETE FROM tags WHERE id = $1")
-        .bind(tag_id)
-        .execute(&state.db_pool)
-        .await?;
+    // Links to todos are kept, so restoring the tag brings them back
+    let result = sqlx::query(
+        "UPDATE tags SET deleted_at = NOW() WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL"
+    )
+    .bind(tag_id)
+    .bind(auth.id)
+    .execute(&state.db_pool)
+    .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound(format!("Tag with id {} not found", tag_id)));
@@ -102,54 +118,125 @@ pub async fn delete_tag(
 
 pub async fn assign_tag_to_todo(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path((todo_id, tag_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
 ) -> Result<StatusCode> {
-    // Check if todo and tag exist
-    let todo_exists = sqlx::query("SELECT 1 FROM todos WHERE id = $1")
-        .bind(todo_id)
-        .fetch_optional(&state.db_pool)
-        .await?
-        .is_some();
-
-    if !todo_exists {
-        return Err(AppError::NotFound(format!("Todo with id {} not found", todo_id)));
-    }
-
-    let tag_exists = sqlx::query("SELECT 1 FROM tags WHERE id = $1")
-        .bind(tag_id)
-        .fetch_optional(&state.db_pool)
-        .await?
-        .is_some();
-
-    if !tag_exists {
-        return Err(AppError::NotFound(format!("Tag with id {} not found", tag_id)));
-    }
+    // Both sides of the link must belong to the caller
+    let mut tx = state.db_pool.begin().await?;
+    let (todo, before) = history::lock(&mut tx, &auth, todo_id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, todo.version)?;
+    ownership::owned_tag(&mut *tx, &auth, tag_id).await?;
 
     // Insert the relationship (ignore if it already exists)
-    sqlx::query(
+    let result = sqlx::query(
         "INSERT INTO todo_tags (todo_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
     )
     .bind(todo_id)
     .bind(tag_id)
-    .execute(&state.db_pool)
+    .execute(&mut *tx)
     .await?;
+    if result.rows_affected() > 0 {
+        touch(&mut tx, todo_id).await?;
+    }
+    history::record(&mut tx, auth.id, todo_id, &before).await?;
+    tx.commit().await?;
 
     Ok(StatusCode::OK)
 }
 
 pub async fn remove_tag_from_todo(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path((todo_id, tag_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
 ) -> Result<StatusCode> {
+    let mut tx = state.db_pool.begin().await?;
+    let (todo, before) = history::lock(&mut tx, &auth, todo_id).await?;
+    preconditions::check_if_match(&headers, state.config.require_if_match, todo.version)?;
+
     let result = sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1 AND tag_id = $2")
         .bind(todo_id)
         .bind(tag_id)
-        .execute(&state.db_pool)
+        .execute(&mut *tx)
         .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound("Tag assignment not found".to_string()));
     }
+    touch(&mut tx, todo_id).await?;
+    history::record(&mut tx, auth.id, todo_id, &before).await?;
+    tx.commit().await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Replaces a todo's tags with the tags named in `names`, creating the ones
+/// the user does not have yet.
+pub(crate) async fn replace_tags(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    todo_id: Uuid,
+    names: &[String],
+) -> Result<()> {
+    sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1")
+        .bind(todo_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for name in names {
+        let tag = sqlx::query_as::<_, Tag>(
+            "INSERT INTO tags (name, user_id, created_at) VALUES ($1, $2, $3) 
+             ON CONFLICT (name, user_id) WHERE deleted_at IS NULL DO UPDATE SET name = EXCLUDED.name
+             RETURNING *"
+        )
+        .bind(name)
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query("INSERT INTO todo_tags (todo_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(todo_id)
+            .bind(tag.id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Takes the caller's tags called `names` back out of the trash, unless a live
+/// tag has the name by now. Of several with the same name, the one deleted
+/// last comes back.
+pub(crate) async fn restore_trashed(conn: &mut PgConnection, user_id: Uuid, names: &[String]) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE tags SET deleted_at = NULL
+        WHERE id IN (
+            SELECT DISTINCT ON (name) id FROM tags
+            WHERE user_id = $1 AND name = ANY($2) AND deleted_at IS NOT NULL
+            ORDER BY name, deleted_at DESC
+        )
+        AND NOT EXISTS (
+            SELECT 1 FROM tags live WHERE live.user_id = $1 AND live.name = tags.name AND live.deleted_at IS NULL
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(names)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Marks a todo as changed after its tags were, which bumps its version.
+async fn touch(conn: &mut PgConnection, todo_id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE todos SET updated_at = NOW() WHERE id = $1")
+        .bind(todo_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! The trash: deleted todos, categories and tags, which can be restored or
+//! deleted for good until `jobs::trash_purge` removes them.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{postgres::PgRow, FromRow, PgExecutor};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    handlers::subtasks,
+    kafka::TodoRestoredEvent,
+    middleware::auth::AuthUser,
+    models::{Category, CategoryResponse, Tag, TagResponse, Todo, TodoResponse, TrashResponse, TrashedItem},
+    routes::AppState,
+};
+
+use super::get_todo_with_relations;
+
+/// Tables with a trash, and the column shown as the item's name.
+const TRASHABLE: [(&str, &str); 3] = [("todos", "title"), ("categories", "name"), ("tags", "name")];
+
+pub async fn get_trash(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<TrashResponse>> {
+    let retention = Duration::seconds(state.config.trash_retention_secs);
+    let mut lists = Vec::with_capacity(TRASHABLE.len());
+
+    for (table, name_column) in TRASHABLE {
+        let rows: Vec<(Uuid, String, DateTime<Utc>)> = sqlx::query_as(&format!(
+            "SELECT id, {name_column}, deleted_at FROM {table} \
+             WHERE user_id = $1 AND deleted_at IS NOT NULL ORDER BY deleted_at DESC"
+        ))
+        .bind(auth.id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+        lists.push(
+            rows.into_iter()
+                .map(|(id, name, deleted_at)| TrashedItem {
+                    id,
+                    name,
+                    deleted_at,
+                    purge_after: deleted_at + retention,
+                })
+                .collect(),
+        );
+    }
+
+    let [todos, categories, tags] = <[Vec<TrashedItem>; 3]>::try_from(lists).expect("one list per table");
+    Ok(Json(TrashResponse { todos, categories, tags }))
+}
+
+/// Restores a todo together with the subtasks deleted along with it. If its
+/// parent is still in the trash, it comes back as a top-level todo.
+pub async fn restore_todo(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TodoResponse>> {
+    let policy = &state.config.subtasks;
+    let mut tx = state.db_pool.begin().await?;
+    let todo = trashed::<Todo, _>(&mut *tx, "todos", &auth, id).await?;
+
+    let restored: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id FROM todos WHERE id = $1
+            UNION
+            SELECT t.id FROM todos t JOIN subtree s ON t.parent_id = s.id WHERE t.deleted_at = $2
+        )
+        UPDATE todos SET deleted_at = NULL WHERE id IN (SELECT id FROM subtree)
+        RETURNING id
+        "#,
+    )
+    .bind(id)
+    .bind(todo.deleted_at)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let parent_is_live = match todo.parent_id {
+        Some(parent_id) => sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM todos WHERE id = $1 AND deleted_at IS NULL)"
+        )
+        .bind(parent_id)
+        .fetch_one(&mut *tx)
+        .await?,
+        None => false,
+    };
+    let completions = if parent_is_live {
+        subtasks::settle_ancestors(&mut tx, policy, todo.parent_id).await?
+    } else {
+        sqlx::query("UPDATE todos SET parent_id = NULL WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        Vec::new()
+    };
+    tx.commit().await?;
+
+    let restored_at = Utc::now();
+    for todo_id in restored {
+        let event = TodoRestoredEvent { todo_id, restored_at };
+        if let Err(e) = state.kafka_producer.publish_todo_restored(event, auth.id).await {
+            tracing::warn!("Failed to publish todo restored event: {}", e);
+        }
+    }
+    subtasks::publish_completions(&state, auth.id, completions).await;
+
+    Ok(Json(get_todo_with_relations(&state.db_pool, id).await?))
+}
+
+/// Restores a category, which reappears on the todos that still reference it.
+pub async fn restore_category(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CategoryResponse>> {
+    let mut tx = state.db_pool.begin().await?;
+    let category = trashed::<Category, _>(&mut *tx, "categories", &auth, id).await?;
+    ensure_name_free(&mut *tx, "categories", &auth, &category.name).await?;
+
+    let category = sqlx::query_as::<_, Category>(
+        "UPDATE categories SET deleted_at = NULL WHERE id = $1 RETURNING *"
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| name_conflict(e, &category.name))?;
+    tx.commit().await?;
+
+    Ok(Json(category.into()))
+}
+
+/// Restores a tag together with its links to todos.
+pub async fn restore_tag(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TagResponse>> {
+    let mut tx = state.db_pool.begin().await?;
+    let tag = trashed::<Tag, _>(&mut *tx, "tags", &auth, id).await?;
+    ensure_name_free(&mut *tx, "tags", &auth, &tag.name).await?;
+
+    let tag = sqlx::query_as::<_, Tag>("UPDATE tags SET deleted_at = NULL WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| name_conflict(e, &tag.name))?;
+    tx.commit().await?;
+
+    Ok(Json(tag.into()))
+}
+
+pub async fn purge_todo(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    purge(&state, "todos", &auth, id).await
+}
+
+pub async fn purge_category(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    purge(&state, "categories", &auth, id).await
+}
+
+pub async fn purge_tag(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    purge(&state, "tags", &auth, id).await
+}
+
+/// Deletes everything in the caller's trash for good.
+pub async fn empty_trash(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode> {
+    let mut tx = state.db_pool.begin().await?;
+    for (table, _) in TRASHABLE {
+        sqlx::query(&format!("DELETE FROM {table} WHERE user_id = $1 AND deleted_at IS NOT NULL"))
+            .bind(auth.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// One of the caller's items in the trash of `table`.
+async fn trashed<'e, T, E>(executor: E, table: &str, auth: &AuthUser, id: Uuid) -> Result<T>
+where
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as::<_, T>(&format!(
+        "SELECT * FROM {table} WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL"
+    ))
+    .bind(id)
+    .bind(auth.id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("No item with id {} in the trash", id)))
+}
+
+/// Names are unique among items outside the trash, so a restored item must
+/// not clash with one created since.
+async fn ensure_name_free<'e, E: PgExecutor<'e>>(executor: E, table: &str, auth: &AuthUser, name: &str) -> Result<()> {
+    let taken: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {table} WHERE name = $1 AND user_id = $2 AND deleted_at IS NULL)"
+    ))
+    .bind(name)
+    .bind(auth.id)
+    .fetch_one(executor)
+    .await?;
+
+    if taken {
+        return Err(name_taken(name));
+    }
+    Ok(())
+}
+
+/// An item created with the same name after [`ensure_name_free`] looked is
+/// only caught by the unique index.
+fn name_conflict(error: sqlx::Error, name: &str) -> AppError {
+    match &error {
+        sqlx::Error::Database(e) if e.is_unique_violation() => name_taken(name),
+        _ => error.into(),
+    }
+}
+
+fn name_taken(name: &str) -> AppError {
+    AppError::Conflict(format!("'{}' already exists; rename it before restoring", name))
+}
+
+async fn purge(state: &AppState, table: &str, auth: &AuthUser, id: Uuid) -> Result<StatusCode> {
+    let result = sqlx::query(&format!(
+        "DELETE FROM {table} WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL"
+    ))
+    .bind(id)
+    .bind(auth.id)
+    .execute(&state.db_pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("No item with id {} in the trash", id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,158 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+
+use crate::{
+    auth::{lockout, two_factor},
+    error::{AppError, Result},
+    middleware::{auth::AuthUser, client::ClientInfo},
+    models::{
+        AuthResponse, RecoveryCodesResponse, TwoFactorCodeRequest, TwoFactorEnrollmentResponse,
+        TwoFactorLoginRequest, User,
+    },
+    routes::AppState,
+};
+
+async fn load_user(state: &AppState, auth: &AuthUser) -> Result<User> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", auth.id)))
+}
+
+/// Starts enrollment with a fresh secret. 2FA is not enforced until the
+/// secret is confirmed with a code.
+pub async fn enroll(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<TwoFactorEnrollmentResponse>> {
+    let user = load_user(&state, &auth).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret = two_factor::generate_secret();
+    let totp = two_factor::totp(&secret, &state.config.totp_issuer, &user.username)?;
+
+    sqlx::query("UPDATE users SET totp_secret = $1, totp_last_used_step = NULL WHERE id = $2")
+        .bind(&secret)
+        .bind(user.id)
+        .execute(&state.db_pool)
+        .await?;
+
+    Ok(Json(TwoFactorEnrollmentResponse {
+        otpauth_uri: totp.get_url(),
+        secret,
+    }))
+}
+
+/// Enables 2FA once the user proves their authenticator produces valid codes,
+/// and hands out the recovery codes. They are shown only this once.
+pub async fn confirm(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>> {
+    let user = load_user(&state, &auth).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+    if user.totp_secret.is_none() {
+        return Err(AppError::BadRequest("Two-factor enrollment has not been started".to_string()));
+    }
+
+    if !two_factor::verify_totp(&state.db_pool, &user, &state.config.totp_issuer, &payload.code).await? {
+        return Err(AppError::BadRequest("Invalid two-factor code".to_string()));
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+    sqlx::query("UPDATE users SET totp_enabled_at = NOW() WHERE id = $1")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+    let recovery_codes = two_factor::replace_recovery_codes(&mut tx, user.id).await?;
+    tx.commit().await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Replaces the recovery codes; requires a current TOTP or recovery code.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>> {
+    let user = require_code(&state, &auth, &payload.code).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    let recovery_codes = two_factor::replace_recovery_codes(&mut tx, user.id).await?;
+    tx.commit().await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn disable(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<StatusCode> {
+    let user = require_code(&state, &auth, &payload.code).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    sqlx::query(
+        "UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_used_step = NULL WHERE id = $1"
+    )
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn require_code(state: &AppState, auth: &AuthUser, code: &str) -> Result<User> {
+    let user = load_user(state, auth).await?;
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::BadRequest("Two-factor authentication is not enabled".to_string()));
+    }
+
+    if !two_factor::verify_code(&state.db_pool, &user, &state.config.totp_issuer, code).await? {
+        return Err(AppError::BadRequest("Invalid two-factor code".to_string()));
+    }
+
+    Ok(user)
+}
+
+/// Second login step: exchanges the challenge from `login_user` and a TOTP or
+/// recovery code for tokens. Wrong codes count as failed logins.
+pub async fn verify_login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Result<(HeaderMap, Json<AuthResponse>)> {
+    let user_id = two_factor::verify_challenge(&state.jwt, &payload.challenge_token)?;
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE id = $1 AND is_active = true AND totp_enabled_at IS NOT NULL"
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db_pool)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid or expired two-factor challenge".to_string()))?;
+
+    lockout::check(&state.db_pool, &user.username, client.ip_address.as_deref()).await?;
+
+    if !two_factor::verify_code(&state.db_pool, &user, &state.config.totp_issuer, &payload.code).await? {
+        return Err(super::auth::reject_login(&state, &user.username, Some(user.id), &client, "invalid_two_factor_code").await);
+    }
+
+    let auth = super::auth::complete_login(&state, user, &client).await?;
+    Ok((super::auth::session_cookies(&state, &auth), Json(auth)))
+}
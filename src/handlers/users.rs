@@ -1,31 +1,28 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     Json,
 };
-use bcrypt::{hash, verify, DEFAULT_COST};
 use chrono::Utc;
-use jsonwebtoken::{encode, Header, EncodingKey};
-use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    auth::{
+        account_deletion, lockout, ownership,
+        password::PasswordCheck,
+        session,
+    },
     routes::AppState,
     error::{AppError, Result},
+    middleware::{auth::AuthUser, client::ClientInfo},
     models::{
-        AuthResponse, CreateUserRequest, LoginRequest, UpdateUserRequest, User, UserResponse,
+        AccountDeletionResponse, ChangePasswordRequest, CreateUserRequest, LoginRequest,
+        LoginResponse, UpdateUserRequest, User, UserResponse,
     },
 };
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Claims {
-    pub sub: String, // User ID
-    pub username: String,
-    pub exp: usize, // Expiration time
-}
-
-const JWT_SECRET: &[u8] = b"your-secret-key"; // In production, use environment variable
+pub use crate::auth::Claims;
 
 pub async fn register_user(
     State(state): State<AppState>,
@@ -46,8 +43,7 @@ pub async fn register_user(
         return Err(AppError::Conflict("Username or email already exists".to_string()));
     }
 
-    let password_hash = hash(&payload.password, DEFAULT_COST)
-        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
+    let password_hash = state.password_hasher.hash(&payload.password).await?;
 
     let now = Utc::now();
     let user = sqlx::query_as::<_, User>(
@@ -66,49 +62,77 @@ pub async fn register_user(
     .fetch_one(&state.db_pool)
     .await?;
 
+    super::auth::send_verification_email(&state, &user)?;
+
     Ok((StatusCode::CREATED, Json(user.into())))
 }
 
 pub async fn login_user(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>> {
+) -> Result<(HeaderMap, Json<LoginResponse>)> {
+    lockout::check(&state.db_pool, &payload.username, client.ip_address.as_deref()).await?;
+
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = $1")
         .bind(&payload.username)
         .fetch_optional(&state.db_pool)
-        .await?
-        .ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))?;
+        .await?;
+
+    let Some(user) = user else {
+        // Take as long as a wrong password would, so timing does not give away unknown usernames
+        state.password_hasher.verify_dummy(&payload.password).await?;
+        return Err(super::auth::reject_login(&state, &payload.username, None, &client, "unknown_user").await);
+    };
 
+    let check = state.password_hasher.verify(&payload.password, &user.password_hash).await?;
+
+    if !check.is_valid() {
+        return Err(super::auth::reject_login(&state, &payload.username, Some(user.id), &client, "invalid_password").await);
+    }
+
+    // Only someone who knows the password learns that the account is disabled
     if !user.is_active {
         return Err(AppError::Unauthorized("Account is disabled".to_string()));
     }
 
-    let is_valid = verify(&payload.password, &user.password_hash)
-        .map_err(|e| AppError::Internal(format!("Failed to verify password: {}", e)))?;
+    // The old hash keeps working if the upgrade fails
+    if check == PasswordCheck::ValidNeedsRehash
+        && let Err(e) = upgrade_password_hash(&state, &user, &payload.password).await
+    {
+        tracing::warn!(user_id = %user.id, "Failed to upgrade password hash: {}", e);
+    }
 
-    if !is_valid {
-        return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+    if state.config.require_email_verification && user.email_verified_at.is_none() {
+        return Err(AppError::Forbidden("Email address has not been verified".to_string()));
     }
 
-    let claims = Claims {
-        sub: user.id.to_string(),
-        username: user.username.clone(),
-        exp: (Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
-    };
+    let response = super::auth::login_response(&state, user, &client).await?;
+    Ok((super::auth::login_cookies(&state, &response), Json(response)))
+}
+
+/// Replaces a legacy or under-cost hash once the password has been verified.
+async fn upgrade_password_hash(state: &AppState, user: &User, password: &str) -> Result<()> {
+    let password_hash = state.password_hasher.hash(password).await?;
 
-    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(JWT_SECRET))
-        .map_err(|e| AppError::Internal(format!("Failed to generate token: {}", e)))?;
+    // Skip the update if the password changed concurrently
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3")
+        .bind(&password_hash)
+        .bind(user.id)
+        .bind(&user.password_hash)
+        .execute(&state.db_pool)
+        .await?;
 
-    Ok(Json(AuthResponse {
-        user: user.into(),
-        token,
-    }))
+    Ok(())
 }
 
 pub async fn get_user_profile(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>,
 ) -> Result<Json<UserResponse>> {
+    ownership::ensure_self(&auth, user_id)?;
+
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(user_id)
         .fetch_optional(&state.db_pool)
@@ -120,10 +144,12 @@ pub async fn get_user_profile(
 
 pub async fn update_user_profile(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>,
     Json(payload): Json<UpdateUserRequest>,
 ) -> Result<Json<UserResponse>> {
     payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+    ownership::ensure_self(&auth, user_id)?;
 
     let existing_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(user_id)
@@ -131,41 +157,88 @@ pub async fn update_user_profile(
         .await?
         .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))?;
 
+    let email_changed = payload.email.as_ref().is_some_and(|email| *email != existing_user.email);
     let email = payload.email.unwrap_or(existing_user.email);
     let full_name = payload.full_name.or(existing_user.full_name);
-    let is_active = payload.is_active.unwrap_or(existing_user.is_active);
+    // A new address has to be verified again
+    let email_verified_at = if email_changed { None } else { existing_user.email_verified_at };
 
     let updated_user = sqlx::query_as::<_, User>(
         r#"
         UPDATE users
-        SET email = $1, full_name = $2, is_active = $3, updated_at = $4
+        SET email = $1, full_name = $2, email_verified_at = $3, updated_at = $4
         WHERE id = $5
         RETURNING *
         "#,
     )
     .bind(&email)
     .bind(&full_name)
-    .bind(is_active)
+    .bind(email_verified_at)
     .bind(Utc::now())
     .bind(user_id)
     .fetch_one(&state.db_pool)
     .await?;
 
+    if email_changed {
+        super::auth::send_verification_email(&state, &updated_user)?;
+    }
+
     Ok(Json(updated_user.into()))
 }
 
+/// Requests deletion of the caller's account. It is disabled immediately and
+/// its data purged once the grace period has passed.
 pub async fn delete_user(
     State(state): State<AppState>,
+    auth: AuthUser,
     Path(user_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<AccountDeletionResponse>)> {
+    ownership::ensure_self(&auth, user_id)?;
+
+    let purge_after = account_deletion::schedule(
+        &state.db_pool,
+        user_id,
+        state.config.account_deletion_grace_secs,
+    )
+    .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(AccountDeletionResponse { purge_after })))
+}
+
+/// Changes the caller's password. Every other session is signed out; the one
+/// making the request stays valid.
+pub async fn change_password(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<ChangePasswordRequest>,
 ) -> Result<StatusCode> {
-    let result = sqlx::query("DELETE FROM users WHERE id = $1")
-        .bind(user_id)
-        .execute(&state.db_pool)
-        .await?;
+    payload.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
-    if result.rows_affected() == 0 {
-        return Err(AppError::NotFound(format!("User with id {} not found", user_id)));
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", auth.id)))?;
+
+    let check = state.password_hasher.verify(&payload.current_password, &user.password_hash).await?;
+
+    if !check.is_valid() {
+        return Err(AppError::Unauthorized("Current password is incorrect".to_string()));
     }
 
+    let password_hash = state.password_hasher.hash(&payload.new_password).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = $2 WHERE id = $3")
+        .bind(&password_hash)
+        .bind(Utc::now())
+        .bind(auth.id)
+        .execute(&mut *tx)
+        .await?;
+
+    session::revoke_all(&mut *tx, auth.id, Some(auth.session_id()?), session::REVOKED_PASSWORD_CHANGED).await?;
+
+    tx.commit().await?;
     Ok(StatusCode::NO_CONTENT)
 }
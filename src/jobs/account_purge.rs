@@ -0,0 +1,49 @@
+//! Purges accounts whose deletion grace period has ended.
+
+use chrono::Utc;
+use std::time::Duration;
+
+use crate::{
+    auth::account_deletion,
+    db::DbPool,
+    error::Result,
+    kafka::{EventProducer, UserDeletedEvent},
+};
+
+/// Accounts purged per run; the rest wait for the next one.
+const BATCH_SIZE: i64 = 100;
+
+pub fn spawn(pool: DbPool, producer: EventProducer, interval: Duration) {
+    super::spawn_every("account_purge", interval, move || {
+        let pool = pool.clone();
+        let producer = producer.clone();
+        async move { run_once(&pool, &producer).await.map(|_| ()) }
+    });
+}
+
+/// Purges every account that is due and publishes a `UserDeleted` event for
+/// each. Returns how many were purged.
+pub async fn run_once(pool: &DbPool, producer: &EventProducer) -> Result<usize> {
+    let mut purged = 0;
+
+    for user_id in account_deletion::due(pool, BATCH_SIZE).await? {
+        let Some(account) = account_deletion::purge(pool, user_id).await? else {
+            continue;
+        };
+        purged += 1;
+
+        let event = UserDeletedEvent {
+            user_id: account.user_id,
+            deletion_requested_at: account.requested_at,
+            deleted_at: Utc::now(),
+        };
+        if let Err(e) = producer.publish_user_deleted(event).await {
+            tracing::warn!("Failed to publish user deleted event: {}", e);
+        }
+    }
+
+    if purged > 0 {
+        tracing::info!("Purged {} deleted account(s)", purged);
+    }
+    Ok(purged)
+}
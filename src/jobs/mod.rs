@@ -0,0 +1,26 @@
+//! Background jobs that run on a fixed interval alongside the server.
+
+use std::{future::Future, time::Duration};
+
+pub mod account_purge;
+pub mod reminders;
+pub mod trash_purge;
+
+/// Runs `job` every `interval` until the process exits. Failures are logged and
+/// retried on the next tick.
+pub fn spawn_every<F, Fut>(name: &'static str, interval: Duration, mut job: F)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = crate::error::Result<()>> + Send,
+{
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = job().await {
+                tracing::error!(job = name, "Background job failed: {}", e);
+            }
+        }
+    });
+}
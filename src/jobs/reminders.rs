@@ -0,0 +1,158 @@
+//! Delivers due reminders and reports todos that have become overdue.
+//!
+//! Each run claims its rows with `FOR UPDATE SKIP LOCKED`, so server instances
+//! running the scheduler side by side never pick up the same reminder, and
+//! records the delivery in the same transaction once the event is published.
+//! Anything whose event could not be published stays pending for the next run.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    db::DbPool,
+    error::Result,
+    kafka::{EventProducer, TodoOverdueEvent, TodoReminderDueEvent},
+};
+
+/// Reminders, and separately overdue todos, handled per run; the rest wait
+/// for the next one.
+const BATCH_SIZE: i64 = 100;
+
+#[derive(FromRow)]
+struct DueReminder {
+    id: Uuid,
+    todo_id: Uuid,
+    user_id: Uuid,
+    title: String,
+    due_date: Option<DateTime<Utc>>,
+    fire_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct OverdueTodo {
+    id: Uuid,
+    user_id: Uuid,
+    title: String,
+    due_date: DateTime<Utc>,
+}
+
+pub fn spawn(pool: DbPool, producer: EventProducer, interval: Duration) {
+    super::spawn_every("reminders", interval, move || {
+        let pool = pool.clone();
+        let producer = producer.clone();
+        async move { run_once(&pool, &producer).await.map(|_| ()) }
+    });
+}
+
+/// Publishes a `TodoReminderDue` event for every reminder that is due and a
+/// `TodoOverdue` event for every open todo whose due date has passed. Returns
+/// how many events were published.
+pub async fn run_once(pool: &DbPool, producer: &EventProducer) -> Result<usize> {
+    let reminders = deliver_reminders(pool, producer).await?;
+    let overdue = report_overdue(pool, producer).await?;
+
+    if reminders + overdue > 0 {
+        tracing::info!("Sent {} reminder(s) and {} overdue notice(s)", reminders, overdue);
+    }
+    Ok(reminders + overdue)
+}
+
+async fn deliver_reminders(pool: &DbPool, producer: &EventProducer) -> Result<usize> {
+    let mut tx = pool.begin().await?;
+
+    // Reminders on completed todos are kept, in case the todo is reopened
+    let due = sqlx::query_as::<_, DueReminder>(
+        r#"
+        SELECT r.id, r.todo_id, r.user_id, t.title, t.due_date, f.fire_at
+        FROM todo_reminders r
+        JOIN todos t ON t.id = r.todo_id
+        CROSS JOIN LATERAL (
+            SELECT COALESCE(r.remind_at, t.due_date - make_interval(secs => r.before_due_secs)) AS fire_at
+        ) f
+        WHERE r.sent_at IS NULL AND t.completed = FALSE AND t.deleted_at IS NULL AND f.fire_at <= NOW()
+        ORDER BY f.fire_at
+        LIMIT $1
+        FOR UPDATE OF r SKIP LOCKED
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut sent = Vec::with_capacity(due.len());
+    for reminder in due {
+        let event = TodoReminderDueEvent {
+            reminder_id: reminder.id,
+            todo_id: reminder.todo_id,
+            user_id: reminder.user_id,
+            title: reminder.title,
+            due_date: reminder.due_date,
+            fire_at: reminder.fire_at,
+        };
+        match producer.publish_todo_reminder_due(event, reminder.user_id).await {
+            Ok(()) => sent.push(reminder.id),
+            Err(e) => tracing::warn!("Failed to publish reminder due event: {}", e),
+        }
+    }
+
+    sqlx::query("UPDATE todo_reminders SET sent_at = NOW() WHERE id = ANY($1)")
+        .bind(&sent)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(sent.len())
+}
+
+async fn report_overdue(pool: &DbPool, producer: &EventProducer) -> Result<usize> {
+    let mut tx = pool.begin().await?;
+
+    let overdue = sqlx::query_as::<_, OverdueTodo>(
+        r#"
+        SELECT t.id, t.user_id, t.title, t.due_date
+        FROM todos t
+        WHERE t.completed = FALSE AND t.deleted_at IS NULL AND t.due_date <= NOW() AND t.user_id IS NOT NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM todo_overdue_notices n
+              WHERE n.todo_id = t.id AND n.due_date = t.due_date
+          )
+        ORDER BY t.due_date
+        LIMIT $1
+        FOR UPDATE OF t SKIP LOCKED
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut reported = 0;
+    for todo in overdue {
+        let event = TodoOverdueEvent {
+            todo_id: todo.id,
+            user_id: todo.user_id,
+            title: todo.title,
+            due_date: todo.due_date,
+        };
+        if let Err(e) = producer.publish_todo_overdue(event, todo.user_id).await {
+            tracing::warn!("Failed to publish todo overdue event: {}", e);
+            continue;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO todo_overdue_notices (todo_id, due_date) VALUES ($1, $2)
+            ON CONFLICT (todo_id) DO UPDATE SET due_date = EXCLUDED.due_date, notified_at = NOW()
+            "#,
+        )
+        .bind(todo.id)
+        .bind(todo.due_date)
+        .execute(&mut *tx)
+        .await?;
+        reported += 1;
+    }
+    tx.commit().await?;
+
+    Ok(reported)
+}
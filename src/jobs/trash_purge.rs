@@ -0,0 +1,34 @@
+//! Deletes todos, categories and tags that have been in the trash for longer
+//! than the retention period.
+
+use std::time::Duration;
+
+use crate::{db::DbPool, error::Result};
+
+pub fn spawn(pool: DbPool, retention_secs: i64, interval: Duration) {
+    super::spawn_every("trash_purge", interval, move || {
+        let pool = pool.clone();
+        async move { run_once(&pool, retention_secs).await.map(|_| ()) }
+    });
+}
+
+/// Purges every item deleted more than `retention_secs` ago. Returns how many
+/// were purged, not counting subtasks removed along with their parent.
+pub async fn run_once(pool: &DbPool, retention_secs: i64) -> Result<usize> {
+    let mut purged = 0;
+
+    for table in ["todos", "categories", "tags"] {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {table} WHERE deleted_at < NOW() - $1 * INTERVAL '1 second'"
+        ))
+        .bind(retention_secs)
+        .execute(pool)
+        .await?;
+        purged += result.rows_affected() as usize;
+    }
+
+    if purged > 0 {
+        tracing::info!("Purged {} item(s) from the trash", purged);
+    }
+    Ok(purged)
+}
@@ -45,6 +45,7 @@ impl EventConsumer {
         // Subscribe to all topics
         let topics = vec![
             format!("{}.users", config.topic_prefix),
+            format!("{}.security", config.topic_prefix),
             format!("{}.todos", config.topic_prefix),
             format!("{}.categories", config.topic_prefix),
             format!("{}.tags", config.topic_prefix),
@@ -151,6 +152,16 @@ impl EventConsumer {
                 debug!("User logged in: {} ({})", event.username, event.user_id);
                 // Add custom processing logic here (e.g., update last login)
             }
+            DomainEvent::UserDeleted(event) => {
+                info!("User deleted: {}", event.user_id);
+                // Add custom processing logic here (e.g., purge cached copies)
+            }
+            DomainEvent::AccountLocked(event) => {
+                warn!(
+                    "Login locked for {} '{}' until {} after {} failures",
+                    event.scope, event.key, event.locked_until, event.failed_attempts
+                );
+            }
             DomainEvent::TodoCreated(event) => {
                 info!("Todo created: '{}' for user {}", event.title, event.user_id);
                 // Add custom processing logic here (e.g., send notifications)
@@ -159,6 +170,14 @@ impl EventConsumer {
                 info!("Todo completed: {}", event.todo_id);
                 // Add custom processing logic here (e.g., achievement tracking)
             }
+            DomainEvent::TodoReminderDue(event) => {
+                info!("Reminder {} due for todo '{}' ({})", event.reminder_id, event.title, event.todo_id);
+                // Add custom processing logic here (e.g., push a notification)
+            }
+            DomainEvent::TodoOverdue(event) => {
+                info!("Todo overdue: {} (due {})", event.todo_id, event.due_date);
+                // Add custom processing logic here (e.g., escalate)
+            }
             DomainEvent::TodoDeleted(event) => {
                 info!("Todo deleted: {}", event.todo_id);
                 // Add custom processing logic here (e.g., cleanup related data)
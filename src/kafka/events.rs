@@ -32,12 +32,23 @@ pub enum DomainEvent {
     // User Events
     UserRegistered(UserRegisteredEvent),
     UserLoggedIn(UserLoggedInEvent),
+    UserDeleted(UserDeletedEvent),
+
+    // Security Events
+    LoginFailed(LoginFailedEvent),
+    AccountLocked(AccountLockedEvent),
+    AccountUnlocked(AccountUnlockedEvent),
+    AdminAction(AdminActionEvent),
     
     // Todo Events
     TodoCreated(TodoCreatedEvent),
     TodoUpdated(TodoUpdatedEvent),
     TodoCompleted(TodoCompletedEvent),
     TodoDeleted(TodoDeletedEvent),
+    TodoRestored(TodoRestoredEvent),
+    TodoMoved(TodoMovedEvent),
+    TodoReminderDue(TodoReminderDueEvent),
+    TodoOverdue(TodoOverdueEvent),
     TodosDeletedBatch(TodosDeletedBatchEvent),
     TodosUpdatedBatch(TodosUpdatedBatchEvent),
     
@@ -83,6 +94,56 @@ pub struct UserLoggedInEvent {
     pub login_timestamp: DateTime<Utc>,
 }
 
+/// An account's data has been purged. Consumers holding copies of it must
+/// delete them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDeletedEvent {
+    pub user_id: Uuid,
+    pub deletion_requested_at: DateTime<Utc>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+// Security Events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginFailedEvent {
+    pub username: String,
+    pub user_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub reason: String,
+    pub failed_attempts: i32,
+    pub attempted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountLockedEvent {
+    /// `username` or `ip`
+    pub scope: String,
+    pub key: String,
+    pub user_id: Option<Uuid>,
+    pub failed_attempts: i32,
+    pub locked_until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountUnlockedEvent {
+    pub user_id: Uuid,
+    pub username: String,
+    pub unlocked_by: Uuid,
+    pub unlocked_at: DateTime<Utc>,
+}
+
+/// A change made to an account through the admin API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminActionEvent {
+    /// `deactivated`, `reactivated`, `role_changed` or `sessions_revoked`
+    pub action: String,
+    pub user_id: Uuid,
+    pub username: String,
+    pub performed_by: Uuid,
+    pub details: Option<String>,
+    pub performed_at: DateTime<Utc>,
+}
+
 // Todo Events
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TodoCreatedEvent {
@@ -94,6 +155,7 @@ pub struct TodoCreatedEvent {
     pub priority: Option<i32>,
     pub due_date: Option<DateTime<Utc>>,
     pub tags: Vec<String>,
+    pub parent_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,6 +182,42 @@ pub struct TodoDeletedEvent {
     pub deleted_at: DateTime<Utc>,
 }
 
+/// A todo was brought back from the trash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoRestoredEvent {
+    pub todo_id: Uuid,
+    pub restored_at: DateTime<Utc>,
+}
+
+/// A todo and its subtasks were nested under another todo, or made top-level
+/// when `parent_id` is `None`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoMovedEvent {
+    pub todo_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub previous_parent_id: Option<Uuid>,
+}
+
+/// Published once per reminder; `reminder_id` lets consumers drop a repeat
+/// delivery after a failed commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoReminderDueEvent {
+    pub reminder_id: Uuid,
+    pub todo_id: Uuid,
+    pub user_id: Uuid,
+    pub title: String,
+    pub due_date: Option<DateTime<Utc>>,
+    pub fire_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoOverdueEvent {
+    pub todo_id: Uuid,
+    pub user_id: Uuid,
+    pub title: String,
+    pub due_date: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TodosDeletedBatchEvent {
     pub todo_ids: Vec<Uuid>,
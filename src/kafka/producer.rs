@@ -84,11 +84,21 @@ impl EventProducer {
 
     fn get_topic_for_event(&self, event: &DomainEvent) -> String {
         let topic_suffix = match event {
-            DomainEvent::UserRegistered(_) | DomainEvent::UserLoggedIn(_) => "users",
+            DomainEvent::UserRegistered(_)
+            | DomainEvent::UserLoggedIn(_)
+            | DomainEvent::UserDeleted(_) => "users",
+            DomainEvent::LoginFailed(_)
+            | DomainEvent::AccountLocked(_)
+            | DomainEvent::AccountUnlocked(_)
+            | DomainEvent::AdminAction(_) => "security",
             DomainEvent::TodoCreated(_)
             | DomainEvent::TodoUpdated(_)
             | DomainEvent::TodoCompleted(_)
             | DomainEvent::TodoDeleted(_)
+            | DomainEvent::TodoRestored(_)
+            | DomainEvent::TodoMoved(_)
+            | DomainEvent::TodoReminderDue(_)
+            | DomainEvent::TodoOverdue(_)
             | DomainEvent::TodosDeletedBatch(_)
             | DomainEvent::TodosUpdatedBatch(_) => "todos",
             DomainEvent::CategoryCreated(_)
@@ -105,10 +115,19 @@ impl EventProducer {
         match event {
             DomainEvent::UserRegistered(e) => format!("user.{}", e.user_id),
             DomainEvent::UserLoggedIn(e) => format!("user.{}", e.user_id),
+            DomainEvent::UserDeleted(e) => format!("user.{}", e.user_id),
+            DomainEvent::LoginFailed(e) => format!("login.{}", e.username),
+            DomainEvent::AccountLocked(e) => format!("{}.{}", e.scope, e.key),
+            DomainEvent::AccountUnlocked(e) => format!("user.{}", e.user_id),
+            DomainEvent::AdminAction(e) => format!("user.{}", e.user_id),
             DomainEvent::TodoCreated(e) => format!("todo.{}", e.todo_id),
             DomainEvent::TodoUpdated(e) => format!("todo.{}", e.todo_id),
             DomainEvent::TodoCompleted(e) => format!("todo.{}", e.todo_id),
             DomainEvent::TodoDeleted(e) => format!("todo.{}", e.todo_id),
+            DomainEvent::TodoRestored(e) => format!("todo.{}", e.todo_id),
+            DomainEvent::TodoMoved(e) => format!("todo.{}", e.todo_id),
+            DomainEvent::TodoReminderDue(e) => format!("todo.{}", e.todo_id),
+            DomainEvent::TodoOverdue(e) => format!("todo.{}", e.todo_id),
             DomainEvent::TodosDeletedBatch(_) => "batch.delete".to_string(),
             DomainEvent::TodosUpdatedBatch(_) => "batch.update".to_string(),
             DomainEvent::CategoryCreated(e) => format!("category.{}", e.category_id),
@@ -168,6 +187,42 @@ impl EventProducer {
             .await
     }
 
+    pub async fn publish_user_logged_in(&self, event: crate::kafka::UserLoggedInEvent) -> Result<(), KafkaEventError> {
+        let user_id = event.user_id;
+        self.publish_event(DomainEvent::UserLoggedIn(event), Some(user_id))
+            .await
+    }
+
+    pub async fn publish_user_deleted(&self, event: crate::kafka::UserDeletedEvent) -> Result<(), KafkaEventError> {
+        let user_id = event.user_id;
+        self.publish_event(DomainEvent::UserDeleted(event), Some(user_id))
+            .await
+    }
+
+    pub async fn publish_login_failed(&self, event: crate::kafka::LoginFailedEvent) -> Result<(), KafkaEventError> {
+        let user_id = event.user_id;
+        self.publish_event(DomainEvent::LoginFailed(event), user_id)
+            .await
+    }
+
+    pub async fn publish_account_locked(&self, event: crate::kafka::AccountLockedEvent) -> Result<(), KafkaEventError> {
+        let user_id = event.user_id;
+        self.publish_event(DomainEvent::AccountLocked(event), user_id)
+            .await
+    }
+
+    pub async fn publish_account_unlocked(&self, event: crate::kafka::AccountUnlockedEvent) -> Result<(), KafkaEventError> {
+        let user_id = event.user_id;
+        self.publish_event(DomainEvent::AccountUnlocked(event), Some(user_id))
+            .await
+    }
+
+    pub async fn publish_admin_action(&self, event: crate::kafka::AdminActionEvent) -> Result<(), KafkaEventError> {
+        let user_id = event.user_id;
+        self.publish_event(DomainEvent::AdminAction(event), Some(user_id))
+            .await
+    }
+
     pub async fn publish_todo_created(&self, event: crate::kafka::TodoCreatedEvent) -> Result<(), KafkaEventError> {
         let user_id = event.user_id;
         self.publish_event(DomainEvent::TodoCreated(event), Some(user_id))
@@ -188,4 +243,24 @@ impl EventProducer {
         self.publish_event(DomainEvent::TodoDeleted(event), Some(user_id))
             .await
     }
+
+    pub async fn publish_todo_moved(&self, event: crate::kafka::TodoMovedEvent, user_id: Uuid) -> Result<(), KafkaEventError> {
+        self.publish_event(DomainEvent::TodoMoved(event), Some(user_id))
+            .await
+    }
+
+    pub async fn publish_todo_reminder_due(&self, event: crate::kafka::TodoReminderDueEvent, user_id: Uuid) -> Result<(), KafkaEventError> {
+        self.publish_event(DomainEvent::TodoReminderDue(event), Some(user_id))
+            .await
+    }
+
+    pub async fn publish_todo_overdue(&self, event: crate::kafka::TodoOverdueEvent, user_id: Uuid) -> Result<(), KafkaEventError> {
+        self.publish_event(DomainEvent::TodoOverdue(event), Some(user_id))
+            .await
+    }
+
+    pub async fn publish_todo_restored(&self, event: crate::kafka::TodoRestoredEvent, user_id: Uuid) -> Result<(), KafkaEventError> {
+        self.publish_event(DomainEvent::TodoRestored(event), Some(user_id))
+            .await
+    }
 }
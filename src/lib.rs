@@ -1,10 +1,14 @@
+pub mod auth;
 pub mod config;
 pub mod db;
 pub mod error;
 pub mod handlers;
+pub mod jobs;
 pub mod kafka;
+pub mod mail;
 pub mod middleware;
 pub mod models;
+pub mod recurrence;
 pub mod routes;
 
 pub use config::Config;
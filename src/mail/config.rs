@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    Smtp,
+    File,
+    Log,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MailConfig {
+    pub backend: MailBackend,
+    pub from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_starttls: bool,
+    /// Directory the `file` backend writes one `.eml` file per message into.
+    pub file_dir: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            backend: MailBackend::Log,
+            from: "Todo App <no-reply@localhost>".to_string(),
+            smtp_host: "localhost".to_string(),
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            smtp_starttls: true,
+            file_dir: "mail".to_string(),
+        }
+    }
+}
+
+impl MailConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let backend = match env::var("MAIL_BACKEND").as_deref() {
+            Ok("smtp") => MailBackend::Smtp,
+            Ok("file") => MailBackend::File,
+            _ => MailBackend::Log,
+        };
+
+        Self {
+            backend,
+            from: env::var("MAIL_FROM").unwrap_or(defaults.from),
+            smtp_host: env::var("SMTP_HOST").unwrap_or(defaults.smtp_host),
+            smtp_port: env::var("SMTP_PORT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.smtp_port),
+            smtp_username: env::var("SMTP_USERNAME").ok(),
+            smtp_password: env::var("SMTP_PASSWORD").ok(),
+            smtp_starttls: env::var("SMTP_STARTTLS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.smtp_starttls),
+            file_dir: env::var("MAIL_FILE_DIR").unwrap_or(defaults.file_dir),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::{smtp::build_message, Email, MailError, Mailer};
+
+/// Development backend that writes every message to `<dir>/<recipient>-<id>.eml`.
+pub struct FileMailer {
+    from: String,
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(from: &str, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            from: from.to_string(),
+            dir: dir.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), MailError> {
+        let from = self
+            .from
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::InvalidMessage(format!("MAIL_FROM: {}", e)))?;
+        let file_name = format!("{}-{}.eml", email.to, Uuid::new_v4());
+        let message = build_message(&from, email)?;
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.dir.join(file_name), message.formatted()).await?;
+        Ok(())
+    }
+}
+
+/// Writes messages to the application log instead of delivering them.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), MailError> {
+        tracing::info!(
+            to = %email.to,
+            subject = %email.subject,
+            "Outgoing email (log backend):\n{}",
+            email.body
+        );
+        Ok(())
+    }
+}
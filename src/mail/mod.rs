@@ -0,0 +1,57 @@
+//! Outgoing email. Handlers only see the [`Mailer`] trait; the backend is
+//! chosen by `MAIL_BACKEND` (`smtp`, `file` or `log`).
+
+pub mod config;
+pub mod file;
+pub mod smtp;
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub use config::{MailBackend, MailConfig};
+pub use file::{FileMailer, LogMailer};
+pub use smtp::SmtpMailer;
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("Invalid email: {0}")]
+    InvalidMessage(String),
+
+    #[error("Mail transport error: {0}")]
+    Transport(String),
+
+    #[error("Mail I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), MailError>;
+}
+
+/// Builds the mailer selected in the configuration.
+pub fn from_config(config: &MailConfig) -> Result<Arc<dyn Mailer>, MailError> {
+    Ok(match config.backend {
+        MailBackend::Smtp => Arc::new(SmtpMailer::new(config)?),
+        MailBackend::File => Arc::new(FileMailer::new(&config.from, &config.file_dir)),
+        MailBackend::Log => Arc::new(LogMailer),
+    })
+}
+
+/// Sends `email` without blocking the request. Failures are only logged.
+pub fn send_in_background(mailer: Arc<dyn Mailer>, email: Email) {
+    tokio::spawn(async move {
+        let subject = email.subject.clone();
+        if let Err(e) = mailer.send(email).await {
+            tracing::warn!("Failed to send email '{}': {}", subject, e);
+        }
+    });
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{Email, MailConfig, MailError, Mailer};
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &MailConfig) -> Result<Self, MailError> {
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::InvalidMessage(format!("MAIL_FROM: {}", e)))?;
+
+        let mut builder = if config.smtp_starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+                .map_err(|e| MailError::Transport(e.to_string()))?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+        };
+        builder = builder.port(config.smtp_port);
+
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            from,
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), MailError> {
+        let message = build_message(&self.from, email)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+pub(super) fn build_message(from: &Mailbox, email: Email) -> Result<Message, MailError> {
+    let to = email
+        .to
+        .parse::<Mailbox>()
+        .map_err(|e| MailError::InvalidMessage(format!("recipient {}: {}", email.to, e)))?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body)
+        .map_err(|e| MailError::InvalidMessage(e.to_string()))
+}
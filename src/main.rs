@@ -1,5 +1,5 @@
-use axum_server::{config::Config, db, kafka::EventProducer, routes};
-use std::process;
+use axum_server::{auth::roles, config::Config, db, jobs, kafka::EventProducer, routes};
+use std::{net::SocketAddr, process, time::Duration};
 use tokio::net::TcpListener;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
@@ -30,6 +30,25 @@ async fn main() {
         process::exit(1);
     }
 
+    match roles::grant_bootstrap_admins(&pool, &config.admin_usernames).await {
+        Ok(None) => {}
+        Ok(Some(admins)) => {
+            if !admins.promoted.is_empty() {
+                tracing::info!("Granted the admin role to {} from ADMIN_USERNAMES", admins.promoted.join(", "));
+            }
+            if !admins.not_found.is_empty() {
+                tracing::warn!(
+                    "ADMIN_USERNAMES lists {} without an active, verified account; not promoted",
+                    admins.not_found.join(", ")
+                );
+            }
+        }
+        Err(err) => {
+            tracing::error!("Failed to grant bootstrap admin roles: {}", err);
+            process::exit(1);
+        }
+    }
+
     let kafka_producer = match EventProducer::new(config.kafka.clone()).await {
         Ok(producer) => {
             tracing::info!("Kafka producer initialized successfully");
@@ -44,21 +63,44 @@ async fn main() {
         }
     };
 
-    let app = routes::create_routes(pool, kafka_producer)
-        .layer(axum_server::middleware::create_cors_layer())
+    jobs::account_purge::spawn(
+        pool.clone(),
+        kafka_producer.clone(),
+        Duration::from_secs(config.account_purge_interval_secs),
+    );
+    jobs::reminders::spawn(
+        pool.clone(),
+        kafka_producer.clone(),
+        Duration::from_secs(config.reminder_scan_interval_secs),
+    );
+    jobs::trash_purge::spawn(
+        pool.clone(),
+        config.trash_retention_secs,
+        Duration::from_secs(config.trash_purge_interval_secs),
+    );
+
+    let server_address = config.server_address();
+    let cors_layer = axum_server::middleware::create_cors_layer(&config.cors_allowed_origins);
+    let state = routes::AppState::new(pool, kafka_producer, config).unwrap_or_else(|err| {
+        tracing::error!("Failed to initialize application state: {}", err);
+        process::exit(1);
+    });
+
+    let app = routes::create_routes(state)
+        .layer(cors_layer)
         .layer(axum_server::middleware::create_trace_layer())
         .layer(axum::middleware::from_fn(axum_server::middleware::request_logging));
 
-    let listener = TcpListener::bind(&config.server_address())
+    let listener = TcpListener::bind(&server_address)
         .await
         .unwrap_or_else(|err| {
-            tracing::error!("Failed to bind to {}: {}", config.server_address(), err);
+            tracing::error!("Failed to bind to {}: {}", server_address, err);
             process::exit(1);
         });
 
-    tracing::info!("Server running at http://{}", config.server_address());
+    tracing::info!("Server running at http://{}", server_address);
 
-    axum::serve(listener, app).await.unwrap_or_else(|err| {
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap_or_else(|err| {
         tracing::error!("Server error: {}", err);
         process::exit(1);
     });
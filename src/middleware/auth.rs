@@ -1,46 +1,204 @@
 use axum::{
-    extract::{Request, State},
-    http::{header::AUTHORIZATION, StatusCode},
+    extract::{FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
     middleware::Next,
     response::Response,
 };
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use uuid::Uuid;
 
 use crate::{
-    db::DbPool,
-    handlers::users::Claims,
+    auth::{api_key, cookies, session, Claims},
+    error::{AppError, Result},
+    models::Role,
+    routes::AppState,
 };
 
-const JWT_SECRET: &[u8] = b"your-secret-key"; // In production, use environment variable
+/// How the caller authenticated.
+#[derive(Debug, Clone)]
+pub enum Credential {
+    /// An access token issued for a login session.
+    Session(Uuid),
+    /// An API key, limited to its scopes.
+    ApiKey { id: Uuid, scopes: Vec<String> },
+}
+
+/// The authenticated caller, which `auth_middleware` stores in the request
+/// extensions.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub username: String,
+    pub role: Role,
+    pub credential: Credential,
+}
+
+impl AuthUser {
+    /// The login session of the caller. API keys have none, so endpoints that
+    /// manage the account's own credentials reject them.
+    pub fn session_id(&self) -> Result<Uuid> {
+        match self.credential {
+            Credential::Session(session_id) => Ok(session_id),
+            Credential::ApiKey { .. } => Err(AppError::Forbidden(
+                "This endpoint cannot be used with an API key".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<&Claims> for AuthUser {
+    type Error = AppError;
+
+    fn try_from(claims: &Claims) -> Result<Self> {
+        let id = claims
+            .sub
+            .parse::<Uuid>()
+            .map_err(|_| AppError::Unauthorized("Invalid token subject".to_string()))?;
+
+        Ok(Self {
+            id,
+            username: claims.username.clone(),
+            role: claims.role,
+            credential: Credential::Session(claims.sid),
+        })
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+    }
+}
+
+/// An [`AuthUser`] whose email address has been verified. Use it in place of
+/// `AuthUser` on operations unverified accounts may not perform.
+#[derive(Debug, Clone)]
+pub struct VerifiedUser(pub AuthUser);
+
+impl FromRequestParts<AppState> for VerifiedUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        let auth = AuthUser::from_request_parts(parts, state).await?;
+
+        let verified: Option<bool> = sqlx::query_scalar(
+            "SELECT email_verified_at IS NOT NULL FROM users WHERE id = $1"
+        )
+        .bind(auth.id)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+        if verified != Some(true) {
+            return Err(AppError::Forbidden("Email address has not been verified".to_string()));
+        }
+
+        Ok(Self(auth))
+    }
+}
 
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+}
+
+/// Rejects any request that does not carry a valid Bearer token for a live
+/// session or a live API key. With cookie auth enabled, the session cookie is
+/// accepted in place of the header, subject to the CSRF check.
 pub async fn auth_middleware(
-    State(_pool): State<DbPool>,
+    State(state): State<AppState>,
     mut request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let auth_header = request
-        .headers()
-        .get(AUTHORIZATION)
-        .and_then(|header| header.to_str().ok());
-
-    if let Some(auth_header) = auth_header {
-        if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            match decode::<Claims>(
-                token,
-                &DecodingKey::from_secret(JWT_SECRET),
-                &Validation::default(),
-            ) {
-                Ok(token_data) => {
-                    // Add user info to request extensions
-                    request.extensions_mut().insert(token_data.claims);
-                    return Ok(next.run(request).await);
-                }
-                Err(_) => return Err(StatusCode::UNAUTHORIZED),
+) -> Result<Response> {
+    let token = match bearer_token(request.headers()) {
+        Some(token) => token.to_string(),
+        None if state.config.cookie_auth.enabled => {
+            let token = cookies::get(request.headers(), cookies::SESSION_COOKIE)
+                .filter(|token| !token.is_empty())
+                .ok_or_else(|| AppError::Unauthorized("Missing bearer token or session cookie".to_string()))?;
+            if !request.method().is_safe() {
+                cookies::verify_csrf(request.headers())?;
             }
+            token
         }
+        None => return Err(AppError::Unauthorized("Missing bearer token".to_string())),
+    };
+    let token = token.as_str();
+
+    let auth = if token.starts_with(api_key::KEY_PREFIX) {
+        let (key, username, role) = api_key::authenticate(&state.db_pool, token).await?;
+        AuthUser {
+            id: key.user_id,
+            username,
+            role,
+            credential: Credential::ApiKey { id: key.id, scopes: key.scopes },
+        }
+    } else {
+        let claims = state.jwt.decode::<Claims>(token)?;
+        let auth = AuthUser::try_from(&claims)?;
+
+        if !session::touch(&state.db_pool, claims.sid).await? {
+            return Err(AppError::Unauthorized("Session has been revoked".to_string()));
+        }
+        auth
+    };
+
+    request.extensions_mut().insert(auth);
+    Ok(next.run(request).await)
+}
+
+/// Route layer that lets API keys through only if they carry `scope`. Session
+/// logins have full access. Use with `from_fn_with_state(scope, require_scope)`
+/// inside `auth_middleware`.
+pub async fn require_scope(
+    State(scope): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if let Some(AuthUser { credential: Credential::ApiKey { scopes, .. }, .. }) =
+        request.extensions().get::<AuthUser>()
+        && !scopes.iter().any(|s| s == scope)
+    {
+        return Err(AppError::Forbidden(format!("API key is missing the '{}' scope", scope)));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Route layer for endpoints that manage the account itself, which API keys
+/// may not use.
+pub async fn require_session(request: Request, next: Next) -> Result<Response> {
+    if let Some(auth) = request.extensions().get::<AuthUser>() {
+        auth.session_id()?;
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Route layer that admits callers whose role is at least `role`. Use with
+/// `from_fn_with_state(role, require_role)` inside `auth_middleware`.
+pub async fn require_role(
+    State(role): State<Role>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let permitted = request
+        .extensions()
+        .get::<AuthUser>()
+        .is_some_and(|auth| auth.role >= role);
+
+    if !permitted {
+        return Err(AppError::Forbidden("Insufficient role for this endpoint".to_string()));
     }
 
-    // For now, allow requests without auth for backwards compatibility
-    // In production, you'd return Err(StatusCode::UNAUTHORIZED) here
     Ok(next.run(request).await)
 }
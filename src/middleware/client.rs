@@ -0,0 +1,51 @@
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use std::{convert::Infallible, net::SocketAddr};
+
+use crate::routes::AppState;
+
+/// Where a request came from. Proxy headers are only honoured when
+/// `TRUST_PROXY_HEADERS` is enabled, otherwise the socket address is used.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let forwarded_ip = if state.config.trust_proxy_headers {
+            header("x-forwarded-for")
+                .and_then(|value| value.split(',').next())
+                .map(|ip| ip.trim().to_string())
+                .or_else(|| header("x-real-ip").map(str::to_string))
+        } else {
+            None
+        };
+
+        let ip_address = forwarded_ip.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        });
+
+        Ok(Self {
+            ip_address,
+            user_agent: header(USER_AGENT.as_str()).map(|ua| ua.chars().take(512).collect()),
+        })
+    }
+}
@@ -1,23 +1,55 @@
 use axum::{
     body::Body,
-    http::{Method, Request},
+    http::{header, HeaderName, HeaderValue, Method, Request},
     middleware::Next,
     response::Response,
 };
 use std::time::Duration;
 use tower_http::{
-    cors::{Any, CorsLayer},
+    cors::{AllowOrigin, Any, CorsLayer},
     trace::TraceLayer,
 };
 
+use crate::auth::cookies;
+
 pub mod auth;
+pub mod client;
 
-pub fn create_cors_layer() -> CorsLayer {
-    CorsLayer::new()
-        .allow_origin(Any)
+/// Without `allowed_origins` any origin may call the API, but without
+/// credentials. With them, only those origins may, and browsers send cookies.
+pub fn create_cors_layer(allowed_origins: &[String]) -> CorsLayer {
+    let layer = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE, Method::PUT])
-        .allow_headers(Any)
-        .max_age(Duration::from_secs(3600))
+        .expose_headers([header::ETAG, header::LAST_MODIFIED])
+        .max_age(Duration::from_secs(3600));
+
+    if allowed_origins.is_empty() {
+        return layer.allow_origin(Any).allow_headers(Any);
+    }
+
+    let origins: Vec<HeaderValue> = allowed_origins
+        .iter()
+        .filter_map(|origin| match HeaderValue::from_str(origin.trim_end_matches('/')) {
+            Ok(origin) => Some(origin),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid CORS origin '{}'", origin);
+                None
+            }
+        })
+        .collect();
+
+    // Wildcards are not allowed together with credentials
+    layer
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_credentials(true)
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::IF_MATCH,
+            header::IF_NONE_MATCH,
+            header::IF_MODIFIED_SINCE,
+            HeaderName::from_static(cookies::CSRF_HEADER),
+        ])
 }
 
 pub fn create_trace_layer() -> TraceLayer<tower_http::classify::SharedClassifier<tower_http::classify::ServerErrorsAsFailures>> {
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -16,6 +17,100 @@ pub struct Todo {
     pub due_date: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub parent_id: Option<Uuid>,
+    pub series_id: Option<Uuid>,
+    pub occurrence_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub version: i64,
+}
+
+/// The schedule of a recurring todo and the template its occurrences are
+/// created from.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct TodoSeries {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub rrule: String,
+    pub timezone: String,
+    pub starts_at: DateTime<Utc>,
+    pub title: String,
+    pub description: Option<String>,
+    pub category_id: Option<Uuid>,
+    pub priority: Option<i32>,
+    pub tags: Vec<String>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The fields of a todo tracked by its revision history. Tags are kept by
+/// name, so a revert can bring back a tag that has since been deleted.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct TodoSnapshot {
+    pub title: String,
+    pub description: Option<String>,
+    pub completed: bool,
+    pub category_id: Option<Uuid>,
+    pub priority: Option<i32>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct TodoRevision {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    pub revision: i32,
+    pub actor_id: Option<Uuid>,
+    pub before: sqlx::types::Json<TodoSnapshot>,
+    pub after: sqlx::types::Json<TodoSnapshot>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A reminder for a todo, due either at `remind_at` or `before_due_secs`
+/// before the todo's due date.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct TodoReminder {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    pub user_id: Uuid,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub before_due_secs: Option<i32>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What happens to the subtasks of a deleted todo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtaskDeletion {
+    /// Delete them along with the todo.
+    #[default]
+    Cascade,
+    /// Move them up to the deleted todo's parent.
+    Reparent,
+}
+
+/// Account roles, ordered by privilege: support staff can do everything a
+/// user can, admins everything support staff can.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Support,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Support => "support",
+            Role::Admin => "admin",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -28,6 +123,12 @@ pub struct User {
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    pub totp_last_used_step: Option<i64>,
+    pub role: Role,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -39,6 +140,7 @@ pub struct Category {
     pub user_id: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -47,6 +149,44 @@ pub struct Tag {
     pub name: String,
     pub user_id: Uuid,
     pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_reason: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
 }
 
 // Request/Response models
@@ -61,6 +201,32 @@ pub struct CreateTodoRequest {
     pub priority: Option<i32>,
     pub due_date: Option<DateTime<Utc>>,
     pub tags: Option<Vec<String>>,
+    pub parent_id: Option<Uuid>,
+    /// Makes the todo the first occurrence of a series; requires `due_date`.
+    pub recurrence: Option<RecurrenceRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecurrenceRequest {
+    /// An iCalendar `RRULE` value, e.g. `FREQ=WEEKLY;BYDAY=MO,WE`.
+    pub rrule: String,
+    /// IANA timezone the rule is evaluated in; defaults to UTC.
+    pub timezone: Option<String>,
+}
+
+/// Changes to a series, applied to its template and open occurrences.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateSeriesRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+    pub category_id: Option<Uuid>,
+    #[validate(range(min = 0, max = 4))]
+    pub priority: Option<i32>,
+    pub tags: Option<Vec<String>>,
+    pub rrule: Option<String>,
+    pub timezone: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -95,7 +261,6 @@ pub struct UpdateUserRequest {
     pub email: Option<String>,
     #[validate(length(max = 255))]
     pub full_name: Option<String>,
-    pub is_active: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -130,12 +295,116 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    #[validate(length(min = 6))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[validate(length(min = 6))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUserQuery {
+    /// Matched against username, email and full name.
+    pub search: Option<String>,
+    pub role: Option<Role>,
+    pub is_active: Option<bool>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// The parameters an identity provider redirects back with.
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BatchUpdateTodosRequest {
     pub todo_ids: Vec<Uuid>,
     pub completed: Option<bool>,
     pub category_id: Option<Uuid>,
     pub priority: Option<i32>,
+    /// Expected versions by todo id; the batch fails with `412` if any differs.
+    #[serde(default)]
+    pub versions: HashMap<Uuid, i64>,
+}
+
+/// Exactly one of `remind_at` and `before_due_secs` must be given.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateReminderRequest {
+    pub remind_at: Option<DateTime<Utc>>,
+    #[validate(range(min = 0, max = 31536000))]
+    pub before_due_secs: Option<i32>,
+}
+
+/// `parent_id: null` makes the todo top-level.
+#[derive(Debug, Deserialize)]
+pub struct MoveTodoRequest {
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTodoQuery {
+    /// Completes todos even if they are blocked by open todos.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteTodoQuery {
+    /// Overrides the configured rule for the todo's subtasks.
+    pub subtasks: Option<SubtaskDeletion>,
 }
 
 #[derive(Debug, Serialize)]
@@ -149,10 +418,105 @@ pub struct TodoResponse {
     pub priority: Option<i32>,
     pub due_date: Option<DateTime<Utc>>,
     pub tags: Vec<TagResponse>,
+    pub parent_id: Option<Uuid>,
+    pub subtask_count: i64,
+    pub completed_subtask_count: i64,
+    pub recurrence: Option<RecurrenceResponse>,
+    /// Todos that have to be done before this one can start.
+    pub blocked_by: Vec<Uuid>,
+    /// Todos waiting for this one.
+    pub blocks: Vec<Uuid>,
+    /// Bumped by every change; sent back in `If-Match` to detect lost updates.
+    pub version: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct RecurrenceResponse {
+    pub series_id: Uuid,
+    pub rrule: String,
+    pub timezone: String,
+    pub occurrence_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkipOccurrenceResponse {
+    /// `null` when the skipped occurrence was the series' last.
+    pub next: Option<TodoResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReminderResponse {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub before_due_secs: Option<i32>,
+    /// When the reminder fires; `null` for an offset on a todo without a due date.
+    pub fire_at: Option<DateTime<Utc>>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionResponse {
+    pub revision: i32,
+    pub actor_id: Option<Uuid>,
+    pub changes: Vec<FieldChange>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+impl From<TodoRevision> for RevisionResponse {
+    fn from(revision: TodoRevision) -> Self {
+        let before = serde_json::to_value(&revision.before.0).unwrap_or_default();
+        let after = serde_json::to_value(&revision.after.0).unwrap_or_default();
+
+        let changes = match (before, after) {
+            (serde_json::Value::Object(before), serde_json::Value::Object(mut after)) => before
+                .into_iter()
+                .filter_map(|(field, before)| {
+                    let after = after.remove(&field).unwrap_or_default();
+                    (before != after).then_some(FieldChange { field, before, after })
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            revision: revision.revision,
+            actor_id: revision.actor_id,
+            changes,
+            created_at: revision.created_at,
+        }
+    }
+}
+
+/// Deleted items, newest first.
+#[derive(Debug, Serialize)]
+pub struct TrashResponse {
+    pub todos: Vec<TrashedItem>,
+    pub categories: Vec<TrashedItem>,
+    pub tags: Vec<TrashedItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashedItem {
+    pub id: Uuid,
+    /// The todo's title, or the category's or tag's name.
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+    /// When the item is deleted for good.
+    pub purge_after: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: Uuid,
@@ -162,6 +526,10 @@ pub struct UserResponse {
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub two_factor_enabled: bool,
+    pub role: Role,
 }
 
 #[derive(Debug, Serialize)]
@@ -185,6 +553,102 @@ pub struct TagResponse {
 pub struct AuthResponse {
     pub user: UserResponse,
     pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+/// Returned by login instead of tokens when the account has 2FA enabled.
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    TwoFactorRequired(TwoFactorChallengeResponse),
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Returned once when a key is created; `key` cannot be retrieved later.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKeyResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+    pub key: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountDeletionResponse {
+    /// When the account's data will be permanently deleted.
+    pub purge_after: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OidcProviderResponse {
+    pub name: String,
+    pub login_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserListResponse {
+    pub users: Vec<UserResponse>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SystemStatsResponse {
+    pub total_users: i64,
+    pub active_users: i64,
+    pub verified_users: i64,
+    pub users_by_role: Vec<RoleCount>,
+    pub active_sessions: i64,
+    pub logins_last_24h: i64,
+    pub total_todos: i64,
+    pub completed_todos: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleCount {
+    pub role: Role,
+    pub count: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -220,6 +684,10 @@ impl From<User> for UserResponse {
             is_active: user.is_active,
             created_at: user.created_at,
             updated_at: user.updated_at,
+            last_login_at: user.last_login_at,
+            email_verified_at: user.email_verified_at,
+            two_factor_enabled: user.totp_enabled_at.is_some(),
+            role: user.role,
         }
     }
 }
@@ -250,19 +718,34 @@ impl From<Tag> for TagResponse {
 #[derive(Debug, Serialize)]
 pub struct TodoListResponse {
     pub todos: Vec<TodoResponse>,
-    pub total: i64,
-    pub page: i64,
+    /// Only counted when asked for with `with_total=true`.
+    pub total: Option<i64>,
+    /// Set when paging by page number rather than by cursor.
+    pub page: Option<i64>,
     pub per_page: i64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct TodoQuery {
     pub page: Option<i64>,
     pub per_page: Option<i64>,
+    /// A `next_cursor` or `prev_cursor` from an earlier page; excludes `page`.
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub with_total: bool,
+    /// Comma-separated fields, each descending when prefixed with `-` and
+    /// optionally followed by `:nulls_first` or `:nulls_last`.
+    pub sort: Option<String>,
     pub completed: Option<bool>,
     pub search: Option<String>,
     pub category_id: Option<Uuid>,
     pub priority: Option<i32>,
     pub tag: Option<String>,
     pub overdue: Option<bool>,
+    /// Todos with (`true`) or without (`false`) an open blocker.
+    pub blocked: Option<bool>,
+    /// Open todos without an open blocker (`true`), or the rest (`false`).
+    pub actionable: Option<bool>,
 }
\ No newline at end of file
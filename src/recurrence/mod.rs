@@ -0,0 +1,20 @@
+//! Recurring todos. A series repeats on a subset of the iCalendar `RRULE`
+//! grammar (RFC 5545), evaluated in the series' timezone so that a 9:00
+//! chore stays at 9:00 local time across DST changes.
+//!
+//! Supported parts are `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY` or `YEARLY`),
+//! `INTERVAL`, `COUNT`, `UNTIL`, `BYDAY` (with ordinals such as `-1FR` in
+//! monthly and yearly rules), `BYMONTHDAY`, `BYMONTH` and `WKST`.
+
+mod rrule;
+
+pub use chrono_tz::Tz;
+pub use rrule::{Frequency, RecurrenceRule};
+
+use crate::error::{AppError, Result};
+
+/// Parses an IANA timezone name such as `Europe/Berlin`.
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse()
+        .map_err(|_| AppError::Validation(format!("Unknown timezone '{}'", name)))
+}
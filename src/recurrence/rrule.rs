@@ -0,0 +1,556 @@
+use chrono::{
+    DateTime, Datelike, Days, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use crate::error::{AppError, Result};
+
+/// Periods in a row that may produce no occurrence before a rule is treated
+/// as exhausted, e.g. `FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30`.
+const MAX_EMPTY_PERIODS: u32 = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Until {
+    Utc(DateTime<Utc>),
+    /// A date or floating time, compared in the series' timezone.
+    Local(NaiveDateTime),
+}
+
+/// A parsed `RRULE` value, such as `FREQ=WEEKLY;BYDAY=MO,WE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    until: Option<Until>,
+    /// Weekdays, with an optional ordinal within the month (`2TU`, `-1FR`).
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+    week_start: Weekday,
+}
+
+impl RecurrenceRule {
+    /// The first occurrence strictly after `after` of a series whose first
+    /// occurrence is `start`, or `None` once the series has run out.
+    pub fn next_after(&self, start: DateTime<Utc>, tz: Tz, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.occurrences(start, tz).find(|occurrence| *occurrence > after)
+    }
+
+    /// Every occurrence of the series, starting with `start` itself, which
+    /// counts towards `COUNT` as in RFC 5545.
+    pub fn occurrences(&self, start: DateTime<Utc>, tz: Tz) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        Occurrences {
+            rule: self,
+            tz,
+            start: start.with_timezone(&tz).naive_local(),
+            period: 0,
+            pending: VecDeque::new(),
+            emitted: 0,
+            done: false,
+        }
+    }
+
+    /// Candidate dates of the `period`-th period after the one containing `start`.
+    fn expand(&self, start: NaiveDate, period: u32) -> Option<Vec<NaiveDate>> {
+        let step = period.checked_mul(self.interval)?;
+
+        let mut dates = match self.freq {
+            Frequency::Daily => {
+                let date = start.checked_add_days(Days::new(step.into()))?;
+                let matches_day = self.by_month_day.is_empty()
+                    || self.by_month_day.iter().any(|day| month_day(date, *day) == Some(date));
+                let matches_weekday = self.by_day.is_empty()
+                    || self.by_day.iter().any(|(_, weekday)| *weekday == date.weekday());
+                if matches_day && matches_weekday { vec![date] } else { Vec::new() }
+            }
+            Frequency::Weekly => {
+                let week = start
+                    .checked_sub_days(Days::new(start.weekday().days_since(self.week_start).into()))?
+                    .checked_add_days(Days::new(u64::from(step) * 7))?;
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|(_, weekday)| *weekday).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| week.checked_add_days(Days::new(weekday.days_since(self.week_start).into())))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let months = start.year() * 12 + start.month0() as i32 + i32::try_from(step).ok()?;
+                self.month_dates(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, start)?
+            }
+            Frequency::Yearly => {
+                let year = start.year().checked_add(i32::try_from(step).ok()?)?;
+                let months: Vec<u32> = if !self.by_month.is_empty() {
+                    self.by_month.clone()
+                } else if self.by_day.is_empty() && self.by_month_day.is_empty() {
+                    vec![start.month()]
+                } else {
+                    (1..=12).collect()
+                };
+                let mut dates = Vec::new();
+                for month in months {
+                    dates.extend(self.month_dates(year, month, start)?);
+                }
+                dates
+            }
+        };
+
+        if !self.by_month.is_empty() {
+            dates.retain(|date| self.by_month.contains(&date.month()));
+        }
+        dates.sort();
+        dates.dedup();
+        Some(dates)
+    }
+
+    /// Dates of one month selected by `BYMONTHDAY` and `BYDAY`, or the
+    /// start's day of the month when neither is set.
+    fn month_dates(&self, year: i32, month: u32, start: NaiveDate) -> Option<Vec<NaiveDate>> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+
+        let mut dates: Vec<NaiveDate> = if !self.by_month_day.is_empty() {
+            self.by_month_day.iter().filter_map(|day| month_day(first, *day)).collect()
+        } else if self.by_day.is_empty() {
+            // Months without that day are skipped, as RFC 5545 requires
+            NaiveDate::from_ymd_opt(year, month, start.day()).into_iter().collect()
+        } else {
+            first.iter_days().take_while(|date| date.month() == month).collect()
+        };
+
+        if !self.by_day.is_empty() {
+            let last_day = last_day_of_month(first);
+            dates.retain(|date| {
+                self.by_day.iter().any(|(ordinal, weekday)| {
+                    *weekday == date.weekday()
+                        && match ordinal {
+                            None => true,
+                            Some(n) if *n > 0 => (date.day() as i32 - 1) / 7 + 1 == *n,
+                            Some(n) => (last_day as i32 - date.day() as i32) / 7 + 1 == -n,
+                        }
+                })
+            });
+        }
+
+        Some(dates)
+    }
+}
+
+struct Occurrences<'a> {
+    rule: &'a RecurrenceRule,
+    tz: Tz,
+    start: NaiveDateTime,
+    period: u32,
+    pending: VecDeque<NaiveDate>,
+    emitted: u32,
+    /// Set once `UNTIL` has passed.
+    done: bool,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = DateTime<Utc>;
+
+    fn next(&mut self) -> Option<DateTime<Utc>> {
+        if self.done || self.rule.count.is_some_and(|count| self.emitted >= count) {
+            return None;
+        }
+
+        let local = if self.emitted == 0 {
+            self.start
+        } else {
+            let mut empty_periods = 0;
+            loop {
+                if let Some(date) = self.pending.pop_front() {
+                    break date.and_time(self.start.time());
+                }
+                if empty_periods >= MAX_EMPTY_PERIODS {
+                    return None;
+                }
+
+                let start = self.start;
+                let dates = self.rule.expand(start.date(), self.period)?;
+                self.period += 1;
+                let before = self.pending.len();
+                self.pending.extend(dates.into_iter().filter(|date| date.and_time(start.time()) > start));
+                if self.pending.len() == before {
+                    empty_periods += 1;
+                }
+            }
+        };
+
+        let occurrence = resolve(self.tz, local);
+        let within_until = match self.rule.until {
+            None => true,
+            Some(Until::Utc(until)) => occurrence <= until,
+            Some(Until::Local(until)) => local <= until,
+        };
+        if !within_until {
+            self.done = true;
+            return None;
+        }
+
+        self.emitted += 1;
+        Some(occurrence)
+    }
+}
+
+/// Converts a wall-clock time to UTC. Times skipped by a DST change move
+/// forward by the length of the gap; repeated times use the first instance.
+fn resolve(tz: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.with_timezone(&Utc),
+        LocalResult::None => {
+            let before = tz.offset_from_utc_datetime(&(local - TimeDelta::days(1)));
+            let after = tz.offset_from_utc_datetime(&(local + TimeDelta::days(1)));
+            let gap = after.fix().local_minus_utc() - before.fix().local_minus_utc();
+            tz.from_local_datetime(&(local + TimeDelta::seconds(gap.into())))
+                .earliest()
+                .map(|time| time.with_timezone(&Utc))
+                .unwrap_or_else(|| Utc.from_utc_datetime(&local))
+        }
+    }
+}
+
+/// The `day`-th day of `date`'s month, counting from the end when negative.
+fn month_day(date: NaiveDate, day: i32) -> Option<NaiveDate> {
+    let last_day = last_day_of_month(date) as i32;
+    let day = if day > 0 { day } else { last_day + 1 + day };
+    if !(1..=last_day).contains(&day) {
+        return None;
+    }
+    date.with_day(day as u32)
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+fn invalid(message: impl fmt::Display) -> AppError {
+    AppError::Validation(format!("Invalid recurrence rule: {}", message))
+}
+
+fn parse_number<T: FromStr + PartialOrd>(name: &str, value: &str, min: T, max: T) -> Result<T> {
+    value
+        .parse::<T>()
+        .ok()
+        .filter(|number| *number >= min && *number <= max)
+        .ok_or_else(|| invalid(format!("{}={} is out of range", name, value)))
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday> {
+    Ok(match value {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return Err(invalid(format!("'{}' is not a weekday", value))),
+    })
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_until(value: &str) -> Result<Until> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map(|time| Until::Utc(time.and_utc()))
+            .map_err(|_| invalid(format!("UNTIL={} is not a valid time", value)))
+    } else if value.contains('T') {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map(Until::Local)
+            .map_err(|_| invalid(format!("UNTIL={} is not a valid time", value)))
+    } else {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(23, 59, 59))
+            .map(Until::Local)
+            .ok_or_else(|| invalid(format!("UNTIL={} is not a valid date", value)))
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        // Every valid rule is ASCII, which keeps the byte slicing below on char boundaries
+        if !value.is_ascii() {
+            return Err(invalid("only ASCII characters are allowed"));
+        }
+        let value = value.trim().to_ascii_uppercase();
+        let value = value.strip_prefix("RRULE:").unwrap_or(&value);
+
+        let mut freq = None;
+        let mut rule = RecurrenceRule {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            week_start: Weekday::Mon,
+        };
+
+        for part in value.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("'{}' is not NAME=VALUE", part)))?;
+            match name {
+                "FREQ" => {
+                    freq = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid(format!("FREQ={} is not supported", value))),
+                    })
+                }
+                "INTERVAL" => rule.interval = parse_number(name, value, 1, 1000)?,
+                "COUNT" => rule.count = Some(parse_number(name, value, 1, 10_000)?),
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(|day| {
+                            let (ordinal, weekday) = day.split_at(day.len().saturating_sub(2));
+                            let ordinal = match ordinal {
+                                "" => None,
+                                ordinal => match parse_number(name, ordinal.trim_start_matches('+'), -5, 5)? {
+                                    0 => return Err(invalid(format!("BYDAY={} is out of range", day))),
+                                    n => Some(n),
+                                },
+                            };
+                            Ok((ordinal, parse_weekday(weekday)?))
+                        })
+                        .collect::<Result<_>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = value
+                        .split(',')
+                        .map(|day| parse_number(name, day, -31, 31))
+                        .collect::<Result<Vec<i32>>>()?;
+                    if rule.by_month_day.contains(&0) {
+                        return Err(invalid("BYMONTHDAY=0 is out of range"));
+                    }
+                }
+                "BYMONTH" => {
+                    rule.by_month = value
+                        .split(',')
+                        .map(|month| parse_number(name, month, 1, 12))
+                        .collect::<Result<_>>()?
+                }
+                "WKST" => rule.week_start = parse_weekday(value)?,
+                _ => return Err(invalid(format!("{} is not supported", name))),
+            }
+        }
+
+        rule.freq = freq.ok_or_else(|| invalid("FREQ is required"))?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(invalid("COUNT and UNTIL cannot be combined"));
+        }
+        let has_ordinals = rule.by_day.iter().any(|(ordinal, _)| ordinal.is_some());
+        match rule.freq {
+            Frequency::Daily | Frequency::Weekly if has_ordinals => {
+                return Err(invalid("BYDAY ordinals need FREQ=MONTHLY or FREQ=YEARLY"));
+            }
+            Frequency::Weekly if !rule.by_month_day.is_empty() => {
+                return Err(invalid("BYMONTHDAY cannot be used with FREQ=WEEKLY"));
+            }
+            Frequency::Yearly if has_ordinals && rule.by_month.is_empty() => {
+                return Err(invalid("BYDAY ordinals in yearly rules need BYMONTH"));
+            }
+            _ => {}
+        }
+
+        Ok(rule)
+    }
+}
+
+/// Writes the rule in a canonical form, which is how it is stored.
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: Vec<String>| values.join(",");
+
+        let freq = match self.freq {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_month.is_empty() {
+            write!(f, ";BYMONTH={}", join(self.by_month.iter().map(u32::to_string).collect()))?;
+        }
+        if !self.by_month_day.is_empty() {
+            write!(f, ";BYMONTHDAY={}", join(self.by_month_day.iter().map(i32::to_string).collect()))?;
+        }
+        if !self.by_day.is_empty() {
+            let days = self
+                .by_day
+                .iter()
+                .map(|(ordinal, weekday)| match ordinal {
+                    Some(n) => format!("{}{}", n, weekday_code(*weekday)),
+                    None => weekday_code(*weekday).to_string(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", join(days))?;
+        }
+        if self.week_start != Weekday::Mon {
+            write!(f, ";WKST={}", weekday_code(self.week_start))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        match self.until {
+            Some(Until::Utc(until)) => write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%SZ"))?,
+            Some(Until::Local(until)) => write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%S"))?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    fn first(rule: &str, start: &str, tz: Tz, n: usize) -> Vec<DateTime<Utc>> {
+        let rule: RecurrenceRule = rule.parse().unwrap();
+        rule.occurrences(at(start), tz).take(n).collect()
+    }
+
+    fn times(times: &[&str]) -> Vec<DateTime<Utc>> {
+        times.iter().map(|time| at(time)).collect()
+    }
+
+    #[test]
+    fn malformed_rules_are_rejected_without_panicking() {
+        for rule in [
+            "FREQ=DAILY;BYDAY=€",
+            "FREQ=MONTHLY;BYDAY=1€",
+            "FREQ=MONTHLY;BYDAY=é1MO",
+            "FREQ=DAILY;BYDAY=M",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=MONTHLY;BYDAY=6MO",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=DAILY;COUNT=2;UNTIL=20240101",
+            "FREQ=HOURLY",
+            "INTERVAL=2",
+        ] {
+            assert!(rule.parse::<RecurrenceRule>().is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn rules_are_stored_in_canonical_form() {
+        let rule: RecurrenceRule = "rrule:freq=monthly;byday=+2tu,-1fr;interval=1".parse().unwrap();
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;BYDAY=2TU,-1FR");
+        assert_eq!(rule.to_string().parse::<RecurrenceRule>().unwrap(), rule);
+    }
+
+    #[test]
+    fn times_skipped_by_dst_move_forward_and_repeated_times_use_the_first() {
+        let tz = chrono_tz::America::New_York;
+        // 02:30 does not exist on 2024-03-10
+        assert_eq!(
+            first("FREQ=DAILY", "2024-03-09T07:30:00Z", tz, 3),
+            times(&["2024-03-09T07:30:00Z", "2024-03-10T07:30:00Z", "2024-03-11T06:30:00Z"])
+        );
+        // 01:30 happens twice on 2024-11-03
+        assert_eq!(
+            first("FREQ=DAILY", "2024-11-02T05:30:00Z", tz, 3),
+            times(&["2024-11-02T05:30:00Z", "2024-11-03T05:30:00Z", "2024-11-04T06:30:00Z"])
+        );
+    }
+
+    #[test]
+    fn negative_month_days_count_from_the_end_of_the_month() {
+        assert_eq!(
+            first("FREQ=MONTHLY;BYMONTHDAY=-1", "2024-01-31T09:00:00Z", Tz::UTC, 4),
+            times(&["2024-01-31T09:00:00Z", "2024-02-29T09:00:00Z", "2024-03-31T09:00:00Z", "2024-04-30T09:00:00Z"])
+        );
+        // Months without a 31st are skipped rather than clamped
+        assert_eq!(
+            first("FREQ=MONTHLY", "2024-01-31T09:00:00Z", Tz::UTC, 3),
+            times(&["2024-01-31T09:00:00Z", "2024-03-31T09:00:00Z", "2024-05-31T09:00:00Z"])
+        );
+    }
+
+    #[test]
+    fn ordinal_weekdays_pick_the_nth_weekday_of_the_month() {
+        assert_eq!(
+            first("FREQ=MONTHLY;BYDAY=2TU", "2024-01-09T09:00:00Z", Tz::UTC, 3),
+            times(&["2024-01-09T09:00:00Z", "2024-02-13T09:00:00Z", "2024-03-12T09:00:00Z"])
+        );
+        assert_eq!(
+            first("FREQ=MONTHLY;BYDAY=-1FR", "2024-01-26T09:00:00Z", Tz::UTC, 3),
+            times(&["2024-01-26T09:00:00Z", "2024-02-23T09:00:00Z", "2024-03-29T09:00:00Z"])
+        );
+        assert_eq!(
+            first("FREQ=YEARLY;BYMONTH=11;BYDAY=4TH", "2024-11-28T17:00:00Z", Tz::UTC, 2),
+            times(&["2024-11-28T17:00:00Z", "2025-11-27T17:00:00Z"])
+        );
+    }
+
+    #[test]
+    fn count_and_until_end_the_series() {
+        assert_eq!(
+            first("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4", "2024-01-01T09:00:00Z", Tz::UTC, 10),
+            times(&["2024-01-01T09:00:00Z", "2024-01-03T09:00:00Z", "2024-01-08T09:00:00Z", "2024-01-10T09:00:00Z"])
+        );
+        // A date-only UNTIL includes that whole day
+        assert_eq!(first("FREQ=DAILY;UNTIL=20240103", "2024-01-01T09:00:00Z", Tz::UTC, 10).len(), 3);
+        assert_eq!(first("FREQ=DAILY;UNTIL=20240102T090000Z", "2024-01-01T09:00:00Z", Tz::UTC, 10).len(), 2);
+
+        let rule: RecurrenceRule = "FREQ=DAILY;COUNT=2".parse().unwrap();
+        assert_eq!(rule.next_after(at("2024-01-01T09:00:00Z"), Tz::UTC, at("2024-01-02T09:00:00Z")), None);
+    }
+
+    #[test]
+    fn rules_that_never_match_run_out_instead_of_looping() {
+        assert_eq!(
+            first("FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30", "2024-01-30T09:00:00Z", Tz::UTC, 3),
+            times(&["2024-01-30T09:00:00Z"])
+        );
+        assert_eq!(
+            first("FREQ=YEARLY", "2024-02-29T09:00:00Z", Tz::UTC, 2),
+            times(&["2024-02-29T09:00:00Z", "2028-02-29T09:00:00Z"])
+        );
+    }
+}
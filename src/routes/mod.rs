@@ -1,57 +1,196 @@
 use axum::{
-    routing::{delete, get, patch, post},
+    middleware,
+    routing::{delete, get, patch, post, put},
     Router,
 };
+use std::sync::Arc;
 
-use crate::{db::DbPool, handlers, kafka::EventProducer};
+use crate::{
+    auth::{
+        api_key,
+        oidc::OidcClient,
+        password::{Argon2Hasher, AsyncPasswordHasher},
+        JwtKeys,
+    },
+    config::Config,
+    db::DbPool,
+    error::{AppError, Result},
+    handlers,
+    kafka::EventProducer,
+    mail::{self, Mailer},
+    middleware::auth::{auth_middleware, require_role, require_scope, require_session},
+    models::Role,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: DbPool,
     pub kafka_producer: EventProducer,
+    pub config: Arc<Config>,
+    pub jwt: Arc<JwtKeys>,
+    pub mailer: Arc<dyn Mailer>,
+    pub password_hasher: AsyncPasswordHasher,
+    pub oidc: Arc<OidcClient>,
 }
 
-pub fn create_routes(pool: DbPool, kafka_producer: EventProducer) -> Router {
-    let state = AppState {
-        db_pool: pool,
-        kafka_producer,
-    };
-    Router::new()
-        // Todo routes
-        .route("/api/todos", post(handlers::create_todo))
+impl AppState {
+    /// Fails when the configured JWT key material, mail backend, password
+    /// hash parameters or OIDC client cannot be loaded.
+    pub fn new(db_pool: DbPool, kafka_producer: EventProducer, config: Config) -> Result<Self> {
+        let jwt = JwtKeys::from_config(&config.jwt)?;
+        let mailer = mail::from_config(&config.mail)
+            .map_err(|e| AppError::Internal(format!("Failed to configure mailer: {}", e)))?;
+        let password_hasher = Argon2Hasher::new(&config.password_hash)?;
+        let oidc = OidcClient::new(&config.oidc, &config.public_url)?;
+
+        Ok(Self {
+            db_pool,
+            kafka_producer,
+            config: Arc::new(config),
+            jwt: Arc::new(jwt),
+            mailer,
+            password_hasher: AsyncPasswordHasher::new(password_hasher),
+            oidc: Arc::new(oidc),
+        })
+    }
+}
+
+/// Lets API keys reach `router` only if they carry `scope`.
+fn scoped(router: Router<AppState>, scope: &'static str) -> Router<AppState> {
+    router.route_layer(middleware::from_fn_with_state(scope, require_scope))
+}
+
+pub fn create_routes(state: AppState) -> Router {
+    // Todo routes
+    let todos_read = Router::new()
         .route("/api/todos", get(handlers::get_todos))
         .route("/api/todos/{id}", get(handlers::get_todo))
+        .route("/api/todos/{id}/subtasks", get(handlers::subtasks::list_subtasks))
+        .route("/api/todos/{id}/reminders", get(handlers::reminders::list_reminders))
+        .route("/api/todos/{id}/history", get(handlers::history::get_history));
+    let todos_write = Router::new()
+        .route("/api/todos", post(handlers::create_todo))
         .route("/api/todos/{id}", patch(handlers::update_todo))
         .route("/api/todos/{id}", delete(handlers::delete_todo))
+        .route("/api/todos/{id}/move", post(handlers::subtasks::move_todo))
+        .route("/api/todos/{id}/skip", post(handlers::recurrence::skip_occurrence))
+        .route("/api/todos/{id}/series", patch(handlers::recurrence::update_series))
+        .route("/api/todos/{id}/series/stop", post(handlers::recurrence::stop_series))
+        .route("/api/todos/{id}/reminders", post(handlers::reminders::create_reminder))
+        .route("/api/todos/{id}/reminders/{reminder_id}", delete(handlers::reminders::delete_reminder))
+        .route("/api/todos/{id}/dependencies/{blocker_id}", put(handlers::dependencies::add_dependency))
+        .route("/api/todos/{id}/dependencies/{blocker_id}", delete(handlers::dependencies::remove_dependency))
+        .route("/api/todos/{id}/revert/{revision}", post(handlers::history::revert_todo))
         // Batch operations
         .route("/api/todos/batch", patch(handlers::batch::batch_update_todos))
         .route("/api/todos/batch", delete(handlers::batch::batch_delete_todos))
+        .route("/api/todos/{todo_id}/tags/{tag_id}", put(handlers::tags::assign_tag_to_todo))
+        .route("/api/todos/{todo_id}/tags/{tag_id}", delete(handlers::tags::remove_tag_from_todo))
+        .route("/api/trash/todos/{id}/restore", post(handlers::trash::restore_todo))
+        .route("/api/trash/todos/{id}", delete(handlers::trash::purge_todo));
 
-        // User routes
-        .route("/api/users/register", post(handlers::users::register_user))
-        .route("/api/users/login", post(handlers::users::login_user))
-        .route("/api/users/{id}", get(handlers::users::get_user_profile))
-        .route("/api/users/{id}", patch(handlers::users::update_user_profile))
-        .route("/api/users/{id}", delete(handlers::users::delete_user))
-
-        // Category routes
-        .route("/api/categories", post(handlers::categories::create_category))
+    // Category routes
+    let categories_read = Router::new()
         .route("/api/categories", get(handlers::categories::get_categories))
-        .route("/api/categories/{id}", get(handlers::categories::get_category))
+        .route("/api/categories/{id}", get(handlers::categories::get_category));
+    let categories_write = Router::new()
+        .route("/api/categories", post(handlers::categories::create_category))
         .route("/api/categories/{id}", patch(handlers::categories::update_category))
         .route("/api/categories/{id}", delete(handlers::categories::delete_category))
+        .route("/api/trash/categories/{id}/restore", post(handlers::trash::restore_category))
+        .route("/api/trash/categories/{id}", delete(handlers::trash::purge_category));
 
-        // Tag routes
-        .route("/api/tags", post(handlers::tags::create_tag))
+    // Tag routes
+    let tags_read = Router::new()
         .route("/api/tags", get(handlers::tags::get_tags))
-        .route("/api/tags/{id}", get(handlers::tags::get_tag))
+        .route("/api/tags/{id}", get(handlers::tags::get_tag));
+    let tags_write = Router::new()
+        .route("/api/tags", post(handlers::tags::create_tag))
         .route("/api/tags/{id}", delete(handlers::tags::delete_tag))
-        .route("/api/todos/{todo_id}/tags/{tag_id}", axum::routing::put(handlers::tags::assign_tag_to_todo))
-        .route("/api/todos/{todo_id}/tags/{tag_id}", delete(handlers::tags::remove_tag_from_todo))
+        .route("/api/trash/tags/{id}/restore", post(handlers::trash::restore_tag))
+        .route("/api/trash/tags/{id}", delete(handlers::trash::purge_tag));
+
+    // Statistics routes
+    let stats_read = Router::new()
+        .route("/api/stats/todos", get(handlers::stats::get_todo_statistics));
+
+    // Account and credential management is only available to session logins
+    let account = Router::new()
+        // Session routes
+        .route("/api/auth/sessions", get(handlers::sessions::list_sessions))
+        .route("/api/auth/sessions", delete(handlers::sessions::revoke_other_sessions))
+        .route("/api/auth/sessions/{id}", delete(handlers::sessions::revoke_session))
+        .route("/api/auth/verify-email/resend", post(handlers::auth::resend_verification_email))
+        .route("/api/auth/2fa/enroll", post(handlers::two_factor::enroll))
+        .route("/api/auth/2fa/confirm", post(handlers::two_factor::confirm))
+        .route("/api/auth/2fa/disable", post(handlers::two_factor::disable))
+        .route("/api/auth/2fa/recovery-codes", post(handlers::two_factor::regenerate_recovery_codes))
+        .route("/api/auth/api-keys", post(handlers::api_keys::create_api_key))
+        .route("/api/auth/api-keys", get(handlers::api_keys::list_api_keys))
+        .route("/api/auth/api-keys/{id}", delete(handlers::api_keys::revoke_api_key))
+
+        // User routes
+        .route("/api/users/me/password", post(handlers::users::change_password))
+        .route("/api/users/me/export", get(handlers::export::export_account))
+        // The trash spans todos, categories and tags, which API keys are scoped to separately
+        .route("/api/trash", get(handlers::trash::get_trash))
+        .route("/api/trash", delete(handlers::trash::empty_trash))
+        .route("/api/users/{id}", get(handlers::users::get_user_profile))
+        .route("/api/users/{id}", patch(handlers::users::update_user_profile))
+        .route("/api/users/{id}", delete(handlers::users::delete_user))
+        .route_layer(middleware::from_fn(require_session));
+
+    // Admin routes: support staff can inspect accounts and sign users out,
+    // only admins can change them
+    let support = Router::new()
+        .route("/api/admin/users", get(handlers::admin::list_users))
+        .route("/api/admin/users/{id}", get(handlers::admin::get_user))
+        .route("/api/admin/users/{id}/unlock", post(handlers::admin::unlock_user))
+        .route("/api/admin/users/{id}/sessions", delete(handlers::admin::revoke_user_sessions))
+        .route("/api/admin/stats", get(handlers::admin::system_stats))
+        .route_layer(middleware::from_fn_with_state(Role::Support, require_role));
+    let admin = Router::new()
+        .route("/api/admin/users/{id}/deactivate", post(handlers::admin::deactivate_user))
+        .route("/api/admin/users/{id}/reactivate", post(handlers::admin::reactivate_user))
+        .route("/api/admin/users/{id}/role", put(handlers::admin::update_user_role))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+    let administration = Router::new()
+        .merge(support)
+        .merge(admin)
+        .route_layer(middleware::from_fn(require_session));
+
+    // Everything under /api except registration, login (including SSO), token renewal and password reset
+    // requires a valid token or API key
+    let protected = Router::new()
+        .merge(scoped(todos_read, api_key::TODOS_READ))
+        .merge(scoped(todos_write, api_key::TODOS_WRITE))
+        .merge(scoped(categories_read, api_key::CATEGORIES_READ))
+        .merge(scoped(categories_write, api_key::CATEGORIES_WRITE))
+        .merge(scoped(tags_read, api_key::TAGS_READ))
+        .merge(scoped(tags_write, api_key::TAGS_WRITE))
+        .merge(scoped(stats_read, api_key::STATS_READ))
+        .merge(account)
+        .merge(administration)
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+
+    Router::new()
+        // Public user routes
+        .route("/api/users/register", post(handlers::users::register_user))
+        .route("/api/users/login", post(handlers::users::login_user))
+        .route("/api/auth/refresh", post(handlers::auth::refresh_token))
+        .route("/api/auth/logout", post(handlers::auth::logout))
+        .route("/api/auth/password/forgot", post(handlers::auth::forgot_password))
+        .route("/api/auth/password/reset", post(handlers::auth::reset_password))
+        .route("/api/auth/verify-email", get(handlers::auth::verify_email))
+        .route("/api/auth/2fa/verify", post(handlers::two_factor::verify_login))
+        .route("/api/auth/oidc/providers", get(handlers::oidc::list_providers))
+        .route("/api/auth/oidc/{provider}/login", get(handlers::oidc::login))
+        .route("/api/auth/oidc/{provider}/callback", get(handlers::oidc::callback))
+        .merge(protected)
+
+        // Public signing keys
+        .route("/.well-known/jwks.json", get(handlers::auth::jwks))
 
-        // Statistics routes
-        .route("/api/stats/todos", get(handlers::stats::get_todo_statistics))
-        
         // Health check
         .route("/health", get(health_check))
         .with_state(state)
@@ -0,0 +1,157 @@
+mod common;
+
+use axum::{
+    body::{self, Body},
+    http::{header, Method, Request, StatusCode},
+};
+use axum_server::{config::Config, db, jobs::account_purge, kafka::EventProducer, models::Role};
+use common::{login, register, send, test_app, test_app_with, user_with_role};
+use serde_json::{json, Value};
+use std::io::{Cursor, Read};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn export_contains_the_users_data_without_credentials() {
+    let Some(app) = test_app().await else { return };
+    let (username, password) = register(&app).await;
+    let token = login(&app, &username, &password).await["token"].as_str().unwrap().to_string();
+
+    let (status, category) = send(
+        &app,
+        Method::POST,
+        "/api/categories",
+        Some(&token),
+        Some(json!({ "name": "Errands" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/api/todos",
+        Some(&token),
+        Some(json!({ "title": "Export me", "category_id": category["id"], "tags": ["home"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let request = Request::builder()
+        .uri("/api/users/me/export")
+        .header(header::AUTHORIZATION, format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zip");
+    assert!(response.headers()[header::CONTENT_DISPOSITION].to_str().unwrap().starts_with("attachment"));
+
+    let bytes = body::to_bytes(response.into_body(), 10 * 1024 * 1024).await.unwrap();
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes.to_vec())).unwrap();
+    let mut read_json = |name: &str| -> (String, Value) {
+        let mut contents = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut contents).unwrap();
+        let value = serde_json::from_str(&contents).unwrap();
+        (contents, value)
+    };
+
+    let (profile_raw, profile) = read_json("profile.json");
+    assert_eq!(profile["username"], username.as_str());
+    assert!(!profile_raw.contains("password_hash"));
+
+    let (_, todos) = read_json("todos.json");
+    assert_eq!(todos[0]["title"], "Export me");
+    let (_, categories) = read_json("categories.json");
+    assert_eq!(categories[0]["name"], "Errands");
+    let (_, tags) = read_json("tags.json");
+    assert_eq!(tags[0]["name"], "home");
+    let (_, assignments) = read_json("tag_assignments.json");
+    assert_eq!(assignments[0]["todo_id"], todos[0]["id"]);
+    let (_, sessions) = read_json("sessions.json");
+    assert_eq!(sessions.as_array().unwrap().len(), 1);
+    read_json("security_log.json");
+}
+
+#[tokio::test]
+async fn deleted_accounts_are_disabled_then_purged_after_the_grace_period() {
+    let Some(app) = test_app_with(|cfg| cfg.account_deletion_grace_secs = 0).await else { return };
+    let config = Config::from_env().expect("load config");
+    let pool = db::create_pool(&config.database_url).await.expect("connect to DB");
+    let mut kafka = config.kafka.clone();
+    kafka.enabled = false;
+    let producer = EventProducer::new(kafka).await.expect("disabled producer");
+
+    // Reactivating the account withdraws the request
+    let (_, admin_token) = user_with_role(&app, Role::Admin).await;
+    let (username, password) = register(&app).await;
+    let body = login(&app, &username, &password).await;
+    let (user_id, token) = (body["user"]["id"].as_str().unwrap(), body["token"].as_str().unwrap());
+
+    let (status, deletion) = send(&app, Method::DELETE, &format!("/api/users/{user_id}"), Some(token), None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(deletion["purge_after"].is_string());
+
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/api/users/login",
+        None,
+        Some(json!({ "username": username, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["error"], "Account is disabled");
+    // Without the password the disabled state stays hidden
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/api/users/login",
+        None,
+        Some(json!({ "username": username, "password": "wrong-password" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["error"], "Invalid credentials");
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        &format!("/api/admin/users/{user_id}/reactivate"),
+        Some(&admin_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    account_purge::run_once(&pool, &producer).await.unwrap();
+    let token = login(&app, &username, &password).await["token"].as_str().unwrap().to_string();
+
+    // Without a reactivation the data is purged and the audit trail anonymized
+    let (status, _) = send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "Gone soon" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/users/{user_id}"), Some(&token), None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    assert!(account_purge::run_once(&pool, &producer).await.unwrap() >= 1);
+
+    let user_id: Uuid = user_id.parse().unwrap();
+    let remaining: i64 = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM users WHERE id = $1) + (SELECT COUNT(*) FROM todos WHERE user_id = $1)"
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(remaining, 0);
+
+    let audit_mentions: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM security_audit_log WHERE user_id = $1 OR username = $2"
+    )
+    .bind(user_id)
+    .bind(&username)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(audit_mentions, 0);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use axum_server::models::Role;
+use common::{login, register, send, test_app, user_with_role};
+use serde_json::json;
+
+#[tokio::test]
+async fn support_can_inspect_but_only_admins_can_change_accounts() {
+    let Some(app) = test_app().await else { return };
+    let (_, support_token) = user_with_role(&app, Role::Support).await;
+    let (admin_id, admin_token) = user_with_role(&app, Role::Admin).await;
+    let (username, password) = register(&app).await;
+    let user = login(&app, &username, &password).await;
+    let user_id = user["user"]["id"].as_str().unwrap();
+    let user_token = user["token"].as_str().unwrap();
+    assert_eq!(user["user"]["role"], "user");
+
+    // Plain users have no access
+    let (status, _) = send(&app, Method::GET, "/api/admin/users", Some(user_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, Method::GET, "/api/admin/stats", Some(user_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, found) = send(&app, Method::GET, &format!("/api/admin/users?search={username}"), Some(&support_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["total"], 1);
+    assert_eq!(found["users"][0]["id"], user_id);
+
+    let (status, admins) = send(&app, Method::GET, "/api/admin/users?role=admin&per_page=100", Some(&support_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(admins["users"].as_array().unwrap().iter().all(|u| u["role"] == "admin"));
+
+    let (status, stats) = send(&app, Method::GET, "/api/admin/stats", Some(&support_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(stats["total_users"].as_i64().unwrap() >= 3);
+
+    let deactivate = format!("/api/admin/users/{user_id}/deactivate");
+    let (status, _) = send(&app, Method::POST, &deactivate, Some(&support_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Deactivation signs the user out and blocks logins until reactivated
+    let (status, deactivated) = send(&app, Method::POST, &deactivate, Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deactivated["is_active"], false);
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(user_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let credentials = json!({ "username": username, "password": password });
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(credentials.clone())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, &format!("/api/admin/users/{user_id}/reactivate"), Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(credentials)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Admins cannot lock themselves out
+    let (status, _) = send(&app, Method::POST, &format!("/api/admin/users/{admin_id}/deactivate"), Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn role_changes_apply_to_new_tokens_and_users_cannot_change_their_own_status() {
+    let Some(app) = test_app().await else { return };
+    let (_, admin_token) = user_with_role(&app, Role::Admin).await;
+    let (username, password) = register(&app).await;
+    let user = login(&app, &username, &password).await;
+    let user_id = user["user"]["id"].as_str().unwrap();
+    let user_token = user["token"].as_str().unwrap();
+
+    // is_active is no longer part of the profile
+    let (status, profile) = send(&app, Method::PATCH, &format!("/api/users/{user_id}"), Some(user_token), Some(json!({ "is_active": false }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(profile["is_active"], true);
+
+    let (status, updated) = send(&app, Method::PUT, &format!("/api/admin/users/{user_id}/role"), Some(&admin_token), Some(json!({ "role": "support" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["role"], "support");
+
+    let (status, _) = send(&app, Method::PUT, &format!("/api/admin/users/{user_id}/role"), Some(&admin_token), Some(json!({ "role": "owner" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Force logout ends every session of the user
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/admin/users/{user_id}/sessions"), Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(user_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let support = login(&app, &username, &password).await;
+    let (status, _) = send(&app, Method::GET, "/api/admin/users", support["token"].as_str(), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
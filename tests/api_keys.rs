@@ -0,0 +1,68 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{register_and_login, send, test_app};
+use serde_json::json;
+
+#[tokio::test]
+async fn api_keys_are_limited_to_their_scopes() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/api-keys", Some(&token), Some(json!({ "name": "ci", "scopes": ["todos:admin"] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, created) = send(&app, Method::POST, "/api/auth/api-keys", Some(&token), Some(json!({ "name": "ci", "scopes": ["todos:read", "stats:read"] }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let key = created["key"].as_str().unwrap();
+    assert!(key.starts_with(created["key_prefix"].as_str().unwrap()));
+
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(key), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, Method::GET, "/api/stats/todos", Some(key), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&app, Method::POST, "/api/todos", Some(key), Some(json!({ "title": "from ci" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, Method::GET, "/api/categories", Some(key), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Keys cannot manage credentials
+    let (status, _) = send(&app, Method::GET, "/api/auth/sessions", Some(key), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, Method::POST, "/api/auth/api-keys", Some(key), Some(json!({ "name": "escalate", "scopes": ["todos:write"] }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, keys) = send(&app, Method::GET, "/api/auth/api-keys", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let keys = keys.as_array().unwrap();
+    assert_eq!(keys.len(), 1);
+    assert!(keys[0]["last_used_at"].is_string());
+    assert!(keys[0].get("key").is_none());
+
+    let uri = format!("/api/auth/api-keys/{}", created["id"].as_str().unwrap());
+    let (status, _) = send(&app, Method::DELETE, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(key), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, Method::DELETE, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn expired_api_keys_are_rejected() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/api-keys", Some(&token), Some(json!({ "name": "old", "scopes": ["todos:read"], "expires_at": "2000-01-01T00:00:00Z" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(1);
+    let (status, created) = send(&app, Method::POST, "/api/auth/api-keys", Some(&token), Some(json!({ "name": "short", "scopes": ["todos:read"], "expires_at": expires_at }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let key = created["key"].as_str().unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(key), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
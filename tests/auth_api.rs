@@ -0,0 +1,37 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{register_and_login, send, test_app};
+use serde_json::json;
+
+#[tokio::test]
+async fn api_routes_require_bearer_token() {
+    let Some(app) = test_app().await else { return };
+
+    let (status, _) = send(&app, Method::GET, "/api/todos", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::GET, "/api/categories", Some("not-a-jwt"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn created_todos_and_tags_belong_to_the_caller() {
+    let Some(app) = test_app().await else { return };
+    let (user_id, token) = register_and_login(&app).await;
+
+    let (status, todo) = send(
+        &app,
+        Method::POST,
+        "/api/todos",
+        Some(&token),
+        Some(json!({ "title": "Write tests", "tags": ["auth"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(todo["user_id"], json!(user_id));
+
+    let (status, tags) = send(&app, Method::GET, "/api/tags", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tags[0]["name"], "auth");
+}
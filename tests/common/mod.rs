@@ -0,0 +1,269 @@
+#![allow(dead_code)]
+
+use axum::{
+    body::{self, Body},
+    http::{header, HeaderMap, HeaderName, Method, Request, StatusCode},
+    Router,
+};
+use axum_server::{
+    auth::{email_verification, JwtKeys},
+    config::Config,
+    db,
+    kafka::EventProducer,
+    models::Role,
+    routes,
+};
+use serde_json::Value;
+use std::{path::Path, time::Duration};
+use tower::ServiceExt; // for oneshot
+use uuid::Uuid;
+
+/// Builds the full application against `DATABASE_URL`, or returns `None` when
+/// the database is unreachable so callers can skip.
+pub async fn test_app() -> Option<Router> {
+    test_app_with(|_| {}).await
+}
+
+/// Like [`test_app`], but lets the caller adjust the configuration first.
+pub async fn test_app_with(configure: impl FnOnce(&mut Config)) -> Option<Router> {
+    dotenvy::dotenv().ok();
+
+    let mut cfg = Config::from_env().expect("load config");
+    // Tests retry logins right after failures; lockout tests opt back in
+    cfg.lockout.base_delay_secs = 0;
+    configure(&mut cfg);
+
+    let pool = match db::create_pool(&cfg.database_url).await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("skipping integration test: cannot connect to DB: {e}");
+            return None;
+        }
+    };
+    db::run_migrations(&pool).await.expect("run migrations");
+
+    let mut kafka = cfg.kafka.clone();
+    kafka.enabled = false;
+    let producer = EventProducer::new(kafka).await.expect("disabled producer");
+
+    let state = routes::AppState::new(pool, producer, cfg).expect("app state");
+    Some(routes::create_routes(state))
+}
+
+pub async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let (status, _, json) = send_with_headers(app, method, uri, token, body).await;
+    (status, json)
+}
+
+/// Like [`send`], but also returns the response headers.
+pub async fn send_with_headers(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    send_with_request_headers(app, method, uri, token, &[], body).await
+}
+
+/// Like [`send_with_headers`], but also sends `headers` with the request.
+pub async fn send_with_request_headers(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    headers: &[(HeaderName, &str)],
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut builder = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        builder = builder.header(name, *value);
+    }
+    if let Some(token) = token {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let request = match body {
+        Some(json) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, headers, json)
+}
+
+/// Waits for the file mailer to write a message to `to` with `subject`,
+/// removes it and returns its contents.
+pub async fn take_mail(dir: &Path, to: &str, subject: &str) -> String {
+    let subject_header = format!("Subject: {subject}");
+    for _ in 0..50 {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let for_recipient = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(to));
+                if !for_recipient {
+                    continue;
+                }
+                if let Ok(message) = std::fs::read_to_string(&path)
+                    && message.contains(&subject_header)
+                {
+                    std::fs::remove_file(&path).unwrap();
+                    return decode_quoted_printable(&message);
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("no '{subject}' email for {to}");
+}
+
+/// Undoes quoted-printable soft line breaks and `=XX` escapes, which the
+/// mailer uses for messages with long lines such as links.
+fn decode_quoted_printable(message: &str) -> String {
+    let joined = message.replace("=\r\n", "").replace("=\n", "");
+    let mut bytes = Vec::with_capacity(joined.len());
+    let mut input = joined.bytes();
+    while let Some(b) = input.next() {
+        if b == b'=' {
+            let hex: Vec<u8> = input.by_ref().take(2).collect();
+            let decoded = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match decoded {
+                Some(byte) => bytes.push(byte),
+                None => {
+                    bytes.push(b);
+                    bytes.extend(hex);
+                }
+            }
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Returns the `token` query parameter of the first link in `message`.
+pub fn link_token(message: &str) -> String {
+    let start = message.find("token=").expect("link with token") + "token=".len();
+    message[start..]
+        .split(|c: char| c.is_whitespace())
+        .next()
+        .unwrap()
+        .to_string()
+}
+
+/// A username that has not been registered yet.
+pub fn unique_username() -> String {
+    format!("user_{}", &Uuid::new_v4().simple().to_string()[..12])
+}
+
+/// Registers a fresh user, returning `(username, password)`.
+pub async fn register(app: &Router) -> (String, String) {
+    register_as(app, &unique_username()).await
+}
+
+/// Registers `username` with a fixed password, returning `(username, password)`.
+pub async fn register_as(app: &Router, username: &str) -> (String, String) {
+    let username = username.to_string();
+    let password = "password123".to_string();
+
+    let (status, _) = send(
+        app,
+        Method::POST,
+        "/api/users/register",
+        None,
+        Some(serde_json::json!({
+            "username": username,
+            "email": format!("{username}@example.com"),
+            "password": password,
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    (username, password)
+}
+
+/// Logs in and returns the full `AuthResponse` body.
+pub async fn login(app: &Router, username: &str, password: &str) -> Value {
+    let (status, body) = send(
+        app,
+        Method::POST,
+        "/api/users/login",
+        None,
+        Some(serde_json::json!({ "username": username, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    body
+}
+
+/// Registers a fresh user and logs in, returning `(user_id, token)`.
+pub async fn register_and_login(app: &Router) -> (String, String) {
+    let (username, password) = register(app).await;
+    let body = login(app, &username, &password).await;
+
+    (
+        body["user"]["id"].as_str().unwrap().to_string(),
+        body["token"].as_str().unwrap().to_string(),
+    )
+}
+
+/// Registers a fresh user, verifies their email through a link signed with
+/// the configured keys and logs in, returning `(user_id, token)`.
+pub async fn verified_user(app: &Router) -> (String, String) {
+    let (username, password) = register(app).await;
+    let body = login(app, &username, &password).await;
+    let user_id = body["user"]["id"].as_str().unwrap().to_string();
+
+    let config = Config::from_env().expect("load config");
+    let keys = JwtKeys::from_config(&config.jwt).expect("jwt keys");
+    let link = email_verification::issue(
+        &keys,
+        user_id.parse().unwrap(),
+        &format!("{username}@example.com"),
+        300,
+    )
+    .unwrap();
+    let (status, _) = send(app, Method::GET, &format!("/api/auth/verify-email?token={link}"), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    (user_id, body["token"].as_str().unwrap().to_string())
+}
+
+/// Registers a fresh user with `role` granted directly in the database and
+/// logs in, returning `(user_id, token)`.
+pub async fn user_with_role(app: &Router, role: Role) -> (String, String) {
+    let (username, password) = register(app).await;
+
+    let config = Config::from_env().expect("load config");
+    let pool = db::create_pool(&config.database_url).await.expect("connect to DB");
+    sqlx::query("UPDATE users SET role = $1 WHERE username = $2")
+        .bind(role)
+        .bind(&username)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let body = login(app, &username, &password).await;
+    (
+        body["user"]["id"].as_str().unwrap().to_string(),
+        body["token"].as_str().unwrap().to_string(),
+    )
+}
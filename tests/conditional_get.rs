@@ -0,0 +1,131 @@
+mod common;
+
+use axum::{
+    http::{header, HeaderMap, HeaderName, Method, StatusCode},
+    Router,
+};
+use chrono::{Duration, Utc};
+use common::{register_and_login, send, send_with_headers, send_with_request_headers, test_app};
+use serde_json::{json, Value};
+
+async fn get(app: &Router, token: &str, uri: &str) -> HeaderMap {
+    let (status, headers, _) = send_with_headers(app, Method::GET, uri, Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    headers
+}
+
+async fn revalidate(app: &Router, token: &str, uri: &str, name: HeaderName, value: &str) -> StatusCode {
+    send_with_request_headers(app, Method::GET, uri, Some(token), &[(name, value)], None).await.0
+}
+
+/// Revalidates with both of `cached`'s validators.
+async fn revalidate_both(app: &Router, token: &str, uri: &str, cached: &HeaderMap) -> StatusCode {
+    let since = cached[header::LAST_MODIFIED].to_str().unwrap();
+    let headers = [(header::IF_NONE_MATCH, etag(cached)), (header::IF_MODIFIED_SINCE, since)];
+    send_with_request_headers(app, Method::GET, uri, Some(token), &headers, None).await.0
+}
+
+fn etag(headers: &HeaderMap) -> &str {
+    headers[header::ETAG].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn lists_and_stats_answer_not_modified_until_something_changes() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let (_, first) = send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "First" }))).await;
+
+    let list = get(&app, &token, "/api/todos").await;
+    assert!(etag(&list).starts_with("W/"));
+    assert_eq!(list[header::CACHE_CONTROL], "private, no-cache");
+    assert!(list[header::VARY].to_str().unwrap().contains("Authorization"));
+    let if_none_match = [(header::IF_NONE_MATCH, etag(&list))];
+    let (status, headers, body) = send_with_request_headers(&app, Method::GET, "/api/todos", Some(&token), &if_none_match, None).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(body, Value::Null);
+    assert_eq!(etag(&headers), etag(&list));
+    let since = list[header::LAST_MODIFIED].to_str().unwrap();
+    assert_eq!(revalidate(&app, &token, "/api/todos", header::IF_MODIFIED_SINCE, since).await, StatusCode::NOT_MODIFIED);
+
+    // A copy of one page, filter or sort never validates another
+    for uri in ["/api/todos?page=2", "/api/todos?completed=false", "/api/todos?sort=title", "/api/todos?with_total=true"] {
+        assert_eq!(revalidate(&app, &token, uri, header::IF_NONE_MATCH, etag(&list)).await, StatusCode::OK, "{uri}");
+    }
+    assert_eq!(revalidate(&app, &token, "/api/todos?page=1&sort=-created_at", header::IF_NONE_MATCH, etag(&list)).await, StatusCode::NOT_MODIFIED);
+
+    // Every endpoint shares the same marker, so any change invalidates them all
+    let cached: Vec<(&str, HeaderMap)> = vec![
+        ("/api/todos", list.clone()),
+        ("/api/categories", get(&app, &token, "/api/categories").await),
+        ("/api/tags", get(&app, &token, "/api/tags").await),
+        ("/api/stats/todos", get(&app, &token, "/api/stats/todos").await),
+    ];
+    for (uri, headers) in &cached {
+        assert_eq!(revalidate(&app, &token, uri, header::IF_NONE_MATCH, etag(headers)).await, StatusCode::NOT_MODIFIED);
+    }
+    let (_, second) = send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "Second" }))).await;
+    let dependency = format!("/api/todos/{}/dependencies/{}", second["id"].as_str().unwrap(), first["id"].as_str().unwrap());
+    for (uri, headers) in &cached {
+        assert_eq!(revalidate(&app, &token, uri, header::IF_NONE_MATCH, etag(headers)).await, StatusCode::OK);
+    }
+
+    // Links between todos count as changes too
+    let before_link = get(&app, &token, "/api/todos").await;
+    send(&app, Method::PUT, &dependency, Some(&token), None).await;
+    assert_eq!(revalidate(&app, &token, "/api/todos", header::IF_NONE_MATCH, etag(&before_link)).await, StatusCode::OK);
+
+    // Another user's copy never validates
+    let (_, other) = register_and_login(&app).await;
+    assert_eq!(revalidate(&app, &other, "/api/todos", header::IF_NONE_MATCH, etag(&list)).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn single_items_and_overdue_todos_are_revalidated() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let (_, category) = send(&app, Method::POST, "/api/categories", Some(&token), Some(json!({ "name": "Home" }))).await;
+    let (_, tag) = send(&app, Method::POST, "/api/tags", Some(&token), Some(json!({ "name": "chores" }))).await;
+    let (_, todo) = send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "Sweep" }))).await;
+
+    let todo_uri = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    let category_uri = format!("/api/categories/{}", category["id"].as_str().unwrap());
+    let tag_uri = format!("/api/tags/{}", tag["id"].as_str().unwrap());
+    for uri in [&todo_uri, &category_uri, &tag_uri] {
+        let headers = get(&app, &token, uri).await;
+        assert_eq!(revalidate(&app, &token, uri, header::IF_NONE_MATCH, etag(&headers)).await, StatusCode::NOT_MODIFIED);
+    }
+
+    // The todo's strong ETag also matches weakly
+    let cached = get(&app, &token, &todo_uri).await;
+    let weak = format!("W/{}", etag(&cached));
+    assert_eq!(revalidate(&app, &token, &todo_uri, header::IF_NONE_MATCH, &weak).await, StatusCode::NOT_MODIFIED);
+    send(&app, Method::PATCH, &todo_uri, Some(&token), Some(json!({ "title": "Mop" }))).await;
+    assert_eq!(revalidate(&app, &token, &todo_uri, header::IF_NONE_MATCH, etag(&cached)).await, StatusCode::OK);
+
+    // Dependencies and subtasks shown in the todo leave its version, and so
+    // its ETag, alone; Last-Modified sent along catches them
+    let (_, blocker) = send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "Fetch mop" }))).await;
+    let cached = get(&app, &token, &todo_uri).await;
+    assert_eq!(etag(&cached), format!("\"{}\"", todo["version"].as_i64().unwrap() + 1));
+    assert_eq!(revalidate_both(&app, &token, &todo_uri, &cached).await, StatusCode::NOT_MODIFIED);
+    // Last-Modified has whole seconds
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let dependency = format!("{todo_uri}/dependencies/{}", blocker["id"].as_str().unwrap());
+    send(&app, Method::PUT, &dependency, Some(&token), None).await;
+    assert_eq!(revalidate_both(&app, &token, &todo_uri, &cached).await, StatusCode::OK);
+    let cached = get(&app, &token, &todo_uri).await;
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let subtask = json!({ "title": "Rinse", "parent_id": todo["id"] });
+    send(&app, Method::POST, "/api/todos", Some(&token), Some(subtask)).await;
+    assert_eq!(revalidate_both(&app, &token, &todo_uri, &cached).await, StatusCode::OK);
+    let cached = get(&app, &token, &category_uri).await;
+    send(&app, Method::PATCH, &category_uri, Some(&token), Some(json!({ "name": "House" }))).await;
+    assert_eq!(revalidate(&app, &token, &category_uri, header::IF_NONE_MATCH, etag(&cached)).await, StatusCode::OK);
+
+    // A todo becoming overdue changes `?overdue=true` without any write
+    let due = Utc::now() + Duration::seconds(2);
+    send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "Soon", "due_date": due }))).await;
+    let overdue = get(&app, &token, "/api/todos?overdue=true").await;
+    tokio::time::sleep(std::time::Duration::from_millis(2500)).await;
+    assert_eq!(revalidate(&app, &token, "/api/todos?overdue=true", header::IF_NONE_MATCH, etag(&overdue)).await, StatusCode::OK);
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use axum::{
+    body::{self, Body},
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    routing::get,
+    Router,
+};
+use axum_server::middleware::create_cors_layer;
+use common::{register, test_app_with};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+/// Sends a request with the given `Cookie` header and optional CSRF token.
+async fn send_with_cookies(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    cookies: &str,
+    csrf_token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut builder = Request::builder().method(method).uri(uri).header(header::COOKIE, cookies);
+    if let Some(csrf_token) = csrf_token {
+        builder = builder.header("x-csrf-token", csrf_token);
+    }
+    let request = match body {
+        Some(json) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    };
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = body::to_bytes(response.into_body(), 1024 * 1024).await.unwrap();
+    (status, headers, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// The `name=value` pairs and attributes set by `Set-Cookie` headers.
+fn set_cookies(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect()
+}
+
+fn cookie_value(set_cookies: &[String], name: &str) -> String {
+    let prefix = format!("{name}=");
+    let cookie = set_cookies.iter().find(|cookie| cookie.starts_with(&prefix)).unwrap();
+    cookie[prefix.len()..].split(';').next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn browser_sessions_use_cookies_and_require_csrf_tokens_for_writes() {
+    let Some(app) = test_app_with(|cfg| cfg.cookie_auth.enabled = true).await else { return };
+    let (username, password) = register(&app).await;
+
+    let (status, headers, _) = send_with_cookies(
+        &app,
+        Method::POST,
+        "/api/users/login",
+        "",
+        None,
+        Some(json!({ "username": username, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let issued = set_cookies(&headers);
+    let session_cookie = issued.iter().find(|c| c.starts_with("todo_session=")).unwrap();
+    assert!(session_cookie.contains("HttpOnly"));
+    assert!(session_cookie.contains("Secure"));
+    assert!(session_cookie.contains("SameSite=Lax"));
+    let refresh_cookie = issued.iter().find(|c| c.starts_with("todo_refresh=")).unwrap();
+    assert!(refresh_cookie.contains("Path=/api/auth"));
+    let csrf_cookie = issued.iter().find(|c| c.starts_with("todo_csrf=")).unwrap();
+    assert!(!csrf_cookie.contains("HttpOnly"));
+
+    let csrf = cookie_value(&issued, "todo_csrf");
+    let cookies = format!(
+        "todo_session={}; todo_refresh={}; todo_csrf={}",
+        cookie_value(&issued, "todo_session"),
+        cookie_value(&issued, "todo_refresh"),
+        csrf
+    );
+
+    let (status, _, _) = send_with_cookies(&app, Method::GET, "/api/todos", &cookies, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let todo = json!({ "title": "Cookie todo" });
+    let (status, _, _) =
+        send_with_cookies(&app, Method::POST, "/api/todos", &cookies, None, Some(todo.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) =
+        send_with_cookies(&app, Method::POST, "/api/todos", &cookies, Some("forged"), Some(todo.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) =
+        send_with_cookies(&app, Method::POST, "/api/todos", &cookies, Some(&csrf), Some(todo)).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // The refresh token is read from its cookie, and new cookies replace the old ones
+    let (status, _, _) = send_with_cookies(&app, Method::POST, "/api/auth/refresh", &cookies, None, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, headers, _) =
+        send_with_cookies(&app, Method::POST, "/api/auth/refresh", &cookies, Some(&csrf), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let refreshed = set_cookies(&headers);
+    let csrf = cookie_value(&refreshed, "todo_csrf");
+    let cookies = format!(
+        "todo_session={}; todo_refresh={}; todo_csrf={}",
+        cookie_value(&refreshed, "todo_session"),
+        cookie_value(&refreshed, "todo_refresh"),
+        csrf
+    );
+
+    let (status, headers, _) =
+        send_with_cookies(&app, Method::POST, "/api/auth/logout", &cookies, Some(&csrf), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(set_cookies(&headers).iter().all(|cookie| cookie.contains("Max-Age=0")));
+
+    let (status, _, _) = send_with_cookies(&app, Method::GET, "/api/todos", &cookies, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn cors_allows_credentials_only_for_configured_origins() {
+    let app = Router::new()
+        .route("/health", get(|| async { "OK" }))
+        .layer(create_cors_layer(&["https://app.example.com".to_string()]));
+
+    let preflight = |origin: &str| {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/health")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type,x-csrf-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(preflight("https://app.example.com")).await.unwrap();
+    let headers = response.headers();
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+    let response = app.oneshot(preflight("https://evil.example.com")).await.unwrap();
+    assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use axum_server::mail::MailBackend;
+use common::{link_token, register, send, take_mail, test_app_with};
+use serde_json::json;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn mail_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("axum-server-mail-{}", Uuid::new_v4()))
+}
+
+#[tokio::test]
+async fn verification_link_unlocks_login_and_gated_operations() {
+    let dir = mail_dir();
+    let mail = dir.clone();
+    let Some(app) = test_app_with(move |cfg| {
+        cfg.mail.backend = MailBackend::File;
+        cfg.mail.file_dir = mail.to_string_lossy().into_owned();
+        cfg.require_email_verification = true;
+    })
+    .await else { return };
+
+    let (username, password) = register(&app).await;
+    let email = format!("{username}@example.com");
+    let credentials = json!({ "username": username, "password": password });
+
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(credentials.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let message = take_mail(&dir, &email, "Verify your email address").await;
+    let token = link_token(&message);
+
+    let (status, _) = send(&app, Method::GET, "/api/auth/verify-email?token=not-a-token", None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, user) = send(&app, Method::GET, &format!("/api/auth/verify-email?token={token}"), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(user["email_verified_at"].is_string());
+
+    let (status, body) = send(&app, Method::POST, "/api/users/login", None, Some(credentials)).await;
+    assert_eq!(status, StatusCode::OK);
+    let access = body["token"].as_str().unwrap();
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/verify-email/resend", Some(access), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn changing_email_requires_verifying_it_again() {
+    let dir = mail_dir();
+    let mail = dir.clone();
+    let Some(app) = test_app_with(move |cfg| {
+        cfg.mail.backend = MailBackend::File;
+        cfg.mail.file_dir = mail.to_string_lossy().into_owned();
+    })
+    .await else { return };
+
+    let (username, password) = register(&app).await;
+    let email = format!("{username}@example.com");
+    let old_link = link_token(&take_mail(&dir, &email, "Verify your email address").await);
+
+    // Login is allowed without verification, batch operations are not
+    let (_, body) = send(&app, Method::POST, "/api/users/login", None, Some(json!({ "username": username, "password": password }))).await;
+    let access = body["token"].as_str().unwrap();
+    let user_id = body["user"]["id"].as_str().unwrap();
+
+    let (status, _) = send(&app, Method::DELETE, "/api/todos/batch", Some(access), Some(json!([Uuid::new_v4()]))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/verify-email/resend", Some(access), None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    take_mail(&dir, &email, "Verify your email address").await;
+
+    let new_email = format!("new_{username}@example.com");
+    let (status, user) = send(&app, Method::PATCH, &format!("/api/users/{user_id}"), Some(access), Some(json!({ "email": new_email }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(user["email_verified_at"].is_null());
+
+    // Links sent to the previous address no longer work
+    let (status, _) = send(&app, Method::GET, &format!("/api/auth/verify-email?token={old_link}"), None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let new_link = link_token(&take_mail(&dir, &new_email, "Verify your email address").await);
+    let (status, _) = send(&app, Method::GET, &format!("/api/auth/verify-email?token={new_link}"), None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Past the verification gate; nothing matched, so nothing was deleted
+    let (status, _) = send(&app, Method::DELETE, "/api/todos/batch", Some(access), Some(json!([Uuid::new_v4()]))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use axum_server::{auth::JwtKeyConfig, config::Config};
+use common::{register_and_login, send, test_app_with};
+use jsonwebtoken::Algorithm;
+
+fn fixture(name: &str) -> Option<String> {
+    Some(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name))
+}
+
+fn hs256_key(cfg: &mut Config) {
+    cfg.jwt.keys = vec![JwtKeyConfig {
+        kid: "2024-hs".to_string(),
+        algorithm: Algorithm::HS256,
+        secret: Some("rotation-test-secret".to_string()),
+        private_key_path: None,
+        public_key_path: None,
+    }];
+    cfg.jwt.signing_kid = "2024-hs".to_string();
+}
+
+fn rotated_keys(cfg: &mut Config) {
+    hs256_key(cfg);
+    cfg.jwt.keys.push(JwtKeyConfig {
+        kid: "2025-rs".to_string(),
+        algorithm: Algorithm::RS256,
+        secret: None,
+        private_key_path: fixture("jwt_rs256.pem"),
+        public_key_path: fixture("jwt_rs256.pub.pem"),
+    });
+    cfg.jwt.keys.push(JwtKeyConfig {
+        kid: "2025-ed".to_string(),
+        algorithm: Algorithm::EdDSA,
+        secret: None,
+        private_key_path: None,
+        public_key_path: fixture("jwt_ed25519.pub.pem"),
+    });
+    cfg.jwt.signing_kid = "2025-rs".to_string();
+}
+
+#[tokio::test]
+async fn tokens_signed_with_a_retired_key_keep_working_after_rotation() {
+    let Some(old_app) = test_app_with(hs256_key).await else { return };
+    let (_, old_token) = register_and_login(&old_app).await;
+
+    let Some(new_app) = test_app_with(rotated_keys).await else { return };
+    let (status, _) = send(&new_app, Method::GET, "/api/todos", Some(&old_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, new_token) = register_and_login(&new_app).await;
+    let header = jsonwebtoken::decode_header(&new_token).unwrap();
+    assert_eq!(header.kid.as_deref(), Some("2025-rs"));
+    assert_eq!(header.alg, Algorithm::RS256);
+
+    // The old deployment does not know the new key yet
+    let (status, _) = send(&old_app, Method::GET, "/api/todos", Some(&new_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn jwks_publishes_only_public_keys() {
+    let Some(app) = test_app_with(rotated_keys).await else { return };
+
+    let (status, jwks) = send(&app, Method::GET, "/.well-known/jwks.json", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let keys = jwks["keys"].as_array().unwrap();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[0]["kid"], "2025-rs");
+    assert_eq!(keys[0]["kty"], "RSA");
+    assert_eq!(keys[0]["e"], "AQAB");
+    assert_eq!(keys[1]["kid"], "2025-ed");
+    assert_eq!(keys[1]["crv"], "Ed25519");
+    assert!(keys.iter().all(|key| key.get("k").is_none()));
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use axum::http::{header, Method, StatusCode};
+use axum_server::models::Role;
+use common::{login, register, send, send_with_headers, test_app_with, unique_username, user_with_role};
+use serde_json::json;
+
+#[tokio::test]
+async fn repeated_failures_lock_the_account_with_retry_after() {
+    let Some(app) = test_app_with(|cfg| cfg.lockout.max_failures_per_username = 3).await else { return };
+    let (username, password) = register(&app).await;
+    let wrong = json!({ "username": username, "password": "wrong-password" });
+
+    for _ in 0..3 {
+        let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(wrong.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    // Even the right password is refused while locked
+    let (status, headers, body) = send_with_headers(
+        &app,
+        Method::POST,
+        "/api/users/login",
+        None,
+        Some(json!({ "username": username, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = headers[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 0 && retry_after <= 900);
+    assert_eq!(body["retry_after"], retry_after);
+}
+
+#[tokio::test]
+async fn failures_back_off_exponentially() {
+    let Some(app) = test_app_with(|cfg| cfg.lockout.base_delay_secs = 30).await else { return };
+    let (username, password) = register(&app).await;
+
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(json!({ "username": username, "password": "nope" }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, headers, _) = send_with_headers(
+        &app,
+        Method::POST,
+        "/api/users/login",
+        None,
+        Some(json!({ "username": username, "password": password })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = headers[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+    assert!(retry_after <= 30);
+
+    // Unknown usernames are throttled the same way
+    let ghost = json!({ "username": unique_username(), "password": "nope" });
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(ghost.clone())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(ghost)).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn support_staff_can_unlock_a_locked_account() {
+    let Some(app) = test_app_with(|cfg| cfg.lockout.max_failures_per_username = 2).await else { return };
+
+    let (_, support_token) = user_with_role(&app, Role::Support).await;
+
+    let (username, password) = register(&app).await;
+    let user = login(&app, &username, &password).await;
+    let user_token = user["token"].as_str().unwrap();
+    let user_id = user["user"]["id"].as_str().unwrap();
+
+    for _ in 0..2 {
+        send(&app, Method::POST, "/api/users/login", None, Some(json!({ "username": username, "password": "bad" }))).await;
+    }
+    let credentials = json!({ "username": username, "password": password });
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(credentials.clone())).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    let unlock = format!("/api/admin/users/{user_id}/unlock");
+    let (status, _) = send(&app, Method::POST, &unlock, Some(user_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, Method::POST, &unlock, Some(&support_token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(credentials)).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,320 @@
+mod common;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, Method, StatusCode},
+    response::Redirect,
+    routing::{get, post},
+    Form, Json, Router,
+};
+use axum_server::{
+    auth::{JwtConfig, JwtKeyConfig, JwtKeys, OidcProviderConfig},
+    config::Config,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use common::{register, send, send_with_headers, send_with_request_headers, test_app_with, unique_username, verified_user};
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+const CLIENT_ID: &str = "todo-app";
+
+/// The account signed in at the mock issuer for the next authorization.
+#[derive(Clone)]
+struct MockUser {
+    sub: String,
+    email: String,
+    email_verified: bool,
+    /// Sent instead of the nonce from the authorization request.
+    nonce_override: Option<String>,
+}
+
+struct PendingCode {
+    user: MockUser,
+    nonce: String,
+    code_challenge: String,
+    redirect_uri: String,
+}
+
+#[derive(Clone)]
+struct MockIssuer {
+    url: String,
+    keys: Arc<JwtKeys>,
+    user: Arc<Mutex<Option<MockUser>>>,
+    codes: Arc<Mutex<HashMap<String, PendingCode>>>,
+}
+
+impl MockIssuer {
+    /// Serves discovery, JWKS, authorization and token endpoints on a random local port.
+    async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let fixture = |name: &str| Some(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name));
+
+        let keys = JwtKeys::from_config(&JwtConfig {
+            issuer: url.clone(),
+            audience: CLIENT_ID.to_string(),
+            access_token_ttl_secs: 300,
+            refresh_token_ttl_secs: 300,
+            signing_kid: "mock-rs".to_string(),
+            keys: vec![JwtKeyConfig {
+                kid: "mock-rs".to_string(),
+                algorithm: Algorithm::RS256,
+                secret: None,
+                private_key_path: fixture("jwt_rs256.pem"),
+                public_key_path: fixture("jwt_rs256.pub.pem"),
+            }],
+        })
+        .unwrap();
+
+        let issuer = MockIssuer {
+            url,
+            keys: Arc::new(keys),
+            user: Arc::new(Mutex::new(None)),
+            codes: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        let router = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .with_state(issuer.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        issuer
+    }
+
+    fn provider(&self, name: &str, link_by_email: bool) -> OidcProviderConfig {
+        OidcProviderConfig {
+            name: name.to_string(),
+            issuer: self.url.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            redirect_url: None,
+            link_by_email,
+        }
+    }
+
+    fn sign_in_as(&self, user: MockUser) {
+        *self.user.lock().unwrap() = Some(user);
+    }
+}
+
+async fn discovery(State(issuer): State<MockIssuer>) -> Json<Value> {
+    Json(json!({
+        "issuer": issuer.url,
+        "authorization_endpoint": format!("{}/authorize", issuer.url),
+        "token_endpoint": format!("{}/token", issuer.url),
+        "jwks_uri": format!("{}/jwks", issuer.url),
+    }))
+}
+
+async fn jwks(State(issuer): State<MockIssuer>) -> Json<Value> {
+    Json(serde_json::to_value(issuer.keys.jwks()).unwrap())
+}
+
+async fn authorize(
+    State(issuer): State<MockIssuer>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Redirect {
+    assert_eq!(params["client_id"], CLIENT_ID);
+    assert_eq!(params["response_type"], "code");
+    assert_eq!(params["code_challenge_method"], "S256");
+
+    let user = issuer.user.lock().unwrap().clone().expect("nobody signed in at the mock issuer");
+    let code = unique_username();
+    issuer.codes.lock().unwrap().insert(
+        code.clone(),
+        PendingCode {
+            user,
+            nonce: params["nonce"].clone(),
+            code_challenge: params["code_challenge"].clone(),
+            redirect_uri: params["redirect_uri"].clone(),
+        },
+    );
+
+    let mut redirect = Url::parse(&params["redirect_uri"]).unwrap();
+    redirect
+        .query_pairs_mut()
+        .append_pair("code", &code)
+        .append_pair("state", &params["state"]);
+    Redirect::to(redirect.as_str())
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    grant_type: String,
+    code: String,
+    redirect_uri: String,
+    client_id: String,
+    code_verifier: String,
+}
+
+async fn token(
+    State(issuer): State<MockIssuer>,
+    Form(request): Form<TokenRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let pending = issuer.codes.lock().unwrap().remove(&request.code).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(request.code_verifier.as_bytes()));
+    if request.grant_type != "authorization_code"
+        || request.client_id != CLIENT_ID
+        || request.redirect_uri != pending.redirect_uri
+        || challenge != pending.code_challenge
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let now = Utc::now().timestamp();
+    let id_token = issuer
+        .keys
+        .encode(&json!({
+            "iss": issuer.url,
+            "aud": CLIENT_ID,
+            "sub": pending.user.sub,
+            "email": pending.user.email,
+            "email_verified": pending.user.email_verified,
+            "name": "Sso User",
+            "nonce": pending.user.nonce_override.unwrap_or(pending.nonce),
+            "iat": now,
+            "exp": now + 300,
+        }))
+        .unwrap();
+
+    Ok(Json(json!({ "access_token": "unused", "token_type": "Bearer", "id_token": id_token })))
+}
+
+/// Starts a browser login through `provider` and signs in at the issuer,
+/// returning the callback path and the login state cookie.
+async fn start_login(app: &Router, issuer: &MockIssuer, provider: &str) -> (String, String) {
+    let (status, headers, _) =
+        send_with_headers(app, Method::GET, &format!("/api/auth/oidc/{provider}/login"), None, None).await;
+    assert_eq!(status, StatusCode::SEE_OTHER);
+    let authorization_url = headers[header::LOCATION].to_str().unwrap();
+    assert!(authorization_url.starts_with(&issuer.url));
+    let set_cookie = headers[header::SET_COOKIE].to_str().unwrap();
+    assert!(set_cookie.contains("HttpOnly"));
+    let cookie = set_cookie.split(';').next().unwrap().to_string();
+
+    let browser = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = browser.get(authorization_url).send().await.unwrap();
+    let callback = Url::parse(response.headers()[header::LOCATION].to_str().unwrap()).unwrap();
+    (format!("{}?{}", callback.path(), callback.query().unwrap()), cookie)
+}
+
+/// Runs a browser login through `provider`, returning the callback path, the
+/// login state cookie and the callback response.
+async fn sso_login(app: &Router, issuer: &MockIssuer, provider: &str) -> (String, String, StatusCode, Value) {
+    let (callback_path, cookie) = start_login(app, issuer, provider).await;
+    let (status, body) = complete(app, &callback_path, &cookie).await;
+    (callback_path, cookie, status, body)
+}
+
+/// Follows the provider's redirect back, from a browser holding `cookie`.
+async fn complete(app: &Router, callback_path: &str, cookie: &str) -> (StatusCode, Value) {
+    let (status, _, body) =
+        send_with_request_headers(app, Method::GET, callback_path, None, &[(header::COOKIE, cookie)], None).await;
+    (status, body)
+}
+
+fn mock_user(email: &str) -> MockUser {
+    MockUser {
+        sub: unique_username(),
+        email: email.to_string(),
+        email_verified: true,
+        nonce_override: None,
+    }
+}
+
+async fn sso_app(issuer: &MockIssuer) -> Option<Router> {
+    let providers = vec![issuer.provider("corp", false), issuer.provider("corp-linked", true)];
+    test_app_with(move |cfg: &mut Config| cfg.oidc.providers = providers).await
+}
+
+#[tokio::test]
+async fn first_sso_login_provisions_an_account_that_later_logins_reuse() {
+    let issuer = MockIssuer::start().await;
+    let Some(app) = sso_app(&issuer).await else { return };
+
+    let (status, body) = send(&app, Method::GET, "/api/auth/oidc/providers", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body[0]["name"], "corp");
+
+    let email = format!("{}@corp.example.com", unique_username());
+    issuer.sign_in_as(mock_user(&email));
+
+    let (callback_path, cookie, status, first) = sso_login(&app, &issuer, "corp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["user"]["email"], email.as_str());
+    assert!(first["user"]["email_verified_at"].is_string());
+
+    let token = first["token"].as_str().unwrap();
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The login state is single use
+    let (status, _) = complete(&app, &callback_path, &cookie).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, _, status, second) = sso_login(&app, &issuer, "corp").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["user"]["id"], first["user"]["id"]);
+}
+
+#[tokio::test]
+async fn sso_login_rejects_replayed_nonces_unverified_email_collisions_and_foreign_callbacks() {
+    let issuer = MockIssuer::start().await;
+    let Some(app) = sso_app(&issuer).await else { return };
+
+    let mut user = mock_user(&format!("{}@corp.example.com", unique_username()));
+    user.nonce_override = Some("stolen-nonce".to_string());
+    issuer.sign_in_as(user);
+    let (_, _, status, _) = sso_login(&app, &issuer, "corp").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A local account owns this address; only the linking provider may claim it
+    let (username, _) = register(&app).await;
+    let email = format!("{username}@example.com");
+    issuer.sign_in_as(mock_user(&email));
+    let (_, _, status, _) = sso_login(&app, &issuer, "corp").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let mut unverified = mock_user(&email);
+    unverified.email_verified = false;
+    issuer.sign_in_as(unverified);
+    let (_, _, status, _) = sso_login(&app, &issuer, "corp-linked").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Nor is an account whose owner never proved the address is theirs
+    issuer.sign_in_as(mock_user(&email));
+    let (_, _, status, _) = sso_login(&app, &issuer, "corp-linked").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (user_id, token) = verified_user(&app).await;
+    let (_, user) = send(&app, Method::GET, &format!("/api/users/{user_id}"), Some(&token), None).await;
+    issuer.sign_in_as(mock_user(user["email"].as_str().unwrap()));
+    let (_, _, status, body) = sso_login(&app, &issuer, "corp-linked").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["user"]["id"], user_id.as_str());
+
+    // The callback only completes in the browser that started the login
+    issuer.sign_in_as(mock_user(&format!("{}@corp.example.com", unique_username())));
+    let (_, cookie) = start_login(&app, &issuer, "corp").await;
+    let (foreign_path, _) = start_login(&app, &issuer, "corp").await;
+    let (status, _) = send(&app, Method::GET, &foreign_path, None, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = complete(&app, &foreign_path, &cookie).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{register_and_login, send, test_app, verified_user};
+use serde_json::json;
+
+#[tokio::test]
+async fn other_users_resources_are_not_accessible() {
+    let Some(app) = test_app().await else { return };
+    let (alice_id, alice) = register_and_login(&app).await;
+    let (_, mallory) = verified_user(&app).await;
+
+    let (_, category) = send(&app, Method::POST, "/api/categories", Some(&alice), Some(json!({ "name": "Private" }))).await;
+    let (_, todo) = send(&app, Method::POST, "/api/todos", Some(&alice), Some(json!({ "title": "Secret" }))).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    let category_id = category["id"].as_str().unwrap();
+
+    let (status, _) = send(&app, Method::GET, &format!("/api/todos/{todo_id}"), Some(&mallory), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/categories/{category_id}"), Some(&mallory), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, Method::GET, &format!("/api/users/{alice_id}"), Some(&mallory), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(&app, Method::DELETE, "/api/todos/batch", Some(&mallory), Some(json!([todo_id]))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, Method::GET, &format!("/api/todos/{todo_id}"), Some(&alice), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn cross_entity_references_must_be_owned() {
+    let Some(app) = test_app().await else { return };
+    let (_, alice) = register_and_login(&app).await;
+    let (_, mallory) = register_and_login(&app).await;
+
+    let (_, category) = send(&app, Method::POST, "/api/categories", Some(&alice), Some(json!({ "name": "Work" }))).await;
+    let (_, tag) = send(&app, Method::POST, "/api/tags", Some(&alice), Some(json!({ "name": "urgent" }))).await;
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/api/todos",
+        Some(&mallory),
+        Some(json!({ "title": "Sneaky", "category_id": category["id"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, todo) = send(&app, Method::POST, "/api/todos", Some(&mallory), Some(json!({ "title": "Mine" }))).await;
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        &format!("/api/todos/{}/tags/{}", todo["id"].as_str().unwrap(), tag["id"].as_str().unwrap()),
+        Some(&mallory),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{link_token, login, register, send, take_mail, test_app, test_app_with};
+use axum_server::mail::MailBackend;
+use serde_json::json;
+use uuid::Uuid;
+
+/// Pulls the reset code out of a password reset email.
+fn reset_code(message: &str) -> String {
+    let lines: Vec<&str> = message.lines().map(str::trim).collect();
+    let marker = lines.iter().position(|l| l.starts_with("Use this code")).unwrap();
+    lines[marker + 2].to_string()
+}
+
+#[tokio::test]
+async fn change_password_requires_current_password() {
+    let Some(app) = test_app().await else { return };
+    let (username, password) = register(&app).await;
+    let current = login(&app, &username, &password).await;
+    let other = login(&app, &username, &password).await;
+    let token = current["token"].as_str().unwrap();
+
+    let (status, _) = send(&app, Method::POST, "/api/users/me/password", Some(token), Some(json!({
+        "current_password": "wrong-password",
+        "new_password": "new-password-1",
+    }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, "/api/users/me/password", Some(token), Some(json!({
+        "current_password": password,
+        "new_password": "new-password-1",
+    }))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // The current session survives, every other one is signed out
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, Method::GET, "/api/todos", other["token"].as_str(), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, "/api/users/login", None, Some(json!({ "username": username, "password": password }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    login(&app, &username, "new-password-1").await;
+}
+
+#[tokio::test]
+async fn reset_password_via_emailed_token() {
+    let mail_dir = std::env::temp_dir().join(format!("axum-server-mail-{}", Uuid::new_v4()));
+    let dir = mail_dir.clone();
+    let Some(app) = test_app_with(move |cfg| {
+        cfg.mail.backend = MailBackend::File;
+        cfg.mail.file_dir = dir.to_string_lossy().into_owned();
+    })
+    .await else { return };
+
+    let (username, password) = register(&app).await;
+    let email = format!("{username}@example.com");
+    let session = login(&app, &username, &password).await;
+
+    // Reset emails only go to verified addresses
+    let verification = take_mail(&mail_dir, &email, "Verify your email address").await;
+    let uri = format!("/api/auth/verify-email?token={}", link_token(&verification));
+    let (status, _) = send(&app, Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Unknown addresses get the same answer
+    let (status, _) = send(&app, Method::POST, "/api/auth/password/forgot", None, Some(json!({ "email": "nobody@example.com" }))).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/password/forgot", None, Some(json!({ "email": email }))).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let reset_token = reset_code(&take_mail(&mail_dir, &email, "Reset your password").await);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/password/reset", None, Some(json!({
+        "token": reset_token,
+        "new_password": "brand-new-password",
+    }))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // Existing sessions are gone and the token cannot be used twice
+    let (status, _) = send(&app, Method::GET, "/api/todos", session["token"].as_str(), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, Method::POST, "/api/auth/refresh", None, Some(json!({ "refresh_token": session["refresh_token"] }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/password/reset", None, Some(json!({
+        "token": reset_token,
+        "new_password": "another-password",
+    }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    login(&app, &username, "brand-new-password").await;
+    let _ = std::fs::remove_dir_all(&mail_dir);
+}
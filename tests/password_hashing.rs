@@ -0,0 +1,53 @@
+mod common;
+
+use axum_server::{config::Config, db};
+use common::{login, register, test_app, test_app_with};
+
+async fn stored_hash(username: &str) -> String {
+    let config = Config::from_env().expect("load config");
+    let pool = db::create_pool(&config.database_url).await.expect("connect to DB");
+    sqlx::query_scalar("SELECT password_hash FROM users WHERE username = $1")
+        .bind(username)
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+}
+
+async fn set_hash(username: &str, hash: &str) {
+    let config = Config::from_env().expect("load config");
+    let pool = db::create_pool(&config.database_url).await.expect("connect to DB");
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE username = $2")
+        .bind(hash)
+        .bind(username)
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn legacy_bcrypt_hashes_verify_and_are_upgraded_on_login() {
+    let Some(app) = test_app().await else { return };
+    let (username, password) = register(&app).await;
+    assert!(stored_hash(&username).await.starts_with("$argon2id$"));
+
+    set_hash(&username, &bcrypt::hash(&password, 4).unwrap()).await;
+
+    login(&app, &username, &password).await;
+    let upgraded = stored_hash(&username).await;
+    assert!(upgraded.starts_with("$argon2id$"));
+
+    // Already current hashes are left alone
+    login(&app, &username, &password).await;
+    assert_eq!(stored_hash(&username).await, upgraded);
+}
+
+#[tokio::test]
+async fn under_cost_hashes_are_upgraded_to_the_configured_parameters() {
+    let Some(weak) = test_app_with(|cfg| cfg.password_hash.iterations = 1).await else { return };
+    let (username, password) = register(&weak).await;
+    assert!(stored_hash(&username).await.contains("t=1,"));
+
+    let Some(app) = test_app_with(|cfg| cfg.password_hash.iterations = 3).await else { return };
+    login(&app, &username, &password).await;
+    assert!(stored_hash(&username).await.contains("t=3,"));
+}
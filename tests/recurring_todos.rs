@@ -0,0 +1,140 @@
+mod common;
+
+use axum::{http::{Method, StatusCode}, Router};
+use axum_server::recurrence::{parse_timezone, RecurrenceRule};
+use chrono::{DateTime, Utc};
+use common::{register_and_login, send, test_app, unique_username};
+use serde_json::{json, Value};
+
+fn at(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+}
+
+#[test]
+fn rules_are_evaluated_in_the_series_timezone() {
+    let berlin = parse_timezone("Europe/Berlin").unwrap();
+
+    let weekly: RecurrenceRule = "freq=weekly;byday=mo,we".parse().unwrap();
+    assert_eq!(weekly.to_string(), "FREQ=WEEKLY;BYDAY=MO,WE");
+    let start = at("2024-03-04T08:00:00Z"); // Monday 09:00 in Berlin
+    let first: Vec<_> = weekly.occurrences(start, berlin).take(4).collect();
+    assert_eq!(
+        first,
+        [start, at("2024-03-06T08:00:00Z"), at("2024-03-11T08:00:00Z"), at("2024-03-13T08:00:00Z")]
+    );
+    // Still 09:00 local time after the switch to summer time
+    assert_eq!(
+        weekly.next_after(start, berlin, at("2024-03-29T12:00:00Z")),
+        Some(at("2024-04-01T07:00:00Z"))
+    );
+
+    // The last Friday of each month, three times
+    let last_friday: RecurrenceRule = "FREQ=MONTHLY;BYDAY=-1FR;COUNT=3".parse().unwrap();
+    let fridays: Vec<_> = last_friday.occurrences(at("2024-01-26T17:00:00Z"), parse_timezone("UTC").unwrap()).collect();
+    assert_eq!(fridays, [at("2024-01-26T17:00:00Z"), at("2024-02-23T17:00:00Z"), at("2024-03-29T17:00:00Z")]);
+
+    // Months without a 31st are skipped
+    let month_end: RecurrenceRule = "FREQ=MONTHLY;BYMONTHDAY=31;UNTIL=20240601".parse().unwrap();
+    let days: Vec<_> = month_end.occurrences(at("2024-01-31T12:00:00Z"), parse_timezone("UTC").unwrap()).collect();
+    assert_eq!(days, [at("2024-01-31T12:00:00Z"), at("2024-03-31T12:00:00Z"), at("2024-05-31T12:00:00Z")]);
+
+    for invalid in ["FREQ=HOURLY", "BYDAY=MO", "FREQ=WEEKLY;BYDAY=1MO", "FREQ=DAILY;COUNT=2;UNTIL=20240101", "FREQ=DAILY;BYSETPOS=1"] {
+        assert!(invalid.parse::<RecurrenceRule>().is_err(), "{invalid} should be rejected");
+    }
+}
+
+async fn find_open(app: &Router, token: &str, title: &str) -> Vec<Value> {
+    let (status, body) = send(app, Method::GET, &format!("/api/todos?search={title}&completed=false"), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    body["todos"].as_array().unwrap().clone()
+}
+
+async fn set_completed(app: &Router, token: &str, todo: &Value, completed: bool) {
+    let uri = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    let (status, _) = send(app, Method::PATCH, &uri, Some(token), Some(json!({ "completed": completed }))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn completing_an_occurrence_schedules_the_next_one() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let title = unique_username();
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/api/todos",
+        Some(&token),
+        Some(json!({ "title": title, "due_date": "2024-03-04T08:00:00Z", "recurrence": { "rrule": "FREQ=DAILY;FOO=1" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, category) = send(&app, Method::POST, "/api/categories", Some(&token), Some(json!({ "name": "Chores" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, first) = send(
+        &app,
+        Method::POST,
+        "/api/todos",
+        Some(&token),
+        Some(json!({
+            "title": title,
+            "due_date": "2024-03-04T08:00:00Z",
+            "category_id": category["id"],
+            "tags": ["home"],
+            "recurrence": { "rrule": "FREQ=WEEKLY;BYDAY=MO,WE", "timezone": "Europe/Berlin" },
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(first["recurrence"]["rrule"], "FREQ=WEEKLY;BYDAY=MO,WE");
+
+    // Completing twice, with a reopen in between, creates a single successor
+    set_completed(&app, &token, &first, true).await;
+    set_completed(&app, &token, &first, false).await;
+    set_completed(&app, &token, &first, true).await;
+    let open = find_open(&app, &token, &title).await;
+    assert_eq!(open.len(), 1);
+    let second = &open[0];
+    assert_eq!(second["due_date"], "2024-03-06T08:00:00Z");
+    assert_eq!(second["category"]["id"], category["id"]);
+    assert_eq!(second["tags"][0]["name"], "home");
+
+    // Skipping replaces the occurrence with the one after it
+    let (status, skipped) = send(&app, Method::POST, &format!("/api/todos/{}/skip", second["id"].as_str().unwrap()), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let third = skipped["next"].clone();
+    assert_eq!(third["due_date"], "2024-03-11T08:00:00Z");
+    let (status, _) = send(&app, Method::GET, &format!("/api/todos/{}", second["id"].as_str().unwrap()), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Editing the series changes open occurrences and future ones, not completed ones
+    let renamed = format!("{title}-renamed");
+    let (status, updated) = send(
+        &app,
+        Method::PATCH,
+        &format!("/api/todos/{}/series", third["id"].as_str().unwrap()),
+        Some(&token),
+        Some(json!({ "title": renamed })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["title"], renamed.as_str());
+    let (_, first_now) = send(&app, Method::GET, &format!("/api/todos/{}", first["id"].as_str().unwrap()), Some(&token), None).await;
+    assert_eq!(first_now["title"], title.as_str());
+
+    // A stopped series creates no further occurrences
+    let (status, stopped) = send(
+        &app,
+        Method::POST,
+        &format!("/api/todos/{}/series/stop", third["id"].as_str().unwrap()),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(stopped["recurrence"]["ended_at"].is_string());
+    set_completed(&app, &token, &third, true).await;
+    assert!(find_open(&app, &token, &title).await.is_empty());
+}
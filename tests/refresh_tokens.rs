@@ -0,0 +1,55 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{login, register, send, test_app};
+use serde_json::json;
+
+#[tokio::test]
+async fn refresh_rotates_and_replay_revokes_the_family() {
+    let Some(app) = test_app().await else { return };
+    let (username, password) = register(&app).await;
+    let first = login(&app, &username, &password).await;
+    let first_refresh = first["refresh_token"].as_str().unwrap();
+
+    let (status, second) = send(&app, Method::POST, "/api/auth/refresh", None, Some(json!({ "refresh_token": first_refresh }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let second_refresh = second["refresh_token"].as_str().unwrap();
+    let second_access = second["token"].as_str().unwrap();
+    assert_ne!(first_refresh, second_refresh);
+
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(second_access), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Replaying the first token revokes every token in the family
+    let (status, _) = send(&app, Method::POST, "/api/auth/refresh", None, Some(json!({ "refresh_token": first_refresh }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/refresh", None, Some(json!({ "refresh_token": second_refresh }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(second_access), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn logout_revokes_access_and_refresh_tokens() {
+    let Some(app) = test_app().await else { return };
+    let (username, password) = register(&app).await;
+    let session = login(&app, &username, &password).await;
+    let access = session["token"].as_str().unwrap();
+    let refresh = session["refresh_token"].as_str().unwrap();
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/logout", None, Some(json!({ "refresh_token": refresh }))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(access), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/refresh", None, Some(json!({ "refresh_token": refresh }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Other sessions of the same user are unaffected
+    let other = login(&app, &username, &password).await;
+    let (status, _) = send(&app, Method::GET, "/api/todos", other["token"].as_str(), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
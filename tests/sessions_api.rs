@@ -0,0 +1,71 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{login, register, send, test_app};
+
+#[tokio::test]
+async fn sessions_are_listed_and_revocable() {
+    let Some(app) = test_app().await else { return };
+    let (username, password) = register(&app).await;
+    let first = login(&app, &username, &password).await;
+    let second = login(&app, &username, &password).await;
+    let third = login(&app, &username, &password).await;
+    let first_token = first["token"].as_str().unwrap();
+    let second_token = second["token"].as_str().unwrap();
+    let third_token = third["token"].as_str().unwrap();
+    assert!(first["user"]["last_login_at"].is_string());
+
+    let (status, sessions) = send(&app, Method::GET, "/api/auth/sessions", Some(first_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions.iter().filter(|s| s["current"] == true).count(), 1);
+
+    // Revoke the second session by id
+    let (_, sessions) = send(&app, Method::GET, "/api/auth/sessions", Some(second_token), None).await;
+    let second_id = sessions
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["current"] == true)
+        .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let uri = format!("/api/auth/sessions/{second_id}");
+    let (status, _) = send(&app, Method::DELETE, &uri, Some(first_token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(second_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Revoking all others keeps only the caller's session
+    let (status, _) = send(&app, Method::DELETE, "/api/auth/sessions", Some(first_token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(third_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, sessions) = send(&app, Method::GET, "/api/auth/sessions", Some(first_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(sessions.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn cannot_revoke_another_users_session() {
+    let Some(app) = test_app().await else { return };
+    let (alice, alice_password) = register(&app).await;
+    let (bob, bob_password) = register(&app).await;
+    let alice_login = login(&app, &alice, &alice_password).await;
+    let bob_login = login(&app, &bob, &bob_password).await;
+    let alice_token = alice_login["token"].as_str().unwrap();
+    let bob_token = bob_login["token"].as_str().unwrap();
+
+    let (_, sessions) = send(&app, Method::GET, "/api/auth/sessions", Some(bob_token), None).await;
+    let bob_session = sessions[0]["id"].as_str().unwrap().to_string();
+
+    let uri = format!("/api/auth/sessions/{bob_session}");
+    let (status, _) = send(&app, Method::DELETE, &uri, Some(alice_token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(bob_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
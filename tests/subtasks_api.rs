@@ -0,0 +1,139 @@
+mod common;
+
+use axum::{http::{Method, StatusCode}, Router};
+use axum_server::models::SubtaskDeletion;
+use common::{register_and_login, send, test_app_with};
+use serde_json::{json, Value};
+
+async fn create(app: &Router, token: &str, title: &str, parent: Option<&Value>) -> Value {
+    let body = json!({ "title": title, "parent_id": parent.map(|todo| todo["id"].clone()) });
+    let (status, todo) = send(app, Method::POST, "/api/todos", Some(token), Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    todo
+}
+
+async fn fetch(app: &Router, token: &str, todo: &Value) -> (StatusCode, Value) {
+    send(app, Method::GET, &format!("/api/todos/{}", todo["id"].as_str().unwrap()), Some(token), None).await
+}
+
+#[tokio::test]
+async fn subtasks_roll_progress_up_and_cannot_form_cycles() {
+    let Some(app) = test_app_with(|cfg| {
+        cfg.subtasks.complete_children = true;
+        cfg.subtasks.auto_complete_parent = true;
+    })
+    .await else { return };
+    let (_, token) = register_and_login(&app).await;
+
+    let project = create(&app, &token, "Project", None).await;
+    let design = create(&app, &token, "Design", Some(&project)).await;
+    let build = create(&app, &token, "Build", Some(&project)).await;
+    let wireframes = create(&app, &token, "Wireframes", Some(&design)).await;
+    assert_eq!(design["parent_id"], project["id"]);
+
+    let (status, children) = send(
+        &app,
+        Method::GET,
+        &format!("/api/todos/{}/subtasks", project["id"].as_str().unwrap()),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let titles: Vec<&str> = children.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Design", "Build"]);
+    assert_eq!(children[0]["subtask_count"], 1);
+
+    // A todo cannot be moved under itself or its own subtree
+    for target in [&project, &wireframes] {
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            &format!("/api/todos/{}/move", project["id"].as_str().unwrap()),
+            Some(&token),
+            Some(json!({ "parent_id": target["id"] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    // Completing a todo completes its subtree and, once all siblings are done, its parent
+    let (status, _) = send(
+        &app,
+        Method::PATCH,
+        &format!("/api/todos/{}", design["id"].as_str().unwrap()),
+        Some(&token),
+        Some(json!({ "completed": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetch(&app, &token, &wireframes).await.1["completed"], true);
+    let (_, parent) = fetch(&app, &token, &project).await;
+    assert_eq!((parent["subtask_count"].as_i64(), parent["completed_subtask_count"].as_i64()), (Some(2), Some(1)));
+    assert_eq!(parent["completed"], false);
+
+    send(
+        &app,
+        Method::PATCH,
+        &format!("/api/todos/{}", build["id"].as_str().unwrap()),
+        Some(&token),
+        Some(json!({ "completed": true })),
+    )
+    .await;
+    assert_eq!(fetch(&app, &token, &project).await.1["completed"], true);
+
+    // Moving an open subtask in reopens the parent
+    let stray = create(&app, &token, "Stray", None).await;
+    let (status, moved) = send(
+        &app,
+        Method::POST,
+        &format!("/api/todos/{}/move", stray["id"].as_str().unwrap()),
+        Some(&token),
+        Some(json!({ "parent_id": build["id"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(moved["parent_id"], build["id"]);
+    assert_eq!(fetch(&app, &token, &build).await.1["completed"], false);
+    assert_eq!(fetch(&app, &token, &project).await.1["completed"], false);
+
+    // Another user's todo cannot be used as a parent
+    let (_, other) = register_and_login(&app).await;
+    let body = json!({ "title": "Intruder", "parent_id": project["id"] });
+    let (status, _) = send(&app, Method::POST, "/api/todos", Some(&other), Some(body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deleting_a_parent_cascades_or_reparents_its_subtasks() {
+    let Some(app) = test_app_with(|cfg| cfg.subtasks.on_parent_delete = SubtaskDeletion::Reparent).await else {
+        return;
+    };
+    let (_, token) = register_and_login(&app).await;
+
+    // The configured rule moves subtasks up to the deleted todo's parent
+    let root = create(&app, &token, "Root", None).await;
+    let middle = create(&app, &token, "Middle", Some(&root)).await;
+    let leaf = create(&app, &token, "Leaf", Some(&middle)).await;
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/todos/{}", middle["id"].as_str().unwrap()), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, leaf_now) = fetch(&app, &token, &leaf).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(leaf_now["parent_id"], root["id"]);
+
+    // The request can ask for the whole subtree to be removed instead
+    let nested = create(&app, &token, "Nested", Some(&leaf)).await;
+    let (status, _) = send(
+        &app,
+        Method::DELETE,
+        &format!("/api/todos/{}?subtasks=cascade", root["id"].as_str().unwrap()),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    for todo in [&root, &leaf, &nested] {
+        assert_eq!(fetch(&app, &token, todo).await.0, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use axum::{http::{Method, StatusCode}, Router};
+use common::{register_and_login, send, test_app, unique_username, verified_user};
+use serde_json::{json, Value};
+
+async fn create(app: &Router, token: &str, title: &str) -> Value {
+    let (status, todo) = send(app, Method::POST, "/api/todos", Some(token), Some(json!({ "title": title }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    todo
+}
+
+fn dependency_uri(todo: &Value, blocker: &Value) -> String {
+    format!("/api/todos/{}/dependencies/{}", todo["id"].as_str().unwrap(), blocker["id"].as_str().unwrap())
+}
+
+async fn complete(app: &Router, token: &str, todo: &Value, query: &str) -> StatusCode {
+    let uri = format!("/api/todos/{}{}", todo["id"].as_str().unwrap(), query);
+    send(app, Method::PATCH, &uri, Some(token), Some(json!({ "completed": true }))).await.0
+}
+
+#[tokio::test]
+async fn dependencies_cannot_form_cycles_and_block_completion() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let design = create(&app, &token, "Design").await;
+    let build = create(&app, &token, "Build").await;
+    let ship = create(&app, &token, "Ship").await;
+
+    let (status, linked) = send(&app, Method::PUT, &dependency_uri(&build, &design), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(linked["blocked_by"], json!([design["id"]]));
+    let (status, _) = send(&app, Method::PUT, &dependency_uri(&ship, &build), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, design_now) = send(&app, Method::GET, &format!("/api/todos/{}", design["id"].as_str().unwrap()), Some(&token), None).await;
+    assert_eq!(design_now["blocks"], json!([build["id"]]));
+
+    // Directly or through other todos, nothing may wait for itself
+    for (todo, blocker) in [(&design, &ship), (&design, &design)] {
+        let (status, _) = send(&app, Method::PUT, &dependency_uri(todo, blocker), Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    // Another user's todo cannot be a blocker
+    let (_, other) = register_and_login(&app).await;
+    let foreign = create(&app, &other, "Foreign").await;
+    let (status, _) = send(&app, Method::PUT, &dependency_uri(&build, &foreign), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // A blocked todo can only be completed when forced
+    assert_eq!(complete(&app, &token, &build, "").await, StatusCode::CONFLICT);
+    assert_eq!(complete(&app, &token, &build, "?force=true").await, StatusCode::OK);
+    assert_eq!(complete(&app, &token, &ship, "").await, StatusCode::OK);
+
+    let (status, _) = send(&app, Method::DELETE, &dependency_uri(&build, &design), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::DELETE, &dependency_uri(&build, &design), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+async fn titles(app: &Router, token: &str, query: &str) -> Vec<String> {
+    let (status, body) = send(app, Method::GET, &format!("/api/todos?{query}"), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut titles: Vec<String> = body["todos"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect();
+    titles.sort();
+    titles
+}
+
+#[tokio::test]
+async fn todos_can_be_filtered_by_blocked_and_actionable() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = verified_user(&app).await;
+    let prefix = unique_username();
+    let first = create(&app, &token, &format!("{prefix}-a")).await;
+    let second = create(&app, &token, &format!("{prefix}-b")).await;
+    let done = create(&app, &token, &format!("{prefix}-c")).await;
+    send(&app, Method::PUT, &dependency_uri(&second, &first), Some(&token), None).await;
+    assert_eq!(complete(&app, &token, &done, "").await, StatusCode::OK);
+
+    let search = format!("search={prefix}");
+    assert_eq!(titles(&app, &token, &format!("{search}&blocked=true")).await, [format!("{prefix}-b")]);
+    assert_eq!(titles(&app, &token, &format!("{search}&blocked=false")).await, [format!("{prefix}-a"), format!("{prefix}-c")]);
+    assert_eq!(titles(&app, &token, &format!("{search}&actionable=true")).await, [format!("{prefix}-a")]);
+    assert_eq!(titles(&app, &token, &format!("{search}&actionable=false")).await, [format!("{prefix}-b"), format!("{prefix}-c")]);
+
+    // A batch may complete a todo together with its blocker, in any order
+    let body = json!({ "todo_ids": [second["id"]], "completed": true });
+    let (status, _) = send(&app, Method::PATCH, "/api/todos/batch", Some(&token), Some(body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let body = json!({ "todo_ids": [second["id"], first["id"]], "completed": true });
+    let (status, _) = send(&app, Method::PATCH, "/api/todos/batch", Some(&token), Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&app, &token, &format!("{search}&blocked=true")).await, Vec::<String>::new());
+}
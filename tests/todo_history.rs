@@ -0,0 +1,93 @@
+mod common;
+
+use axum::{http::{Method, StatusCode}, Router};
+use axum_server::{config::Config, db};
+use common::{register_and_login, send, test_app, verified_user};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn history(app: &Router, token: &str, id: &str) -> Value {
+    let (status, history) = send(app, Method::GET, &format!("/api/todos/{id}/history"), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    history
+}
+
+#[tokio::test]
+async fn every_kind_of_change_is_recorded_as_a_field_diff() {
+    let Some(app) = test_app().await else { return };
+    let (user_id, token) = verified_user(&app).await;
+    let (_, todo) = send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "Draft", "tags": ["home"] }))).await;
+    let id = todo["id"].as_str().unwrap();
+    assert_eq!(history(&app, &token, id).await, json!([]));
+
+    send(&app, Method::PATCH, &format!("/api/todos/{id}"), Some(&token), Some(json!({ "title": "Final", "priority": 3 }))).await;
+    send(&app, Method::PATCH, "/api/todos/batch", Some(&token), Some(json!({ "todo_ids": [id], "completed": true }))).await;
+    let (_, tag) = send(&app, Method::POST, "/api/tags", Some(&token), Some(json!({ "name": "urgent" }))).await;
+    let (status, _) = send(&app, Method::PUT, &format!("/api/todos/{id}/tags/{}", tag["id"].as_str().unwrap()), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    // An update that changes nothing leaves no revision
+    send(&app, Method::PATCH, &format!("/api/todos/{id}"), Some(&token), Some(json!({ "title": "Final" }))).await;
+
+    let revisions = history(&app, &token, id).await;
+    let revisions = revisions.as_array().unwrap();
+    assert_eq!(revisions.len(), 3);
+    assert!(revisions.iter().all(|r| r["actor_id"] == json!(user_id)));
+    assert_eq!(revisions[0]["revision"], 3);
+    assert_eq!(revisions[0]["changes"], json!([{ "field": "tags", "before": ["home"], "after": ["home", "urgent"] }]));
+    assert_eq!(revisions[1]["changes"], json!([{ "field": "completed", "before": false, "after": true }]));
+    assert_eq!(
+        revisions[2]["changes"],
+        json!([
+            { "field": "priority", "before": null, "after": 3 },
+            { "field": "title", "before": "Draft", "after": "Final" },
+        ])
+    );
+
+    // Other users see neither the history nor the todo
+    let (_, other) = register_and_login(&app).await;
+    let (status, _) = send(&app, Method::GET, &format!("/api/todos/{id}/history"), Some(&other), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn reverting_restores_fields_and_tags_and_revisions_are_immutable() {
+    let Some(app) = test_app().await else { return };
+    let config = Config::from_env().expect("load config");
+    let pool = db::create_pool(&config.database_url).await.expect("connect to DB");
+    let (_, token) = register_and_login(&app).await;
+    let (_, todo) = send(&app, Method::POST, "/api/todos", Some(&token), Some(json!({ "title": "Plan", "tags": ["work"] }))).await;
+    let id = todo["id"].as_str().unwrap();
+
+    let update = json!({ "title": "Plan trip", "description": "Book flights", "tags": ["travel"] });
+    let (_, first) = send(&app, Method::PATCH, &format!("/api/todos/{id}"), Some(&token), Some(update)).await;
+    let travel = first["tags"][0]["id"].as_str().unwrap().to_string();
+    let update = json!({ "title": "Plan holiday", "tags": ["work"] });
+    send(&app, Method::PATCH, &format!("/api/todos/{id}"), Some(&token), Some(update)).await;
+    send(&app, Method::DELETE, &format!("/api/tags/{travel}"), Some(&token), None).await;
+
+    // Revision 1 is restored as it left the todo, with its tag back from the trash
+    let (status, reverted) = send(&app, Method::POST, &format!("/api/todos/{id}/revert/1"), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reverted["title"], "Plan trip");
+    assert_eq!(reverted["description"], "Book flights");
+    let tags: Vec<&str> = reverted["tags"].as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap()).collect();
+    assert_eq!(tags, [travel.as_str()]);
+    let (_, all_tags) = send(&app, Method::GET, "/api/tags", Some(&token), None).await;
+    let named_travel = all_tags.as_array().unwrap().iter().filter(|t| t["name"] == "travel").count();
+    assert_eq!(named_travel, 1);
+
+    // The revert is a revision of its own, and can itself be reverted
+    let revisions = history(&app, &token, id).await;
+    assert_eq!(revisions[0]["revision"], 3);
+    let (_, redone) = send(&app, Method::POST, &format!("/api/todos/{id}/revert/2"), Some(&token), None).await;
+    assert_eq!(redone["title"], "Plan holiday");
+    let (status, _) = send(&app, Method::POST, &format!("/api/todos/{id}/revert/9"), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let todo_id: Uuid = id.parse().unwrap();
+    let rewritten = sqlx::query("UPDATE todo_revisions SET after = before WHERE todo_id = $1")
+        .bind(todo_id)
+        .execute(&pool)
+        .await;
+    assert!(rewritten.is_err());
+}
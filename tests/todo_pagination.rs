@@ -0,0 +1,79 @@
+mod common;
+
+use axum::{http::{Method, StatusCode}, Router};
+use common::{register_and_login, send, test_app, unique_username};
+use serde_json::{json, Value};
+
+async fn list(app: &Router, token: &str, query: &str) -> Value {
+    let (status, body) = send(app, Method::GET, &format!("/api/todos?{query}"), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    body
+}
+
+fn titles(page: &Value) -> Vec<&str> {
+    page["todos"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect()
+}
+
+async fn create_numbered(app: &Router, token: &str, prefix: &str, count: usize) {
+    for n in 1..=count {
+        let body = json!({ "title": format!("{prefix} {n}") });
+        let (status, _) = send(app, Method::POST, "/api/todos", Some(token), Some(body)).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+}
+
+#[tokio::test]
+async fn cursors_walk_the_list_without_skips_or_duplicates() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let prefix = unique_username();
+    create_numbered(&app, &token, &prefix, 5).await;
+
+    let first = list(&app, &token, &format!("search={prefix}&per_page=2")).await;
+    assert_eq!(titles(&first), [format!("{prefix} 5"), format!("{prefix} 4")]);
+    assert_eq!(first["total"], Value::Null);
+    assert_eq!(first["prev_cursor"], Value::Null);
+
+    // A todo added meanwhile lands before the cursor and does not shift later pages
+    create_numbered(&app, &token, &format!("{prefix} new"), 1).await;
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second = list(&app, &token, &format!("search={prefix}&per_page=2&cursor={cursor}")).await;
+    assert_eq!(titles(&second), [format!("{prefix} 3"), format!("{prefix} 2")]);
+    assert_eq!(second["page"], Value::Null);
+    let cursor = second["next_cursor"].as_str().unwrap();
+    let last = list(&app, &token, &format!("search={prefix}&per_page=2&cursor={cursor}&with_total=true")).await;
+    assert_eq!(titles(&last), [format!("{prefix} 1")]);
+    assert_eq!(last["next_cursor"], Value::Null);
+    assert_eq!(last["total"], 6);
+
+    // And back again
+    let cursor = last["prev_cursor"].as_str().unwrap();
+    let back = list(&app, &token, &format!("search={prefix}&per_page=2&cursor={cursor}")).await;
+    assert_eq!(titles(&back), titles(&second));
+    let cursor = back["prev_cursor"].as_str().unwrap();
+    let front = list(&app, &token, &format!("search={prefix}&per_page=2&cursor={cursor}")).await;
+    assert_eq!(titles(&front), titles(&first));
+    assert!(front["prev_cursor"].is_string());
+}
+
+#[tokio::test]
+async fn page_numbers_still_work_and_bad_cursors_are_refused() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let prefix = unique_username();
+    create_numbered(&app, &token, &prefix, 3).await;
+
+    let second = list(&app, &token, &format!("search={prefix}&page=2&per_page=2&with_total=true")).await;
+    assert_eq!(titles(&second), [format!("{prefix} 1")]);
+    assert_eq!(second["page"], 2);
+    assert_eq!(second["total"], 3);
+    let cursor = second["prev_cursor"].as_str().unwrap();
+    let first = list(&app, &token, &format!("search={prefix}&per_page=2&cursor={cursor}")).await;
+    assert_eq!(titles(&first), [format!("{prefix} 3"), format!("{prefix} 2")]);
+
+    let tampered = format!("{}x", cursor);
+    for query in [format!("cursor={tampered}"), "cursor=garbage".to_string(), format!("page=1&cursor={cursor}")] {
+        let (status, _) = send(&app, Method::GET, &format!("/api/todos?{query}"), Some(&token), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use axum::{http::{Method, StatusCode}, Router};
+use axum_server::{config::Config, db::{self, DbPool}, jobs::reminders, kafka::EventProducer};
+use chrono::{DateTime, Duration, Utc};
+use common::{register_and_login, send, test_app};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn create_todo(app: &Router, token: &str, due_date: Option<DateTime<Utc>>) -> Value {
+    let (status, todo) = send(app, Method::POST, "/api/todos", Some(token), Some(json!({ "title": "Renew passport", "due_date": due_date }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    todo
+}
+
+/// Whole seconds from now, as the database rounds to microseconds.
+fn from_now(offset: Duration) -> DateTime<Utc> {
+    DateTime::from_timestamp((Utc::now() + offset).timestamp(), 0).unwrap()
+}
+
+fn reminders_uri(todo: &Value) -> String {
+    format!("/api/todos/{}/reminders", todo["id"].as_str().unwrap())
+}
+
+#[tokio::test]
+async fn reminders_are_relative_to_the_due_date_or_absolute() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let due = from_now(Duration::days(2));
+    let todo = create_todo(&app, &token, Some(due)).await;
+    let uri = reminders_uri(&todo);
+
+    for invalid in [json!({}), json!({ "remind_at": due, "before_due_secs": 60 }), json!({ "before_due_secs": -1 })] {
+        let (status, _) = send(&app, Method::POST, &uri, Some(&token), Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let undated = create_todo(&app, &token, None).await;
+    let (status, _) = send(&app, Method::POST, &reminders_uri(&undated), Some(&token), Some(json!({ "before_due_secs": 60 }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, relative) = send(&app, Method::POST, &uri, Some(&token), Some(json!({ "before_due_secs": 3600 }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let fire_at: DateTime<Utc> = relative["fire_at"].as_str().unwrap().parse().unwrap();
+    assert_eq!(due - fire_at, Duration::hours(1));
+    let (status, _) = send(&app, Method::POST, &uri, Some(&token), Some(json!({ "remind_at": Utc::now() + Duration::days(1) }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Moving the due date moves the relative reminder with it
+    let uri_todo = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    let new_due = due + Duration::days(1);
+    send(&app, Method::PATCH, &uri_todo, Some(&token), Some(json!({ "due_date": new_due }))).await;
+    let (status, listed) = send(&app, Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    let fire_at: DateTime<Utc> = listed[0]["fire_at"].as_str().unwrap().parse().unwrap();
+    assert_eq!(new_due - fire_at, Duration::hours(1));
+
+    // Reminders belong to the todo's owner
+    let (_, other) = register_and_login(&app).await;
+    let (status, _) = send(&app, Method::GET, &uri, Some(&other), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let reminder_uri = format!("{}/{}", uri, relative["id"].as_str().unwrap());
+    let (status, _) = send(&app, Method::DELETE, &reminder_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::DELETE, &reminder_uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+/// Runs the scheduler until nothing is left, including rows left by other tests.
+async fn drain(pool: &DbPool, producer: &EventProducer) {
+    while reminders::run_once(pool, producer).await.unwrap() > 0 {}
+}
+
+async fn sent_at(app: &Router, token: &str, todo: &Value) -> Vec<Value> {
+    let (_, listed) = send(app, Method::GET, &reminders_uri(todo), Some(token), None).await;
+    listed.as_array().unwrap().iter().map(|reminder| reminder["sent_at"].clone()).collect()
+}
+
+#[tokio::test]
+async fn the_scheduler_delivers_each_reminder_once() {
+    let Some(app) = test_app().await else { return };
+    let config = Config::from_env().expect("load config");
+    let pool = db::create_pool(&config.database_url).await.expect("connect to DB");
+    let mut kafka = config.kafka.clone();
+    kafka.enabled = false;
+    let producer = EventProducer::new(kafka).await.expect("disabled producer");
+
+    let (_, token) = register_and_login(&app).await;
+    let todo = create_todo(&app, &token, Some(Utc::now() - Duration::hours(1))).await;
+    let uri = reminders_uri(&todo);
+    send(&app, Method::POST, &uri, Some(&token), Some(json!({ "before_due_secs": 60 }))).await;
+    send(&app, Method::POST, &uri, Some(&token), Some(json!({ "remind_at": Utc::now() + Duration::days(1) }))).await;
+    let todo_id: Uuid = todo["id"].as_str().unwrap().parse().unwrap();
+
+    // A reminder claimed by another instance is left alone
+    let mut other_instance = pool.begin().await.unwrap();
+    sqlx::query("SELECT id FROM todo_reminders WHERE todo_id = $1 FOR UPDATE")
+        .bind(todo_id)
+        .execute(&mut *other_instance)
+        .await
+        .unwrap();
+    drain(&pool, &producer).await;
+    assert_eq!(sent_at(&app, &token, &todo).await, [Value::Null, Value::Null]);
+    other_instance.rollback().await.unwrap();
+
+    drain(&pool, &producer).await;
+    let sent = sent_at(&app, &token, &todo).await;
+    assert!(sent[0].is_string());
+    assert!(sent[1].is_null());
+    let notices: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todo_overdue_notices WHERE todo_id = $1")
+        .bind(todo_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(notices, 1);
+
+    // Nothing is sent twice
+    drain(&pool, &producer).await;
+    assert_eq!(sent_at(&app, &token, &todo).await, sent);
+
+    // Moving the due date re-arms the relative reminder and the overdue notice
+    let new_due = from_now(-Duration::minutes(30));
+    let uri_todo = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    send(&app, Method::PATCH, &uri_todo, Some(&token), Some(json!({ "due_date": new_due }))).await;
+    assert!(sent_at(&app, &token, &todo).await[0].is_null());
+    drain(&pool, &producer).await;
+    assert!(sent_at(&app, &token, &todo).await[0].is_string());
+    let reported: DateTime<Utc> = sqlx::query_scalar("SELECT due_date FROM todo_overdue_notices WHERE todo_id = $1")
+        .bind(todo_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(reported, new_due);
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use axum::{http::{header, Method, StatusCode}, Router};
+use common::{register_and_login, send, send_with_headers, send_with_request_headers, test_app, unique_username};
+use serde_json::{json, Value};
+
+async fn list(app: &Router, token: &str, query: &str) -> Value {
+    let (status, body) = send(app, Method::GET, &format!("/api/todos?{query}"), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    body
+}
+
+fn titles(page: &Value) -> Vec<String> {
+    page["todos"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect()
+}
+
+/// Pages forward through the whole list, checks that paging back from the
+/// last page gives the one before, and returns every title in order.
+async fn walk(app: &Router, token: &str, query: &str) -> Vec<String> {
+    let mut page = list(app, token, query).await;
+    let mut pages = vec![titles(&page)];
+    while let Some(cursor) = page["next_cursor"].as_str() {
+        page = list(app, token, &format!("{query}&cursor={cursor}")).await;
+        pages.push(titles(&page));
+    }
+    let cursor = page["prev_cursor"].as_str().unwrap();
+    let back = list(app, token, &format!("{query}&cursor={cursor}")).await;
+    assert_eq!(titles(&back), pages[pages.len() - 2]);
+    pages.concat()
+}
+
+async fn create(app: &Router, token: &str, body: Value) {
+    let (status, _) = send(app, Method::POST, "/api/todos", Some(token), Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn todos_sort_by_several_keys_with_nulls_last() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let prefix = unique_username();
+    let title = |name: &str| format!("{prefix} {name}");
+
+    create(&app, &token, json!({ "title": title("b"), "priority": 2, "due_date": "2030-01-02T00:00:00Z" })).await;
+    create(&app, &token, json!({ "title": title("a"), "priority": 2, "due_date": "2030-01-02T00:00:00Z" })).await;
+    create(&app, &token, json!({ "title": title("c"), "priority": 3 })).await;
+    create(&app, &token, json!({ "title": title("d"), "due_date": "2030-01-01T00:00:00Z" })).await;
+    create(&app, &token, json!({ "title": title("e"), "priority": 2 })).await;
+
+    let page = list(&app, &token, &format!("search={prefix}&sort=-priority,due_date,title")).await;
+    assert_eq!(titles(&page), ["c", "a", "b", "e", "d"].map(title));
+    let page = list(&app, &token, &format!("search={prefix}&sort=due_date,-title")).await;
+    assert_eq!(titles(&page), ["d", "b", "a", "e", "c"].map(title));
+
+    // Ties on every key fall back to the id, the same way on every request
+    let first = list(&app, &token, &format!("search={prefix}&sort=completed")).await;
+    let again = list(&app, &token, &format!("search={prefix}&sort=completed")).await;
+    assert_eq!(titles(&first), titles(&again));
+
+    for sort in ["secret", "title,-title", "-priority,description"] {
+        let (status, body) = send(&app, Method::GET, &format!("/api/todos?sort={sort}"), Some(&token), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{sort}: {body}");
+    }
+}
+
+#[tokio::test]
+async fn cursors_follow_a_custom_sort_across_null_values() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let prefix = unique_username();
+    let title = |name: &str| format!("{prefix} {name}");
+
+    create(&app, &token, json!({ "title": title("a"), "due_date": "2030-01-03T00:00:00Z" })).await;
+    create(&app, &token, json!({ "title": title("b"), "due_date": "2030-01-01T00:00:00Z" })).await;
+    create(&app, &token, json!({ "title": title("c") })).await;
+    create(&app, &token, json!({ "title": title("d"), "due_date": "2030-01-02T00:00:00Z" })).await;
+    create(&app, &token, json!({ "title": title("e") })).await;
+
+    let query = format!("search={prefix}&sort=due_date&per_page=2");
+    let mut seen = Vec::new();
+    let mut pages = Vec::new();
+    let mut page = list(&app, &token, &query).await;
+    loop {
+        seen.extend(titles(&page));
+        pages.push(titles(&page));
+        let Some(cursor) = page["next_cursor"].as_str() else { break };
+        page = list(&app, &token, &format!("{query}&cursor={cursor}")).await;
+    }
+    assert_eq!(seen[..3], ["b", "d", "a"].map(title));
+    assert_eq!(seen.len(), 5);
+    assert!(seen[3..].contains(&title("c")) && seen[3..].contains(&title("e")));
+
+    // Back from the last page, through the NULL due dates
+    let cursor = page["prev_cursor"].as_str().unwrap();
+    let back = list(&app, &token, &format!("{query}&cursor={cursor}")).await;
+    assert_eq!(titles(&back), pages[1]);
+
+    // A cursor only continues the sort it was issued for, even when the list is unchanged
+    let (_, headers, _) = send_with_headers(&app, Method::GET, "/api/todos", Some(&token), None).await;
+    let etag = headers[header::ETAG].to_str().unwrap();
+    let uri = format!("/api/todos?sort=title&cursor={cursor}");
+    let (status, _, _) =
+        send_with_request_headers(&app, Method::GET, &uri, Some(&token), &[(header::IF_NONE_MATCH, etag)], None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cursors_keep_empty_values_first_or_last_as_asked() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let prefix = unique_username();
+    let title = |name: &str| format!("{prefix} {name}");
+
+    create(&app, &token, json!({ "title": title("a"), "priority": 1 })).await;
+    create(&app, &token, json!({ "title": title("b") })).await;
+    create(&app, &token, json!({ "title": title("c"), "priority": 3 })).await;
+    create(&app, &token, json!({ "title": title("d") })).await;
+    create(&app, &token, json!({ "title": title("e"), "priority": 2 })).await;
+
+    let sorted = |sort: &str| format!("search={prefix}&sort={sort},title&per_page=2");
+    let walked = walk(&app, &token, &sorted("-priority:nulls_first")).await;
+    assert_eq!(walked, ["b", "d", "c", "e", "a"].map(title));
+    let walked = walk(&app, &token, &sorted("priority:nulls_first")).await;
+    assert_eq!(walked, ["b", "d", "a", "e", "c"].map(title));
+    let walked = walk(&app, &token, &sorted("-priority:nulls_last")).await;
+    assert_eq!(walked, ["c", "e", "a", "b", "d"].map(title));
+    let walked = walk(&app, &token, &sorted("priority")).await;
+    assert_eq!(walked, ["a", "e", "c", "b", "d"].map(title));
+
+    for sort in ["title:nulls_first", "due_date:nulls_middle"] {
+        let (status, body) = send(&app, Method::GET, &format!("/api/todos?sort={sort}"), Some(&token), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{sort}: {body}");
+    }
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use axum::{http::{header, Method, StatusCode}, Router};
+use common::{send, send_with_headers, send_with_request_headers, test_app, test_app_with, verified_user};
+use serde_json::{json, Value};
+
+async fn create(app: &Router, token: &str, title: &str) -> String {
+    let (status, todo) = send(app, Method::POST, "/api/todos", Some(token), Some(json!({ "title": title }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    todo["id"].as_str().unwrap().to_string()
+}
+
+async fn etag(app: &Router, token: &str, id: &str) -> String {
+    let (status, headers, todo) = send_with_headers(app, Method::GET, &format!("/api/todos/{id}"), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let etag = headers[header::ETAG].to_str().unwrap().to_string();
+    assert_eq!(etag, format!("\"{}\"", todo["version"]));
+    etag
+}
+
+async fn update(app: &Router, token: &str, id: &str, if_match: &str, body: Value) -> StatusCode {
+    let headers = [(header::IF_MATCH, if_match)];
+    send_with_request_headers(app, Method::PATCH, &format!("/api/todos/{id}"), Some(token), &headers, Some(body)).await.0
+}
+
+#[tokio::test]
+async fn stale_versions_are_refused_with_precondition_failed() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = verified_user(&app).await;
+    let id = create(&app, &token, "Shared").await;
+    let first = etag(&app, &token, &id).await;
+
+    // The first writer wins; the second still holds the old ETag
+    assert_eq!(update(&app, &token, &id, &first, json!({ "title": "Mine" })).await, StatusCode::OK);
+    assert_eq!(update(&app, &token, &id, &first, json!({ "title": "Theirs" })).await, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(update(&app, &token, &id, &format!("W/{first}"), json!({ "title": "Theirs" })).await, StatusCode::PRECONDITION_FAILED);
+    let (_, todo) = send(&app, Method::GET, &format!("/api/todos/{id}"), Some(&token), None).await;
+    assert_eq!(todo["title"], "Mine");
+
+    // Tag changes count as changes too
+    let current = etag(&app, &token, &id).await;
+    let (_, tag) = send(&app, Method::POST, "/api/tags", Some(&token), Some(json!({ "name": "shared" }))).await;
+    send(&app, Method::PUT, &format!("/api/todos/{id}/tags/{}", tag["id"].as_str().unwrap()), Some(&token), None).await;
+    assert_ne!(etag(&app, &token, &id).await, current);
+    assert_eq!(update(&app, &token, &id, "*", json!({ "priority": 1 })).await, StatusCode::OK);
+
+    let version = todo["version"].as_i64().unwrap() + 2;
+    let batch = json!({ "todo_ids": [id], "completed": true, "versions": { &id: version - 1 } });
+    let (status, _) = send(&app, Method::PATCH, "/api/todos/batch", Some(&token), Some(batch)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let batch = json!({ "todo_ids": [id], "completed": true, "versions": { &id: version } });
+    let (status, updated) = send(&app, Method::PATCH, "/api/todos/batch", Some(&token), Some(batch)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated[0]["version"], version + 1);
+
+    let stale = [(header::IF_MATCH, first.as_str())];
+    let uri = format!("/api/todos/{id}");
+    let (status, _, _) = send_with_request_headers(&app, Method::DELETE, &uri, Some(&token), &stale, None).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let current = etag(&app, &token, &id).await;
+    let fresh = [(header::IF_MATCH, current.as_str())];
+    let (status, _, _) = send_with_request_headers(&app, Method::DELETE, &uri, Some(&token), &fresh, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn if_match_can_be_made_mandatory() {
+    let Some(app) = test_app_with(|cfg| cfg.require_if_match = true).await else { return };
+    let (_, token) = verified_user(&app).await;
+    let id = create(&app, &token, "Guarded").await;
+    let uri = format!("/api/todos/{id}");
+
+    let (status, _) = send(&app, Method::PATCH, &uri, Some(&token), Some(json!({ "title": "Blind" }))).await;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+    let (status, _) = send(&app, Method::DELETE, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+    let batch = json!({ "todo_ids": [id], "completed": true });
+    let (status, _) = send(&app, Method::PATCH, "/api/todos/batch", Some(&token), Some(batch)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+
+    let current = etag(&app, &token, &id).await;
+    let if_match = [(header::IF_MATCH, current.as_str())];
+    let body = json!({ "title": "Seen" });
+    let (status, headers, _) = send_with_request_headers(&app, Method::PATCH, &uri, Some(&token), &if_match, Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(headers[header::ETAG], current.as_str());
+
+    // Every other endpoint that changes the todo is guarded the same way
+    let (_, tag) = send(&app, Method::POST, "/api/tags", Some(&token), Some(json!({ "name": "guarded" }))).await;
+    let tag_uri = format!("{uri}/tags/{}", tag["id"].as_str().unwrap());
+    let recurring = json!({ "title": "Daily", "due_date": "2030-01-01T09:00:00Z", "recurrence": { "rrule": "FREQ=DAILY" } });
+    let (_, recurring) = send(&app, Method::POST, "/api/todos", Some(&token), Some(recurring)).await;
+    let recurring_uri = format!("/api/todos/{}", recurring["id"].as_str().unwrap());
+    let guarded = [
+        (Method::PUT, tag_uri.clone(), None),
+        (Method::DELETE, tag_uri.clone(), None),
+        (Method::POST, format!("{uri}/move"), Some(json!({ "parent_id": null }))),
+        (Method::POST, format!("{uri}/revert/1"), None),
+        (Method::POST, format!("{recurring_uri}/skip"), None),
+        (Method::PATCH, format!("{recurring_uri}/series"), Some(json!({ "title": "Nightly" }))),
+    ];
+    for (method, uri, body) in guarded {
+        let (status, _) = send(&app, method, &uri, Some(&token), body).await;
+        assert_eq!(status, StatusCode::PRECONDITION_REQUIRED, "{uri}");
+    }
+
+    let stale = [(header::IF_MATCH, current.as_str())];
+    let revert = format!("{uri}/revert/1");
+    let (status, _, _) = send_with_request_headers(&app, Method::POST, &revert, Some(&token), &stale, None).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let current = etag(&app, &token, &id).await;
+    let fresh = [(header::IF_MATCH, current.as_str())];
+    let (status, _, _) = send_with_request_headers(&app, Method::PUT, &tag_uri, Some(&token), &fresh, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -32,7 +32,8 @@ async fn get_health_ok_on_full_app() {
         }
     };
 
-    let app = routes::create_routes(pool, producer);
+    let state = routes::AppState::new(pool, producer, cfg).expect("app state");
+    let app = routes::create_routes(state);
     let response = app
         .oneshot(Request::get("/health").body(String::new()).unwrap())
         .await
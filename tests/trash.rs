@@ -0,0 +1,107 @@
+mod common;
+
+use axum::{http::{Method, StatusCode}, Router};
+use axum_server::{config::Config, db, jobs::trash_purge};
+use common::{register_and_login, send, test_app, unique_username};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn create(app: &Router, token: &str, uri: &str, body: Value) -> Value {
+    let (status, created) = send(app, Method::POST, uri, Some(token), Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    created
+}
+
+fn id(item: &Value) -> &str {
+    item["id"].as_str().unwrap()
+}
+
+async fn trash(app: &Router, token: &str) -> Value {
+    let (status, trash) = send(app, Method::GET, "/api/trash", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    trash
+}
+
+#[tokio::test]
+async fn deleted_items_can_be_restored_with_their_subtasks_and_tag_links() {
+    let Some(app) = test_app().await else { return };
+    let (_, token) = register_and_login(&app).await;
+    let title = unique_username();
+
+    let parent = create(&app, &token, "/api/todos", json!({ "title": title, "tags": ["home"] })).await;
+    let child = create(&app, &token, "/api/todos", json!({ "title": "Child", "parent_id": parent["id"] })).await;
+    let tag = parent["tags"][0].clone();
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/todos/{}", id(&parent)), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    for todo in [&parent, &child] {
+        let (status, _) = send(&app, Method::GET, &format!("/api/todos/{}", id(todo)), Some(&token), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+    let (_, listed) = send(&app, Method::GET, &format!("/api/todos?search={title}&with_total=true"), Some(&token), None).await;
+    assert_eq!(listed["total"], 0);
+    assert_eq!(trash(&app, &token).await["todos"].as_array().unwrap().len(), 2);
+
+    // A trashed tag's name is free again, but then it cannot be restored
+    send(&app, Method::DELETE, &format!("/api/tags/{}", id(&tag)), Some(&token), None).await;
+    let replacement = create(&app, &token, "/api/tags", json!({ "name": "home" })).await;
+    let restore_tag = format!("/api/trash/tags/{}/restore", id(&tag));
+    let (status, _) = send(&app, Method::POST, &restore_tag, Some(&token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    send(&app, Method::DELETE, &format!("/api/tags/{}", id(&replacement)), Some(&token), None).await;
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/trash/tags/{}", id(&replacement)), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::POST, &restore_tag, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Restoring the todo brings back its subtask and its tag link
+    let (status, restored) = send(&app, Method::POST, &format!("/api/trash/todos/{}/restore", id(&parent)), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(restored["subtask_count"], 1);
+    assert_eq!(restored["tags"][0]["id"], tag["id"]);
+    let (status, _) = send(&app, Method::GET, &format!("/api/todos/{}", id(&child)), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Other users' trash is out of reach
+    let (_, other) = register_and_login(&app).await;
+    send(&app, Method::DELETE, &format!("/api/todos/{}", id(&parent)), Some(&token), None).await;
+    let (status, _) = send(&app, Method::POST, &format!("/api/trash/todos/{}/restore", id(&parent)), Some(&other), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(trash(&app, &other).await["todos"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn items_are_purged_when_deleted_for_good_or_after_retention() {
+    let Some(app) = test_app().await else { return };
+    let config = Config::from_env().expect("load config");
+    let pool = db::create_pool(&config.database_url).await.expect("connect to DB");
+    let (_, token) = register_and_login(&app).await;
+
+    let category = create(&app, &token, "/api/categories", json!({ "name": "Errands" })).await;
+    let old = create(&app, &token, "/api/todos", json!({ "title": "Old", "category_id": category["id"] })).await;
+    let recent = create(&app, &token, "/api/todos", json!({ "title": "Recent" })).await;
+    send(&app, Method::DELETE, &format!("/api/categories/{}", id(&category)), Some(&token), None).await;
+    for todo in [&old, &recent] {
+        send(&app, Method::DELETE, &format!("/api/todos/{}", id(todo)), Some(&token), None).await;
+    }
+
+    // Only items past the retention period are purged
+    let old_id: Uuid = id(&old).parse().unwrap();
+    sqlx::query("UPDATE todos SET deleted_at = NOW() - INTERVAL '2 days' WHERE id = $1")
+        .bind(old_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(trash_purge::run_once(&pool, 86400).await.unwrap() >= 1);
+    let remaining = trash(&app, &token).await;
+    let titles: Vec<&str> = remaining["todos"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Recent"]);
+    assert_eq!(remaining["categories"][0]["name"], "Errands");
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/trash/todos/{}", id(&old)), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, Method::DELETE, "/api/trash", Some(&token), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let emptied = trash(&app, &token).await;
+    assert_eq!(emptied, json!({ "todos": [], "categories": [], "tags": [] }));
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{login, register, send, test_app};
+use serde_json::json;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+fn code_at(secret: &str, time: u64) -> String {
+    let bytes = Secret::Encoded(secret.to_string()).to_bytes().unwrap();
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, bytes, None, "test".to_string())
+        .unwrap()
+        .generate(time)
+}
+
+#[tokio::test]
+async fn login_with_two_factor_requires_a_code() {
+    let Some(app) = test_app().await else { return };
+    let (username, password) = register(&app).await;
+    let session = login(&app, &username, &password).await;
+    let token = session["token"].as_str().unwrap();
+    let now = chrono::Utc::now().timestamp() as u64;
+
+    let (status, enrollment) = send(&app, Method::POST, "/api/auth/2fa/enroll", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let secret = enrollment["secret"].as_str().unwrap().to_string();
+    assert!(enrollment["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/"));
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/2fa/confirm", Some(token), Some(json!({ "code": "000000" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, confirmed) = send(&app, Method::POST, "/api/auth/2fa/confirm", Some(token), Some(json!({ "code": code_at(&secret, now) }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let recovery_codes: Vec<String> = confirmed["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(recovery_codes.len(), 10);
+
+    // The password alone now only yields a challenge
+    let challenge = login(&app, &username, &password).await;
+    assert_eq!(challenge["two_factor_required"], true);
+    assert!(challenge.get("token").is_none());
+    let challenge_token = challenge["challenge_token"].as_str().unwrap();
+
+    // A challenge token is not an access token
+    let (status, _) = send(&app, Method::GET, "/api/todos", Some(challenge_token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/2fa/verify", None, Some(json!({ "challenge_token": challenge_token, "code": "123456" }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The confirmation code cannot be replayed; the next one works
+    let (status, _) = send(&app, Method::POST, "/api/auth/2fa/verify", None, Some(json!({ "challenge_token": challenge_token, "code": code_at(&secret, now) }))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, tokens) = send(&app, Method::POST, "/api/auth/2fa/verify", None, Some(json!({ "challenge_token": challenge_token, "code": code_at(&secret, now + 30) }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tokens["user"]["two_factor_enabled"], true);
+    let (status, _) = send(&app, Method::GET, "/api/todos", tokens["token"].as_str(), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Recovery codes work once
+    let challenge = login(&app, &username, &password).await;
+    let recovery = json!({ "challenge_token": challenge["challenge_token"], "code": recovery_codes[0].to_uppercase() });
+    let (status, _) = send(&app, Method::POST, "/api/auth/2fa/verify", None, Some(recovery.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, Method::POST, "/api/auth/2fa/verify", None, Some(recovery)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(&app, Method::POST, "/api/auth/2fa/disable", Some(token), Some(json!({ "code": recovery_codes[1] }))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let session = login(&app, &username, &password).await;
+    assert!(session["token"].is_string());
+}